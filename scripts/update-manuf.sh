@@ -0,0 +1,28 @@
+#!/bin/sh
+# Replaces the vendor table bundled in src/data/manuf with Wireshark's complete
+# manuf file, which covers the whole IEEE registry (MA-L, MA-M and MA-S).
+# Without network access, pass a manuf file downloaded elsewhere:
+#   scripts/update-manuf.sh path/to/manuf
+set -eu
+
+# a given file is found relative to where the script was started
+source=${1:+$(realpath "$1")}
+cd "$(dirname "$0")/.."
+url=https://www.wireshark.org/download/automated/data/manuf
+if [ -n "$source" ]; then
+    download=$source
+else
+    download=$(mktemp)
+    trap 'rm -f "$download"' EXIT
+    curl --fail --silent --show-error --location "$url" --output "$download"
+fi
+{
+    echo "# Vendors of MAC address prefixes, Wireshark's manuf file as of $(date -u +%Y-%m-%d):"
+    echo "# prefix, short name and full name separated by tabs. Prefixes are 24 bits"
+    echo "# unless a mask follows, e.g. 00:50:C2:00:00:00/36."
+    echo "#"
+    echo "# Regenerate with scripts/update-manuf.sh. A newer manuf or oui.txt dropped"
+    echo "# into the postal directory of the configuration directory overrides it."
+    grep -v '^#' "$download"
+} > src/data/manuf
+echo "Updated src/data/manuf with $(grep -vc '^#' src/data/manuf) prefixes"
@@ -0,0 +1,158 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use iced::Theme;
+
+use crate::data::config::Config;
+use crate::data::display_filter::DisplayFilter;
+use crate::data::export::{self, ExportFormat, Timestamps};
+use crate::data::geoip;
+use crate::data::host_names::HostNames;
+use crate::data::os_network_interface::OSNetworkInterface;
+use crate::data::packet_subscription::PacketSubscription;
+use crate::data::quic::QuicTracker;
+use crate::data::tcp_analysis::TcpTracker;
+use crate::data::time_format::TimeOrigin;
+use crate::{find_interface, NETWORK_INTERFACES};
+
+/// A simple packet sniffer that shows incoming and outgoing traffic on a network interface.
+/// Options given here take precedence over the saved settings.
+#[derive(Parser, Debug, Clone, Default)]
+#[command(version)]
+pub struct Args {
+    /// Network interface to capture on
+    #[arg(short, long)]
+    pub interface: Option<String>,
+    /// Display filter for the packet list, e.g. "tcp.port == 443 && ip.addr == 10.0.0.0/8"
+    #[arg(short = 'Y', long, value_name = "FILTER")]
+    pub display_filter: Option<String>,
+    /// Discard packets not matching this filter while capturing. It uses the display filter
+    /// syntax and is checked by Postal on every frame read, it is not a BPF filter in the kernel
+    #[arg(short = 'f', long, value_name = "FILTER")]
+    pub capture_filter: Option<String>,
+    /// Start capturing right away
+    #[arg(short = 'c', long)]
+    pub capture: bool,
+    /// Open a pcap or pcapng file
+    #[arg(short = 'r', long, value_name = "FILE")]
+    pub read: Option<PathBuf>,
+    /// Color theme, e.g. "Dark" or "Catppuccin Mocha"
+    #[arg(long)]
+    pub theme: Option<String>,
+    /// Maximum number of packets shown in the list
+    #[arg(long, value_name = "PACKETS")]
+    pub view_limit: Option<usize>,
+    /// GeoIP database (.mmdb) or directory of databases, e.g. MaxMind's GeoLite2 City and ASN
+    #[arg(long, value_name = "PATH")]
+    pub geoip: Option<PathBuf>,
+    /// Write the packets of --read that pass the display filter to FILE and exit without
+    /// opening a window. The format follows the extension: .csv, .json, .ndjson, .har or .pcapng
+    #[arg(short = 'w', long, value_name = "FILE", requires = "read")]
+    pub export: Option<PathBuf>,
+}
+
+impl Args {
+    /// Validates the arguments and writes the ones that are also settings into `config`.
+    pub fn apply(&self, config: &mut Config) -> Result<(), String> {
+        if let Some(name) = &self.interface {
+            if !NETWORK_INTERFACES
+                .iter()
+                .any(|i| i.get_identifier() == name)
+            {
+                let available = NETWORK_INTERFACES
+                    .iter()
+                    .map(|i| i.get_identifier())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(format!(
+                    "Unknown network interface '{}'. Available interfaces: {}",
+                    name, available
+                ));
+            }
+            config.interface = Some(name.clone());
+        }
+        if let Some(name) = &self.theme {
+            let theme = Theme::ALL
+                .iter()
+                .find(|t| t.to_string().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("Unknown theme '{}'", name))?;
+            config.theme = theme.to_string();
+        }
+        if let Some(limit) = self.view_limit {
+            config.view_limit = limit;
+        }
+        if let Some(path) = &self.geoip {
+            if !path.exists() {
+                return Err(format!("No GeoIP database at {}", path.display()));
+            }
+            config.geoip = Some(path.clone());
+        }
+        for filter in [&self.display_filter, &self.capture_filter]
+            .into_iter()
+            .flatten()
+        {
+            filter
+                .parse::<DisplayFilter>()
+                .map_err(|e| format!("Invalid filter '{}': {}", filter, e))?;
+        }
+        if let Some(path) = &self.export {
+            ExportFormat::from_path(path).ok_or_else(|| {
+                format!(
+                    "Cannot export to {}, use a .csv, .json, .ndjson, .har or .pcapng file",
+                    path.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Headless export, returns the number of written packets.
+    pub fn export(&self, config: &Config, path: &Path) -> Result<usize, String> {
+        let read = self.read.as_deref().expect("--export requires --read");
+        let interface = find_interface(config.interface.as_deref())
+            .map_or_else(OSNetworkInterface::detached, |i| i.interface.clone());
+        let filter = self
+            .display_filter
+            .as_deref()
+            .map(str::parse::<DisplayFilter>)
+            .transpose()?;
+        for line in geoip::open(config.geoip.as_deref()) {
+            eprintln!("{}", line);
+        }
+        let mut packets = PacketSubscription::read_file(read, &interface)?;
+        let mut tracker = TcpTracker::default();
+        let mut names = HostNames::default();
+        let mut quic = QuicTracker::default();
+        for packet in &mut packets {
+            tracker.analyze(packet);
+            quic.analyze(packet);
+            names.learn(packet);
+        }
+        let matching = packets
+            .iter()
+            .filter(|p| filter.as_ref().is_none_or(|f| f.matches(p, &names)))
+            .collect::<Vec<_>>();
+
+        let format = ExportFormat::from_path(path).unwrap_or_default();
+        let file = File::create(path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        export::write(
+            &mut BufWriter::new(file),
+            format,
+            matching.iter().copied(),
+            &config.columns,
+            Timestamps {
+                format: config.time_format,
+                origin: TimeOrigin {
+                    start: packets.first().map(|p| p.timestamp).unwrap_or_default(),
+                    previous: None,
+                    reference: None,
+                },
+            },
+        )
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        Ok(matching.len())
+    }
+}
@@ -0,0 +1,91 @@
+use iced::{
+    widget::{button, checkbox, column, container, row, Column, TextInput},
+    Alignment, Color, Element, Font, Length, Theme,
+};
+
+use crate::{
+    data::coloring::{ColoringRule, Rgb, RuleEdit},
+    Message, Postal,
+};
+
+use super::{
+    colors::PostalColor,
+    monospace_text::{monospace, monospace_bold},
+    styled_buttons::SubtleButton,
+};
+
+/// Editor for the ordered list of coloring rules.
+pub struct ColoringRules;
+
+impl ColoringRules {
+    pub fn view(app: &Postal) -> Element<'_, Message> {
+        let rules = app
+            .coloring_rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| Self::rule(index, rule));
+        column![
+            monospace("Coloring Rules:"),
+            Column::with_children(rules).spacing(15),
+            button(monospace_bold("Add rule"))
+                .style(SubtleButton::new())
+                .on_press(Message::ColoringRuleAdded),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn rule(index: usize, rule: &ColoringRule) -> Element<'_, Message> {
+        let edit = move |edit: RuleEdit| Message::ColoringRuleEdited(index, edit);
+        let input = |placeholder, value, on_input: fn(String) -> RuleEdit| {
+            TextInput::new(placeholder, value)
+                .on_input(move |text| edit(on_input(text)))
+                .on_submit(Message::ColoringRulesApplied)
+                .font(Font::MONOSPACE)
+                .padding(5)
+        };
+        let action = |label, edit: RuleEdit| {
+            button(monospace_bold(label))
+                .style(SubtleButton::new())
+                .on_press(Message::ColoringRuleEdited(index, edit))
+        };
+
+        let colors = Rgb::parse(&rule.foreground).zip(Rgb::parse(&rule.background));
+        let sample = container(monospace("Sample").size(14))
+            .padding(5)
+            .style(move |_: &Theme| match colors {
+                Some((Rgb(fr, fg, fb), Rgb(br, bg, bb))) => container::Appearance {
+                    text_color: Some(Color::from_rgb8(fr, fg, fb)),
+                    background: Some(Color::from_rgb8(br, bg, bb).into()),
+                    ..Default::default()
+                },
+                None => container::Appearance::default(),
+            });
+
+        let mut editor = column![
+            row![
+                checkbox("", rule.enabled).on_toggle(move |t| edit(RuleEdit::Enabled(t))),
+                input("Name", &rule.name, RuleEdit::Name),
+                action("↑", RuleEdit::MoveUp),
+                action("↓", RuleEdit::MoveDown),
+                action("✕", RuleEdit::Remove),
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center),
+            input("Filter, e.g. tcp.flags.rst", &rule.filter, RuleEdit::Filter),
+            row![
+                input("#rrggbb", &rule.foreground, RuleEdit::Foreground),
+                input("#rrggbb", &rule.background, RuleEdit::Background),
+                sample,
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(5)
+        .width(Length::Fill);
+        if let Some(e) = &rule.error {
+            editor = editor.push(monospace(e.as_str()).size(14).style(PostalColor::ORANGE));
+        }
+        editor.into()
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use iced::{
+    widget::{button, checkbox, column, container, horizontal_rule, row, scrollable, Column, Row},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    data::{
+        column::SortOrder,
+        connections::{self, ConnectionColumn, ConnectionState},
+    },
+    utils::{byte_formatter::format_size, time_formatter::format_seconds},
+    Message, Postal,
+};
+
+use super::{
+    monospace_text::{monospace, monospace_bold},
+    styled_buttons::SubtleButton,
+};
+
+/// Lifecycle of every TCP connection in the capture. A click on a connection filters by it.
+pub struct Connections;
+
+impl Connections {
+    /// Rows built at most, more are slow to lay out.
+    const MAX_ROWS: usize = 1000;
+    const ADDRESS_WIDTH: f32 = 300.0;
+    const PROCESS_WIDTH: f32 = 200.0;
+    const VALUE_WIDTH: f32 = 120.0;
+
+    pub fn view(app: &Postal) -> Element<'_, Message> {
+        let connections = app.connections.sorted(app.connection_sort);
+        let count = |state| connections.iter().filter(|c| c.state() == state).count();
+        let mut summary = format!(
+            "{} connections, {} half-open, {} refused, {} reset",
+            connections.len(),
+            count(ConnectionState::HalfOpen),
+            count(ConnectionState::Refused),
+            count(ConnectionState::ClosedRst),
+        );
+        let shown = connections
+            .iter()
+            .filter(|connection| !app.failed_connections_only || connection.state().is_failed())
+            .count();
+        if shown > Self::MAX_ROWS {
+            summary.push_str(&format!(", first {} shown", Self::MAX_ROWS));
+        }
+        let width = |column| match column {
+            ConnectionColumn::Client | ConnectionColumn::Server => {
+                Length::Fixed(Self::ADDRESS_WIDTH)
+            }
+            ConnectionColumn::Process => Length::Fixed(Self::PROCESS_WIDTH),
+            _ => Length::Fixed(Self::VALUE_WIDTH),
+        };
+
+        let header = Row::with_children(ConnectionColumn::ALL.into_iter().map(|column| {
+            let marker = match app.connection_sort {
+                (sorted, SortOrder::Ascending) if sorted == column => " ▲",
+                (sorted, SortOrder::Descending) if sorted == column => " ▼",
+                _ => "",
+            };
+            button(monospace_bold(format!("{}{}", column, marker)).width(width(column)))
+                .style(SubtleButton::new())
+                .padding(0)
+                .on_press(Message::ConnectionSortToggled(column))
+                .into()
+        }))
+        .padding([0, 5]);
+
+        let start = app.capture_start.unwrap_or_default();
+        let seconds = |duration: Option<Duration>| {
+            duration.map_or(String::from("-"), |d| format!("{:.6}", d.as_secs_f64()))
+        };
+        let rows = connections
+            .into_iter()
+            .filter(|connection| !app.failed_connections_only || connection.state().is_failed())
+            .take(Self::MAX_ROWS)
+            .map(|connection| {
+                let cells = ConnectionColumn::ALL.into_iter().map(|column| {
+                    let text = match column {
+                        ConnectionColumn::Client => connection.client.to_string(),
+                        ConnectionColumn::Server => connection.server.to_string(),
+                        ConnectionColumn::Process => connection
+                            .process
+                            .as_ref()
+                            .map_or(String::from("-"), |p| p.to_string()),
+                        ConnectionColumn::State => connection.state().to_string(),
+                        ConnectionColumn::Start => format_seconds(start, connection.first_seen),
+                        ConnectionColumn::Duration => seconds(Some(connection.duration())),
+                        ConnectionColumn::SynAck => seconds(connection.syn_ack_delay()),
+                        ConnectionColumn::Handshake => seconds(connection.handshake()),
+                        ConnectionColumn::Packets => connection.packets.to_string(),
+                        ConnectionColumn::ClientBytes => format_size(connection.client_bytes),
+                        ConnectionColumn::ServerBytes => format_size(connection.server_bytes),
+                        ConnectionColumn::Mss => {
+                            let mss = |options: Option<connections::SynOptions>| {
+                                options
+                                    .and_then(|o| o.mss)
+                                    .map_or(String::from("-"), |mss| mss.to_string())
+                            };
+                            format!(
+                                "{}/{}",
+                                mss(connection.client_options),
+                                mss(connection.server_options)
+                            )
+                        }
+                        ConnectionColumn::WindowScale => match connection.window_scale() {
+                            Some((client, server)) => format!("{}/{}", client, server),
+                            None if connection.sack().is_some() => String::from("none"),
+                            None => String::from("-"),
+                        },
+                        ConnectionColumn::Sack => match connection.sack() {
+                            Some(true) => String::from("yes"),
+                            Some(false) => String::from("no"),
+                            None => String::from("-"),
+                        },
+                        ConnectionColumn::Anomalies => connection.anomalies.to_string(),
+                    };
+                    monospace(text).width(width(column)).into()
+                });
+                button(Row::with_children(cells))
+                    .style(SubtleButton::new())
+                    .padding(5)
+                    .on_press(Message::FilterSelected(connection.filter()))
+                    .into()
+            });
+
+        container(
+            column![
+                row![
+                    monospace_bold("TCP Connections").size(20),
+                    checkbox("Only half-open and refused", app.failed_connections_only)
+                        .on_toggle(Message::FailedConnectionsToggled),
+                    monospace(summary),
+                ]
+                .spacing(20)
+                .align_items(Alignment::Center),
+                scrollable(
+                    column![
+                        header,
+                        horizontal_rule(1),
+                        Column::with_children(rows).width(Length::Shrink)
+                    ]
+                    .spacing(10)
+                )
+                .direction(scrollable::Direction::Both {
+                    vertical: scrollable::Properties::default(),
+                    horizontal: scrollable::Properties::default(),
+                }),
+            ]
+            .spacing(10),
+        )
+        .padding(20)
+        .width(Length::FillPortion(5))
+        .height(Length::Fill)
+        .into()
+    }
+}
@@ -0,0 +1,127 @@
+use iced::{
+    widget::{button, column, container, horizontal_rule, pick_list, row, scrollable, Column, Row},
+    Alignment, Element, Font, Length,
+};
+
+use crate::{
+    data::{
+        column::SortOrder,
+        endpoints::{EndpointColumn, EndpointKind},
+    },
+    utils::{byte_formatter::format_size, time_formatter::format_seconds},
+    Message, Postal,
+};
+
+use super::{
+    monospace_text::{monospace, monospace_bold},
+    styled_buttons::SubtleButton,
+};
+
+/// Traffic per address, ranked by the selected column. A click on an endpoint filters by it.
+pub struct Endpoints;
+
+impl Endpoints {
+    /// Rows built at most, the ranking beyond is rarely of interest and slow to lay out.
+    const MAX_ROWS: usize = 1000;
+    const ADDRESS_WIDTH: f32 = 300.0;
+    const VALUE_WIDTH: f32 = 120.0;
+    const LOCATION_WIDTH: f32 = 240.0;
+
+    pub fn view(app: &Postal) -> Element<'_, Message> {
+        let endpoints = app.endpoints.sorted(app.endpoint_sort, &app.host_names);
+        let summary = match endpoints.len() {
+            n if n > Self::MAX_ROWS => format!("{} endpoints, top {} shown", n, Self::MAX_ROWS),
+            n => format!("{} endpoints", n),
+        };
+        let width = |column| match column {
+            EndpointColumn::Address | EndpointColumn::AutonomousSystem => {
+                Length::Fixed(Self::ADDRESS_WIDTH)
+            }
+            EndpointColumn::Location => Length::Fixed(Self::LOCATION_WIDTH),
+            _ => Length::Fixed(Self::VALUE_WIDTH),
+        };
+
+        let header = Row::with_children(EndpointColumn::ALL.into_iter().map(|column| {
+            let marker = match app.endpoint_sort {
+                (sorted, SortOrder::Ascending) if sorted == column => " ▲",
+                (sorted, SortOrder::Descending) if sorted == column => " ▼",
+                _ => "",
+            };
+            button(monospace_bold(format!("{}{}", column, marker)).width(width(column)))
+                .style(SubtleButton::new())
+                .padding(0)
+                .on_press(Message::EndpointSortToggled(column))
+                .into()
+        }))
+        .padding([0, 5]);
+
+        let start = app.capture_start.unwrap_or_default();
+        let rows = endpoints
+            .into_iter()
+            .take(Self::MAX_ROWS)
+            .map(|(label, endpoint)| {
+                let cells = EndpointColumn::ALL.into_iter().map(|column| {
+                    let text = match column {
+                        EndpointColumn::Address => label.clone(),
+                        EndpointColumn::Packets => endpoint.packets().to_string(),
+                        EndpointColumn::Bytes => format_size(endpoint.bytes()),
+                        EndpointColumn::TxPackets => endpoint.tx_packets.to_string(),
+                        EndpointColumn::TxBytes => format_size(endpoint.tx_bytes),
+                        EndpointColumn::RxPackets => endpoint.rx_packets.to_string(),
+                        EndpointColumn::RxBytes => format_size(endpoint.rx_bytes),
+                        EndpointColumn::Inbound => format_size(endpoint.inbound_bytes),
+                        EndpointColumn::Outbound => format_size(endpoint.outbound_bytes),
+                        EndpointColumn::FirstSeen => format_seconds(start, endpoint.first_seen),
+                        EndpointColumn::LastSeen => format_seconds(start, endpoint.last_seen),
+                        EndpointColumn::Location => {
+                            endpoint.geo().map(|g| g.place()).unwrap_or_default()
+                        }
+                        EndpointColumn::AutonomousSystem => endpoint
+                            .geo()
+                            .map(|g| g.autonomous_system())
+                            .unwrap_or_default(),
+                    };
+                    monospace(text).width(width(column)).into()
+                });
+                button(Row::with_children(cells))
+                    .style(SubtleButton::new())
+                    .padding(5)
+                    .on_press(Message::FilterSelected(endpoint.filter()))
+                    .into()
+            });
+
+        container(
+            column![
+                row![
+                    monospace_bold("Endpoints").size(20),
+                    pick_list(
+                        EndpointKind::ALL,
+                        Some(app.endpoints.kind()),
+                        Message::EndpointKindSelected
+                    )
+                    .font(Font::MONOSPACE),
+                    monospace(summary),
+                ]
+                .spacing(20)
+                .align_items(Alignment::Center),
+                scrollable(
+                    column![
+                        header,
+                        horizontal_rule(1),
+                        Column::with_children(rows).width(Length::Shrink)
+                    ]
+                    .spacing(10)
+                )
+                .direction(scrollable::Direction::Both {
+                    vertical: scrollable::Properties::default(),
+                    horizontal: scrollable::Properties::default(),
+                }),
+            ]
+            .spacing(10),
+        )
+        .padding(20)
+        .width(Length::FillPortion(5))
+        .height(Length::Fill)
+        .into()
+    }
+}
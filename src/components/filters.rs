@@ -1,18 +1,22 @@
 use iced::{
     widget::{
-        checkbox, column, container, horizontal_rule, horizontal_space, row, Column, TextInput,
+        checkbox, column, container, horizontal_rule, horizontal_space, pick_list, row, Column,
+        TextInput,
     },
     Alignment, Element, Font, Length,
 };
 
-use crate::{Message, Postal};
+use crate::{
+    data::{direction::Direction, port_filter::PortMatch},
+    Message, Postal,
+};
 
 use super::monospace_text::{monospace, monospace_bold};
 
 pub struct Filters;
 
 impl<'a> Filters {
-    pub fn view(app: &'a Postal) -> Element<Message> {
+    pub fn view(app: &'a Postal) -> Element<'a, Message> {
         let filter_header = container(
             monospace_bold("Protocols")
                 .size(20)
@@ -27,6 +31,14 @@ impl<'a> Filters {
             .font(Font::MONOSPACE)
             .padding(10);
 
+        let port_match_picker = pick_list(
+            PortMatch::ALL,
+            Some(app.port_match),
+            Message::PortMatchSelected,
+        )
+        .font(Font::MONOSPACE)
+        .width(Length::Fill);
+
         let type_rows = app
             .tp_types
             .iter()
@@ -39,15 +51,33 @@ impl<'a> Filters {
             })
             .collect::<Vec<_>>();
 
+        let direction_rows = Direction::ALL
+            .iter()
+            .map(|&direction| {
+                let cb = checkbox("", app.directions[&direction])
+                    .font(Font::MONOSPACE)
+                    .on_toggle(move |t| Message::DirectionFilterChanged(direction, t));
+                row![monospace(direction.to_string()), horizontal_space(), cb].into()
+            })
+            .collect::<Vec<_>>();
+
         let types_container = container(
             Column::with_children(type_rows)
                 .spacing(10)
                 .padding(10)
                 .width(Length::Fill)
                 .align_items(Alignment::Start)
-                .push(column![monospace("Ports:"), port_input]),
+                .push(column![monospace("Ports:"), port_input, port_match_picker].spacing(5))
+                .push(monospace("Directions:"))
+                .push(Column::with_children(direction_rows).spacing(10))
+                .push(row![
+                    monospace("Marked only"),
+                    horizontal_space(),
+                    checkbox("", app.marked_only)
+                        .font(Font::MONOSPACE)
+                        .on_toggle(Message::MarkedOnlyToggled)
+                ]),
         )
-        .height(Length::Fill)
         .width(Length::FillPortion(1));
 
         column![filter_header, horizontal_rule(1), types_container,].into()
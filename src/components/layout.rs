@@ -1,13 +1,22 @@
 use iced::{
-    widget::{button, column, container, horizontal_space, row, vertical_rule, Button},
-    Alignment, Element, Length, Theme,
+    widget::{button, column, container, horizontal_space, pick_list, row, vertical_rule, Button},
+    Alignment, Element, Font, Length, Theme,
 };
 
-use crate::{utils::byte_formatter::format_size, Message, Postal};
+use crate::{
+    data::{export::ExportFormat, main_view::MainView, shortcut::Shortcut},
+    utils::byte_formatter::format_size,
+    Message, Postal,
+};
 
 use super::{
+    connections::Connections,
+    endpoints::Endpoints,
     monospace_text::{monospace, monospace_bold},
     packet_list::PacketList,
+    protocol_hierarchy::ProtocolHierarchy,
+    quic_connections::QuicConnections,
+    shortcut_help::ShortcutHelp,
     sidebar::Sidebar,
     styled_buttons::SubtleButton,
 };
@@ -15,7 +24,7 @@ use super::{
 pub struct Layout {}
 
 impl Layout {
-    pub fn view(app: &Postal) -> Element<Message> {
+    pub fn view(app: &Postal) -> Element<'_, Message> {
         let sniff_btn: Button<_> = if !app.capturing {
             button(monospace_bold("Capture!").size(20))
                 .style(SubtleButton::new())
@@ -25,10 +34,51 @@ impl Layout {
                 .style(SubtleButton::new())
                 .on_press(Message::StopSniffing)
         };
+        let freeze_btn = if app.is_frozen() {
+            button(monospace_bold(format!("Resume ({} new)", app.pending)).size(20))
+        } else {
+            button(monospace_bold("Freeze").size(20))
+        }
+        .style(SubtleButton::new())
+        .on_press(Message::FreezeToggled);
+        let disk_status = app.disk_status.as_ref().map(|status| {
+            let status = status.lock().unwrap();
+            match &status.error {
+                Some(e) => e.clone(),
+                None => format!(
+                    "Writing {} ({})\n{} written in total",
+                    status
+                        .file
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    format_size(status.file_bytes as usize),
+                    format_size(status.total_bytes as usize)
+                ),
+            }
+        });
+        let reference_btn = button(monospace_bold("Time Reference").size(20))
+            .style(SubtleButton::new())
+            .on_press_maybe(app.selected.map(|_| Message::TimeReferenceToggled));
         let footer = row![
             button(monospace_bold("Clear").size(20))
                 .style(SubtleButton::new())
                 .on_press(Message::ClearCache),
+            freeze_btn,
+            reference_btn,
+            row![
+                pick_list(
+                    ExportFormat::ALL,
+                    Some(app.export_format),
+                    Message::ExportFormatSelected
+                )
+                .font(Font::MONOSPACE),
+                button(monospace_bold("Export").size(20))
+                    .style(SubtleButton::new())
+                    .on_press(Message::ExportRequested),
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center),
             horizontal_space(),
             monospace(format!(
                 "Packets captured: {}\nTotal size: {}",
@@ -36,14 +86,32 @@ impl Layout {
                 format_size(app.total_mem)
             ))
             .size(16),
+            monospace(disk_status.unwrap_or_default()).size(16),
+            monospace(app.status.as_deref().unwrap_or_default()).size(16),
             horizontal_space(),
+            pick_list(
+                MainView::ALL,
+                Some(app.main_view),
+                Message::MainViewSelected
+            )
+            .font(Font::MONOSPACE),
+            button(monospace_bold("?").size(20))
+                .style(SubtleButton::new())
+                .on_press(Message::ShortcutPressed(Shortcut::Help)),
             sniff_btn,
         ]
         .spacing(20)
         .align_items(Alignment::Center);
 
-        let sidebar = Sidebar::view(&app);
-        let packet_list = PacketList::view(&app);
+        let sidebar = Sidebar::view(app);
+        let packet_list = match app.main_view {
+            _ if app.show_help => ShortcutHelp::view(),
+            MainView::Packets => PacketList::view(app),
+            MainView::ProtocolHierarchy => ProtocolHierarchy::view(app),
+            MainView::Endpoints => Endpoints::view(app),
+            MainView::Connections => Connections::view(app),
+            MainView::QuicConnections => QuicConnections::view(app),
+        };
         let main = container(row![sidebar, vertical_rule(1), packet_list])
             .style(|theme: &Theme| {
                 let palette = theme.extended_palette();
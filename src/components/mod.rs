@@ -1,11 +1,18 @@
 pub mod colored_container;
+pub mod coloring_rules;
 pub mod colors;
+pub mod connections;
+pub mod endpoints;
 pub mod filters;
 pub mod layout;
 pub mod monospace_text;
 pub mod packet;
+pub mod packet_details;
 pub mod packet_list;
+pub mod protocol_hierarchy;
+pub mod quic_connections;
 pub mod settings;
+pub mod shortcut_help;
 pub mod sidebar;
 pub mod solid_tooltip;
 pub mod styled_buttons;
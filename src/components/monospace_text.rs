@@ -2,8 +2,10 @@ use std::borrow::Cow;
 
 use iced::widget::Text;
 
+#[allow(dead_code)]
 pub struct CustomFont;
 
+#[allow(dead_code)]
 impl CustomFont {
     pub const BOLD: iced::Font = iced::Font {
         family: iced::font::Family::Monospace,
@@ -1,66 +1,108 @@
 use core::str;
 
 use iced::{
-    widget::{row, Tooltip},
-    Element, Length,
+    widget::{container, mouse_area, Row, Tooltip},
+    Alignment, Border, Color, Element, Length, Theme,
 };
 use pnet::packet::Packet;
 
 use crate::{
-    data::parsed_packet::{NetworkPacket, ParsedPacket, TransportPacket},
-    utils::byte_formatter::format_size,
+    data::{
+        coloring::Rgb,
+        column::{ColumnLayout, Field},
+        host_names::HostNames,
+        parsed_packet::{ParsedPacket, TransportPacket},
+    },
     Message,
 };
 
 use super::{
+    colored_container::ColoredContainer,
     colors::PostalColor,
     monospace_text::{monospace, monospace_bold},
+    packet_list::PacketList,
     solid_tooltip::SolidTooltip,
 };
 
 impl ParsedPacket {
-    pub fn view(&self, inbound: bool, relative_widths: &[u16]) -> Element<Message> {
-        let size = self.data.len();
-        let (port, protocol, payload) = match &self.transport {
+    /// A row of the packet list, `time` is the Timestamp column in the chosen time format
+    /// and `colors` the foreground and background of the matching coloring rule.
+    /// Rows the search matches are outlined.
+    pub fn view(
+        &self,
+        columns: &ColumnLayout,
+        selected: bool,
+        matched: bool,
+        time: String,
+        colors: Option<(Rgb, Rgb)>,
+        names: Option<&HostNames>,
+    ) -> Element<'_, Message> {
+        let mut time = Some(time);
+        let cells = columns.visible().map(|(_, column)| {
+            let width = Length::Fixed(column.width + PacketList::HANDLE_WIDTH);
+            match column.field {
+                Field::Payload => self.inspect(width),
+                Field::Timestamp => monospace_bold(time.take().unwrap_or_default())
+                    .width(width)
+                    .into(),
+                field => {
+                    let text = monospace_bold(field.text(self, names)).width(width);
+                    match field_color(field) {
+                        Some(color) if colors.is_none() => text.style(color).into(),
+                        _ => text.into(),
+                    }
+                }
+            }
+        });
+
+        let row = container(
+            Row::with_children(cells)
+                .width(Length::Fill)
+                .height(PacketList::ROW_HEIGHT)
+                .align_items(Alignment::Center),
+        );
+        let row = if selected {
+            row.style(ColoredContainer::new())
+        } else if colors.is_some() || matched {
+            row.style(move |_: &Theme| container::Appearance {
+                text_color: colors.map(|(foreground, _)| to_color(foreground)),
+                background: colors.map(|(_, background)| to_color(background).into()),
+                border: if matched {
+                    Border {
+                        color: PostalColor::ORANGE,
+                        width: 2.0,
+                        radius: 4.0.into(),
+                    }
+                } else {
+                    Border::default()
+                },
+                ..Default::default()
+            })
+        } else {
+            row
+        };
+
+        mouse_area(row)
+            .on_press(Message::RowClicked(self.number))
+            .on_right_press(Message::MarkToggled(self.number))
+            .into()
+    }
+
+    fn inspect(&self, width: Length) -> Element<'_, Message> {
+        let (is_http, payload) = match &self.transport {
             TransportPacket::Tcp(tcp) => (
-                tcp.get_destination(),
-                "TCP".to_string(),
+                tcp.get_source() == 80 || tcp.get_destination() == 80,
                 str::from_utf8(tcp.payload()).unwrap_or(""),
             ),
             TransportPacket::Udp(udp) => (
-                udp.get_destination(),
-                "UDP".to_string(),
+                udp.get_source() == 80 || udp.get_destination() == 80,
                 str::from_utf8(udp.payload()).unwrap_or(""),
             ),
-            TransportPacket::Other => (0, "OTHER".to_string(), ""),
-        };
-        let (source, dest) = match &self.net {
-            NetworkPacket::Ipv4(v4) => (
-                v4.get_source().to_string(),
-                v4.get_destination().to_string(),
-            ),
-            NetworkPacket::Ipv6(v6) => (
-                v6.get_source().to_string(),
-                v6.get_destination().to_string(),
-            ),
-            NetworkPacket::Other => (String::new(), String::new()),
+            TransportPacket::Other => (false, ""),
         };
-
-        let dir = if inbound {
-            "Receiving".to_string()
-        } else {
-            "Sending".to_string()
-        };
-        let timestamp_text = monospace_bold(&self.timestring);
-        let dir_text = monospace_bold(dir);
-        let protocol_text = monospace_bold(protocol).style(PostalColor::MATTBLUE);
-        let port_text = monospace_bold(format!(":{port}")).style(PostalColor::ORANGE);
-        let source_text = monospace_bold(source).style(PostalColor::MINT);
-        let destination_text = monospace_bold(dest).style(PostalColor::PURPLE);
-        let size_text = monospace_bold(format_size(size));
-        let inspect: Element<Message> = if port == 80 && !payload.is_empty() {
+        if is_http && !payload.is_empty() {
             Tooltip::new(
-                monospace_bold("Inspect 💬").width(Length::FillPortion(relative_widths[7])),
+                monospace_bold("Inspect 💬").width(width),
                 payload,
                 iced::widget::tooltip::Position::Left,
             )
@@ -69,22 +111,21 @@ impl ParsedPacket {
             .style(SolidTooltip::new())
             .into()
         } else {
-            monospace("")
-                .width(Length::FillPortion(relative_widths[7]))
-                .into()
-        };
+            monospace("").width(width).into()
+        }
+    }
+}
+
+fn to_color(Rgb(r, g, b): Rgb) -> Color {
+    Color::from_rgb8(r, g, b)
+}
 
-        row![
-            timestamp_text.width(Length::FillPortion(relative_widths[0])),
-            dir_text.width(Length::FillPortion(relative_widths[1])),
-            protocol_text.width(Length::FillPortion(relative_widths[2])),
-            port_text.width(Length::FillPortion(relative_widths[3])),
-            source_text.width(Length::FillPortion(relative_widths[4])),
-            destination_text.width(Length::FillPortion(relative_widths[5])),
-            size_text.width(Length::FillPortion(relative_widths[6])),
-            inspect
-        ]
-        .width(Length::Fill)
-        .into()
+fn field_color(field: Field) -> Option<Color> {
+    match field {
+        Field::Protocol => Some(PostalColor::MATTBLUE),
+        Field::SourcePort | Field::DestinationPort => Some(PostalColor::ORANGE),
+        Field::SourceIp | Field::SourceMac => Some(PostalColor::MINT),
+        Field::DestinationIp | Field::DestinationMac => Some(PostalColor::PURPLE),
+        _ => None,
     }
 }
@@ -0,0 +1,124 @@
+use std::ops::Range;
+
+use iced::{
+    widget::{
+        button, column, container, row, scrollable, text, vertical_rule, Column, Row, Text,
+        TextInput,
+    },
+    Alignment, Element, Font, Length, Theme,
+};
+
+use crate::{
+    data::{packet_details::hex_dump, parsed_packet::ParsedPacket},
+    Message, Postal,
+};
+
+use super::{colors::PostalColor, monospace_text::monospace, styled_buttons::SubtleButton};
+
+/// Dissection tree and hex dump of the selected packet.
+pub struct PacketDetails;
+
+impl PacketDetails {
+    pub fn view<'a>(app: &'a Postal, packet: &'a ParsedPacket) -> Element<'a, Message> {
+        let tree = packet.details(&app.host_names).into_iter().map(|section| {
+            let expanded = !app.collapsed_details.contains(section.id);
+            let marker = if expanded { "▼" } else { "▶" };
+            let header = button(monospace(format!("{} {}", marker, section.summary)).size(14))
+                .style(SubtleButton::new())
+                .padding(2)
+                .on_press(Message::DetailToggled(section.id));
+            let fields = section
+                .fields
+                .into_iter()
+                .filter(|_| expanded)
+                .map(|field| monospace(field).size(14).into());
+            column![header, Column::with_children(fields).padding([0, 0, 0, 24])].into()
+        });
+
+        let found = app.search.as_ref().and_then(|s| s.find(&packet.data));
+        let hex = hex_dump(&packet.data)
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let bytes = i * 16..(i * 16 + 16).min(packet.data.len());
+                match &found {
+                    Some(found) if found.start < bytes.end && bytes.start < found.end => {
+                        let start = found.start.max(bytes.start) - bytes.start;
+                        let end = found.end.min(bytes.end) - bytes.start;
+                        highlighted_line(line, start..end)
+                    }
+                    _ => dump_text(line).into(),
+                }
+            });
+
+        let annotation = row![
+            button(monospace(if packet.marked { "Unmark" } else { "Mark" }).size(14))
+                .style(SubtleButton::new())
+                .on_press(Message::MarkToggled(packet.number)),
+            TextInput::new(
+                "Packet comment",
+                packet.comment.as_deref().unwrap_or_default()
+            )
+            .on_input(Message::CommentChanged)
+            .size(14)
+            .padding(5),
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+        container(row![
+            scrollable(
+                column![annotation, Column::with_children(tree).spacing(2)]
+                    .spacing(5)
+                    .padding([0, 10, 0, 0])
+                    .width(Length::Fill)
+            )
+            .width(Length::FillPortion(3)),
+            vertical_rule(1),
+            scrollable(
+                Column::with_children(hex)
+                    .padding([0, 10])
+                    .width(Length::Shrink)
+            )
+            .width(Length::FillPortion(2)),
+        ])
+        .padding(10)
+        .width(Length::Fill)
+        .height(Length::FillPortion(2))
+        .into()
+    }
+}
+
+fn dump_text<'a>(content: String) -> Text<'a> {
+    text(content).font(Font::MONOSPACE).size(14)
+}
+
+/// A hex dump line with the bytes in `bytes` marked in both the hex and the text column.
+fn highlighted_line<'a>(line: String, bytes: Range<usize>) -> Element<'a, Message> {
+    // the offset is followed by two spaces, the hex column by two more
+    let hex_start = line.find("  ").map_or(0, |i| i + 2);
+    let text_start = hex_start + 16 * 3 + 1;
+    let cuts = [
+        0,
+        hex_start + bytes.start * 3,
+        hex_start + bytes.end * 3 - 1,
+        text_start + bytes.start,
+        text_start + bytes.end,
+        line.len(),
+    ];
+    let segments = cuts.windows(2).enumerate().map(|(i, cut)| {
+        let segment = dump_text(line.get(cut[0]..cut[1]).unwrap_or_default().to_string());
+        if i % 2 == 1 {
+            container(segment)
+                .style(|_: &Theme| container::Appearance {
+                    background: Some(PostalColor::ORANGE.into()),
+                    text_color: Some(iced::Color::BLACK),
+                    ..Default::default()
+                })
+                .into()
+        } else {
+            segment.into()
+        }
+    });
+    Row::with_children(segments).into()
+}
@@ -1,73 +1,70 @@
-use iced::widget::{column, container, horizontal_rule, row, scrollable, Column};
-use iced::{Alignment, Element, Length};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_rule, mouse_area, pick_list, row, scrollable,
+    vertical_rule, Column, Row, Space, TextInput,
+};
+use iced::{mouse, Alignment, Element, Font, Length};
 
-use crate::{Message, Postal, SCROLLABLE_ID};
+use crate::data::coloring;
+use crate::data::column::{self as packet_column, ColumnDrag, SortOrder};
+use crate::data::postal_option::PostalOption;
+use crate::data::search::SearchMode;
+use crate::{Message, Postal, FILTER_INPUT_ID, SCROLLABLE_ID, SEARCH_INPUT_ID};
 
-use super::monospace_text::monospace_bold;
+use super::colored_container::ColoredContainer;
+use super::colors::PostalColor;
+use super::monospace_text::{monospace, monospace_bold};
+use super::packet_details::PacketDetails;
+use super::styled_buttons::SubtleButton;
 
 pub struct PacketList {}
 
 impl PacketList {
-    pub fn view(app: &Postal) -> Element<Message> {
-        let relative_widths = [2, 1, 1, 1, 3, 3, 1, 1];
-        let header = row![
-            monospace_bold("Timestamp")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[0])),
-            monospace_bold("Direction")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[1])),
-            monospace_bold("Protocol")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[2])),
-            monospace_bold("Port")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[3])),
-            monospace_bold("Source IP")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[4])),
-            monospace_bold("Destination IP")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[5])),
-            monospace_bold("Size")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[6])),
-            monospace_bold("Payload")
-                .size(16)
-                .width(Length::FillPortion(relative_widths[7])),
-        ]
+    /// Width of the drag handle between two header cells.
+    pub const HANDLE_WIDTH: f32 = 6.0;
+    /// Rows have a fixed height so the visible range can be derived from the scroll offset.
+    pub const ROW_HEIGHT: f32 = 28.0;
+
+    pub fn view(app: &Postal) -> Element<'_, Message> {
+        let header = Row::with_children(
+            app.columns
+                .visible()
+                .map(|(index, column)| Self::header_cell(app, index, column)),
+        )
         .width(Length::Fill)
         .padding(10);
 
-        let elem = app
-            .packets
-            .iter()
-            .filter(|p| {
-                app.tp_types[&p.transport]
-                    && match p.get_port() {
-                        Some(port) => app.port_list.contains(&port) || app.port_list.is_empty(),
-                        None => false,
-                    }
-            })
-            .rev()
-            .take(app.cache_size)
-            .map(|p| {
-                p.view(
-                    app.network_interface
-                        .interface
-                        .ips
-                        .iter()
-                        .any(|nw| nw.ip() == p.get_source_ip().unwrap()),
-                    &relative_widths,
-                )
-            })
+        // Only the rows inside the viewport are built, the rest is replaced by spacers
+        // of the same height to keep the scrollbar proportional.
+        let total = app.index.len(app.cache_size);
+        let first = ((app.scroll_offset / Self::ROW_HEIGHT) as usize).min(total);
+        let visible = (app.window_size.height / Self::ROW_HEIGHT).ceil() as usize + 1;
+        let last = (first + visible).min(total);
 
-            .collect::<Vec<Element<Message>>>();
+        let rows = (first..last)
+            .filter_map(|row| Some((row, app.index.row(row, app.cache_size, &app.columns)?)))
+            .map(|(row, i)| {
+                let packet = &app.packets[i];
+                let selected = app.selected == Some(packet.number);
+                let colors = coloring::colors(&app.coloring_rules, packet, &app.host_names);
+                let matched = app.search.as_ref().is_some_and(|s| s.matches(packet));
+                packet.view(
+                    &app.columns,
+                    selected,
+                    matched,
+                    app.time_text(row),
+                    colors,
+                    app.options[&PostalOption::ResolveNames]
+                        .0
+                        .then_some(&app.host_names),
+                )
+            });
 
         let packet_list = container(
             scrollable(
-                Column::with_children(elem)
-                    .spacing(10)
+                Column::new()
+                    .push(Space::with_height(first as f32 * Self::ROW_HEIGHT))
+                    .extend(rows)
+                    .push(Space::with_height((total - last) as f32 * Self::ROW_HEIGHT))
                     .align_items(Alignment::Start)
                     .width(Length::Fill),
             )
@@ -76,10 +73,131 @@ impl PacketList {
             .height(Length::Fill),
         )
         .width(Length::Fill)
+        .height(Length::FillPortion(3))
         .padding(10);
 
-        column![header, horizontal_rule(1), packet_list]
-            .width(Length::FillPortion(5))
-            .into()
+        let filter_input = TextInput::new(
+            "Display filter, e.g. tcp.port == 443 && ip.addr == 10.0.0.0/8",
+            &app.filter_input,
+        )
+        .id(FILTER_INPUT_ID.clone())
+        .on_input(Message::FilterInputChanged)
+        .on_submit(Message::DisplayFilterApplied)
+        .font(Font::MONOSPACE)
+        .padding(10);
+        let goto_input = TextInput::new("Go to packet", &app.goto_input)
+            .on_input(Message::GotoInputChanged)
+            .on_submit(Message::GotoSubmitted)
+            .font(Font::MONOSPACE)
+            .padding(10)
+            .width(160);
+        let filter_row = row![filter_input, goto_input].spacing(10);
+        let filter_bar = match &app.filter_error {
+            Some(e) => column![filter_row, monospace(e).style(PostalColor::ORANGE)],
+            None => column![filter_row],
+        }
+        .spacing(5)
+        .padding(10);
+
+        let search_bar = app.search_open.then(|| Self::search_bar(app));
+        let list = Column::new()
+            .push_maybe(search_bar)
+            .push(filter_bar)
+            .push(header)
+            .push(horizontal_rule(1))
+            .push(packet_list);
+        match app.selected_packet() {
+            Some(packet) => list
+                .push(horizontal_rule(1))
+                .push(PacketDetails::view(app, packet)),
+            None => list,
+        }
+        .width(Length::FillPortion(5))
+        .into()
+    }
+
+    fn search_bar(app: &Postal) -> Element<'_, Message> {
+        let input = TextInput::new("Search packet bytes", &app.search_input)
+            .id(SEARCH_INPUT_ID.clone())
+            .on_input(Message::SearchInputChanged)
+            .on_submit(Message::SearchNext)
+            .font(Font::MONOSPACE)
+            .padding(10);
+        let navigation = |label, message| {
+            button(monospace_bold(label))
+                .style(SubtleButton::new())
+                .on_press_maybe(app.search.is_some().then_some(message))
+        };
+        let controls = row![
+            input,
+            pick_list(
+                SearchMode::ALL,
+                Some(app.search_mode),
+                Message::SearchModeSelected
+            ),
+            checkbox("Match case", app.search_case_sensitive).on_toggle_maybe(
+                (app.search_mode != SearchMode::Hex).then_some(Message::SearchCaseToggled)
+            ),
+            checkbox("Only matches", app.search_only_matches)
+                .on_toggle(Message::SearchOnlyMatchesToggled),
+            navigation("▲", Message::SearchPrevious),
+            navigation("▼", Message::SearchNext),
+            button(monospace_bold("✕"))
+                .style(SubtleButton::new())
+                .on_press(Message::SearchClosed),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        match &app.search_error {
+            Some(e) => column![controls, monospace(e).style(PostalColor::ORANGE)],
+            None => column![controls],
+        }
+        .spacing(5)
+        .padding([10, 10, 0, 10])
+        .into()
+    }
+
+    fn header_cell<'a>(
+        app: &'a Postal,
+        index: usize,
+        column: &'a packet_column::Column,
+    ) -> Element<'a, Message> {
+        let sort_marker = match app.columns.sort {
+            Some((field, SortOrder::Ascending)) if field == column.field => " ▲",
+            Some((field, SortOrder::Descending)) if field == column.field => " ▼",
+            _ => "",
+        };
+        let title = container(
+            monospace_bold(format!("{}{}", column.field, sort_marker))
+                .size(16)
+                .width(Length::Fixed(column.width)),
+        );
+        let is_drop_target = matches!(
+            app.column_drag,
+            Some(ColumnDrag::Move { from, to }) if to == index && from != index
+        );
+        let title = if is_drop_target {
+            title.style(ColoredContainer::new())
+        } else {
+            title
+        };
+
+        let handle = mouse_area(
+            container(vertical_rule(1))
+                .width(Length::Fixed(Self::HANDLE_WIDTH))
+                .height(Length::Fixed(20.0))
+                .center_x(),
+        )
+        .on_press(Message::ColumnResizeStarted(index))
+        .interaction(mouse::Interaction::ResizingHorizontally);
+
+        row![
+            mouse_area(title)
+                .on_press(Message::ColumnPressed(index))
+                .on_enter(Message::ColumnHovered(index))
+                .interaction(mouse::Interaction::Pointer),
+            handle
+        ]
+        .into()
     }
 }
@@ -0,0 +1,70 @@
+use iced::{
+    widget::{button, column, container, horizontal_rule, row, scrollable, Column},
+    Element, Length,
+};
+
+use crate::{utils::byte_formatter::format_size, Message, Postal};
+
+use super::{
+    monospace_text::{monospace, monospace_bold},
+    styled_buttons::SubtleButton,
+};
+
+/// Protocol tree of the whole capture, a click on a protocol filters the list by it.
+pub struct ProtocolHierarchy;
+
+impl ProtocolHierarchy {
+    const NAME_WIDTH: f32 = 320.0;
+    const VALUE_WIDTH: f32 = 140.0;
+
+    pub fn view(app: &Postal) -> Element<'_, Message> {
+        let root = &app.protocols;
+        let percent = |part: usize, total: usize| {
+            format!("{:.1}%", part as f64 * 100.0 / total.max(1) as f64)
+        };
+        let cell = |text: String| monospace(text).width(Length::Fixed(Self::VALUE_WIDTH));
+
+        let header = row![
+            monospace_bold("Protocol").width(Length::Fixed(Self::NAME_WIDTH)),
+            monospace_bold("Packets").width(Length::Fixed(Self::VALUE_WIDTH)),
+            monospace_bold("% Packets").width(Length::Fixed(Self::VALUE_WIDTH)),
+            monospace_bold("Bytes").width(Length::Fixed(Self::VALUE_WIDTH)),
+            monospace_bold("% Bytes").width(Length::Fixed(Self::VALUE_WIDTH)),
+        ]
+        .padding([0, 5]);
+
+        let rows = root.flatten().into_iter().map(|(depth, node)| {
+            let name = format!("{}{}", "  ".repeat(depth), node.name);
+            button(row![
+                monospace(name).width(Length::Fixed(Self::NAME_WIDTH)),
+                cell(node.packets.to_string()),
+                cell(percent(node.packets, root.packets)),
+                cell(format_size(node.bytes)),
+                cell(percent(node.bytes, root.bytes)),
+            ])
+            .style(SubtleButton::new())
+            .padding(5)
+            .on_press(Message::FilterSelected(node.filter.clone()))
+            .into()
+        });
+
+        container(
+            column![
+                monospace_bold(format!(
+                    "{} packets, {} in total",
+                    root.packets,
+                    format_size(root.bytes)
+                ))
+                .size(20),
+                header,
+                horizontal_rule(1),
+                scrollable(Column::with_children(rows).width(Length::Fill)),
+            ]
+            .spacing(10),
+        )
+        .padding(20)
+        .width(Length::FillPortion(5))
+        .height(Length::Fill)
+        .into()
+    }
+}
@@ -0,0 +1,125 @@
+use iced::{
+    widget::{button, column, container, horizontal_rule, row, scrollable, Column, Row},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    data::{column::SortOrder, quic, quic_connections::QuicColumn},
+    utils::{byte_formatter::format_size, time_formatter::format_seconds},
+    Message, Postal,
+};
+
+use super::{
+    monospace_text::{monospace, monospace_bold},
+    styled_buttons::SubtleButton,
+};
+
+/// QUIC connections grouped by their connection IDs. A click on a connection filters by it.
+pub struct QuicConnections;
+
+impl QuicConnections {
+    const ADDRESS_WIDTH: f32 = 300.0;
+    const NAME_WIDTH: f32 = 240.0;
+    const ID_WIDTH: f32 = 360.0;
+    const VALUE_WIDTH: f32 = 120.0;
+
+    pub fn view(app: &Postal) -> Element<'_, Message> {
+        let connections = app.quic_connections.sorted(app.quic_sort);
+        let named = connections
+            .iter()
+            .filter(|c| c.server_name().is_some())
+            .count();
+        let summary = format!(
+            "{} connections, {} with a known server name",
+            connections.len(),
+            named
+        );
+        let width = |column| match column {
+            QuicColumn::Client | QuicColumn::Server => Length::Fixed(Self::ADDRESS_WIDTH),
+            QuicColumn::Process | QuicColumn::ServerName => Length::Fixed(Self::NAME_WIDTH),
+            QuicColumn::ConnectionIds => Length::Fixed(Self::ID_WIDTH),
+            _ => Length::Fixed(Self::VALUE_WIDTH),
+        };
+
+        let header = Row::with_children(QuicColumn::ALL.into_iter().map(|column| {
+            let marker = match app.quic_sort {
+                (sorted, SortOrder::Ascending) if sorted == column => " ▲",
+                (sorted, SortOrder::Descending) if sorted == column => " ▼",
+                _ => "",
+            };
+            button(monospace_bold(format!("{}{}", column, marker)).width(width(column)))
+                .style(SubtleButton::new())
+                .padding(0)
+                .on_press(Message::QuicSortToggled(column))
+                .into()
+        }))
+        .padding([0, 5]);
+
+        let start = app.capture_start.unwrap_or_default();
+        let rows = connections.into_iter().map(|connection| {
+            let cells = QuicColumn::ALL.into_iter().map(|column| {
+                let text = match column {
+                    QuicColumn::Client => connection.client.to_string(),
+                    QuicColumn::Server => connection.server.to_string(),
+                    QuicColumn::Process => connection
+                        .process
+                        .as_ref()
+                        .map_or(String::from("-"), |p| p.to_string()),
+                    QuicColumn::Version => connection
+                        .version
+                        .map_or(String::from("-"), quic::version_name),
+                    QuicColumn::ServerName => connection.server_name().unwrap_or("-").to_string(),
+                    QuicColumn::Alpn => connection.alpn(),
+                    QuicColumn::ConnectionIds => connection
+                        .connection_ids
+                        .iter()
+                        .map(|id| quic::hex(id))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    QuicColumn::Start => format_seconds(start, connection.first_seen),
+                    QuicColumn::Duration => {
+                        format!("{:.6}", connection.duration().as_secs_f64())
+                    }
+                    QuicColumn::Packets => connection.packets.to_string(),
+                    QuicColumn::ClientBytes => format_size(connection.client_bytes),
+                    QuicColumn::ServerBytes => format_size(connection.server_bytes),
+                    QuicColumn::Migrations => connection.migrations.to_string(),
+                };
+                monospace(text).width(width(column)).into()
+            });
+            button(Row::with_children(cells))
+                .style(SubtleButton::new())
+                .padding(5)
+                .on_press(Message::FilterSelected(connection.filter()))
+                .into()
+        });
+
+        container(
+            column![
+                row![
+                    monospace_bold("QUIC Connections").size(20),
+                    monospace(summary),
+                ]
+                .spacing(20)
+                .align_items(Alignment::Center),
+                scrollable(
+                    column![
+                        header,
+                        horizontal_rule(1),
+                        Column::with_children(rows).width(Length::Shrink)
+                    ]
+                    .spacing(10)
+                )
+                .direction(scrollable::Direction::Both {
+                    vertical: scrollable::Properties::default(),
+                    horizontal: scrollable::Properties::default(),
+                }),
+            ]
+            .spacing(10),
+        )
+        .padding(20)
+        .width(Length::FillPortion(5))
+        .height(Length::Fill)
+        .into()
+    }
+}
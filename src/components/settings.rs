@@ -6,9 +6,14 @@ use iced::{
     Alignment, Element, Font, Length, Theme,
 };
 
-use crate::{Message, Postal, NETWORK_INTERFACES};
+use crate::{
+    data::{ring_buffer::RingBufferField, time_format::TimeFormat},
+    Message, Postal, NETWORK_INTERFACES,
+};
 
 use super::{
+    coloring_rules::ColoringRules,
+    colors::PostalColor,
     monospace_text::{monospace, monospace_bold},
     solid_tooltip::SolidTooltip,
 };
@@ -16,7 +21,7 @@ use super::{
 pub struct Settings;
 
 impl<'a> Settings {
-    pub fn view(app: &'a Postal) -> Element<Message> {
+    pub fn view(app: &'a Postal) -> Element<'a, Message> {
         let setting_header = container(
             monospace_bold("Settings")
                 .size(20)
@@ -30,7 +35,7 @@ impl<'a> Settings {
                 .iter()
                 .map(|int| int.get_identifier().to_string())
                 .collect::<Vec<String>>(),
-            Some(app.network_interface.to_string()),
+            app.network_interface.as_ref().map(ToString::to_string),
             Message::NetworkInterfaceSelected,
         )
         .font(Font::MONOSPACE)
@@ -42,6 +47,26 @@ impl<'a> Settings {
             .font(Font::MONOSPACE)
             .padding(10);
 
+        let capture_filter_input = TextInput::new(
+            "Display filter syntax, applied to the next capture",
+            &app.capture_filter_input,
+        )
+        .on_input(Message::CaptureFilterInputChanged)
+        .on_submit(Message::CaptureFilterApplied)
+        .font(Font::MONOSPACE)
+        .padding(10);
+        let capture_filter = match &app.capture_filter_error {
+            Some(e) => column![
+                monospace("Capture Filter (userspace):"),
+                capture_filter_input,
+                monospace(e).style(PostalColor::ORANGE)
+            ],
+            None => column![
+                monospace("Capture Filter (userspace):"),
+                capture_filter_input
+            ],
+        };
+
         let opt_rows = app
             .options
             .iter()
@@ -65,6 +90,34 @@ impl<'a> Settings {
             })
             .collect::<Vec<_>>();
 
+        let ring_buffer_inputs = RingBufferField::ALL
+            .into_iter()
+            .map(|field| {
+                column![
+                    monospace(field.to_string()),
+                    TextInput::new("0 = unlimited", &app.ring_buffer_inputs[&field])
+                        .on_input(move |input| Message::RingBufferInputChanged(field, input))
+                        .on_submit(Message::RingBufferApplied(field))
+                        .font(Font::MONOSPACE)
+                        .padding(10)
+                ]
+                .into()
+            })
+            .collect::<Vec<_>>();
+
+        let column_rows = app
+            .columns
+            .columns
+            .iter()
+            .map(|column| {
+                let field = column.field;
+                let cb = checkbox("", column.visible)
+                    .font(Font::MONOSPACE)
+                    .on_toggle(move |t| Message::ColumnToggled(field, t));
+                row![monospace(field.to_string()), horizontal_space(), cb].into()
+            })
+            .collect::<Vec<_>>();
+
         let settings_container = container(
             Column::with_children(opt_rows)
                 .spacing(10)
@@ -72,13 +125,29 @@ impl<'a> Settings {
                 .width(Length::Fill)
                 .align_items(Alignment::Start)
                 .push(column![monospace("View Limit:"), window_input])
+                .push(capture_filter)
                 .push(column![monospace("Network Interface:"), interface_picker])
+                .push(column![
+                    monospace("Time Format:"),
+                    pick_list(
+                        TimeFormat::ALL,
+                        Some(app.time_format),
+                        Message::TimeFormatSelected
+                    )
+                    .font(Font::MONOSPACE)
+                    .width(Length::Fill)
+                ])
                 .push(column![
                     monospace("Theme:"),
                     pick_list(Theme::ALL, Some(&app.theme), Message::ThemeSelected)
                         .font(Font::MONOSPACE)
                         .width(Length::Fill)
-                ]),
+                ])
+                .push(monospace("Capture to Disk:"))
+                .push(Column::with_children(ring_buffer_inputs).spacing(10))
+                .push(ColoringRules::view(app))
+                .push(monospace("Columns:"))
+                .push(Column::with_children(column_rows).spacing(10)),
         )
         .width(Length::FillPortion(1))
         .center_y();
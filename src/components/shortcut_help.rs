@@ -0,0 +1,41 @@
+use iced::{
+    widget::{button, column, container, row, Column},
+    Element, Length,
+};
+
+use crate::{data::shortcut::Shortcut, Message};
+
+use super::{
+    monospace_text::{monospace, monospace_bold},
+    styled_buttons::SubtleButton,
+};
+
+/// Overview of the keyboard shortcuts, shown in place of the packet list.
+pub struct ShortcutHelp;
+
+impl ShortcutHelp {
+    pub fn view<'a>() -> Element<'a, Message> {
+        let shortcuts = Shortcut::ALL.iter().map(|shortcut| {
+            row![
+                monospace_bold(shortcut.keys()).width(Length::Fixed(140.0)),
+                monospace(shortcut.description()),
+            ]
+            .into()
+        });
+        container(
+            column![
+                monospace_bold("Keyboard Shortcuts").size(24),
+                Column::with_children(shortcuts).spacing(8),
+                button(monospace_bold("Close"))
+                    .style(SubtleButton::new())
+                    .on_press(Message::ShortcutPressed(Shortcut::Help)),
+            ]
+            .spacing(20),
+        )
+        .width(Length::FillPortion(5))
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{Element, Message, Postal};
-use iced::widget::column;
+use iced::widget::{column, scrollable};
+use iced::Length;
 
 use super::filters::Filters;
 use super::settings::Settings;
@@ -8,6 +9,8 @@ pub struct Sidebar;
 
 impl<'a> Sidebar {
     pub fn view(app: &'a Postal) -> Element<'a, Message> {
-        column![Settings::view(app), Filters::view(app)].into()
+        scrollable(column![Settings::view(app), Filters::view(app)])
+            .width(Length::FillPortion(1))
+            .into()
     }
 }
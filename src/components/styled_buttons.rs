@@ -28,8 +28,10 @@ impl button::StyleSheet for SubtleButton {
     }
 }
 
+#[allow(dead_code)]
 pub struct PayloadButton;
 
+#[allow(dead_code)]
 impl PayloadButton {
     pub fn new() -> iced::theme::Button {
         iced::theme::Button::Custom(Box::new(Self))
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+
+use super::display_filter::DisplayFilter;
+use super::host_names::HostNames;
+use super::parsed_packet::ParsedPacket;
+
+/// A color as `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub fn parse(input: &str) -> Option<Self> {
+        let hex = input.trim().strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+/// Colors the rows of packets matching `filter`, the first matching rule wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColoringRule {
+    pub enabled: bool,
+    pub name: String,
+    pub filter: String,
+    pub foreground: String,
+    pub background: String,
+    #[serde(skip)]
+    compiled: Option<DisplayFilter>,
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+impl ColoringRule {
+    pub fn new(name: &str, filter: &str, foreground: &str, background: &str) -> Self {
+        let mut rule = Self {
+            enabled: true,
+            name: name.to_string(),
+            filter: filter.to_string(),
+            foreground: foreground.to_string(),
+            background: background.to_string(),
+            compiled: None,
+            error: None,
+        };
+        rule.compile();
+        rule
+    }
+
+    /// Parses the filter and colors after they were edited or loaded.
+    /// A rule without a filter, like a freshly added one, matches nothing.
+    pub fn compile(&mut self) {
+        self.compiled = None;
+        self.error = if self.filter.trim().is_empty() {
+            None
+        } else {
+            match self.filter.parse::<DisplayFilter>() {
+                Ok(filter) => {
+                    self.compiled = Some(filter);
+                    None
+                }
+                Err(e) => Some(e),
+            }
+        };
+        for color in [&self.foreground, &self.background] {
+            if Rgb::parse(color).is_none() {
+                self.error = Some(format!("'{}' is not a color like #a40000", color));
+            }
+        }
+    }
+
+    fn matches(&self, packet: &ParsedPacket, names: &HostNames) -> bool {
+        self.enabled
+            && self.error.is_none()
+            && self
+                .compiled
+                .as_ref()
+                .is_some_and(|f| f.matches(packet, names))
+    }
+
+    /// Default rules after Wireshark's: bad TCP, resets, ICMP errors and DNS.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            ColoringRule::new(
+                "TCP retransmission",
+                "tcp.analysis.retransmission",
+                "#000000",
+                "#ffd966",
+            ),
+            ColoringRule::new("TCP RST", "tcp.flags.rst", "#fffc9c", "#a40000"),
+            ColoringRule::new(
+                "ICMP errors",
+                "icmp.type == 3 || icmp.type == 4 || icmp.type == 5 || icmp.type == 11 \
+                 || icmp.type == 12 || (icmpv6.type >= 1 && icmpv6.type <= 4)",
+                "#b7f774",
+                "#000000",
+            ),
+            ColoringRule::new("DNS", "dns", "#12272e", "#daeeff"),
+        ]
+    }
+}
+
+/// Marked packets are white on black regardless of the rules.
+const MARKED: (Rgb, Rgb) = (Rgb(0xff, 0xff, 0xff), Rgb(0x00, 0x00, 0x00));
+
+/// Foreground and background of the first matching rule.
+pub fn colors(
+    rules: &[ColoringRule],
+    packet: &ParsedPacket,
+    names: &HostNames,
+) -> Option<(Rgb, Rgb)> {
+    if packet.marked {
+        return Some(MARKED);
+    }
+    let rule = rules.iter().find(|rule| rule.matches(packet, names))?;
+    Some((Rgb::parse(&rule.foreground)?, Rgb::parse(&rule.background)?))
+}
+
+/// A change made in the rule editor.
+#[derive(Debug, Clone)]
+pub enum RuleEdit {
+    Enabled(bool),
+    Name(String),
+    Filter(String),
+    Foreground(String),
+    Background(String),
+    MoveUp,
+    MoveDown,
+    Remove,
+}
+
+/// Applies `edit` to the rule at `index` and recompiles it. Returns whether the change
+/// is complete and should be saved, text being typed is saved once it is submitted.
+pub fn edit(rules: &mut Vec<ColoringRule>, index: usize, edit: RuleEdit) -> bool {
+    if index >= rules.len() {
+        return false;
+    }
+    let toggled = matches!(edit, RuleEdit::Enabled(_));
+    let rule = &mut rules[index];
+    match edit {
+        RuleEdit::Enabled(enabled) => rule.enabled = enabled,
+        RuleEdit::Name(name) => rule.name = name,
+        RuleEdit::Filter(filter) => rule.filter = filter,
+        RuleEdit::Foreground(color) => rule.foreground = color,
+        RuleEdit::Background(color) => rule.background = color,
+        RuleEdit::MoveUp if index > 0 => {
+            rules.swap(index, index - 1);
+            return true;
+        }
+        RuleEdit::MoveDown if index + 1 < rules.len() => {
+            rules.swap(index, index + 1);
+            return true;
+        }
+        RuleEdit::MoveUp | RuleEdit::MoveDown => return false,
+        RuleEdit::Remove => {
+            rules.remove(index);
+            return true;
+        }
+    }
+    rule.compile();
+    toggled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_packets::{self, Segment};
+
+    #[test]
+    fn default_rules_compile_and_match() {
+        let rules = ColoringRule::defaults();
+        for rule in &rules {
+            assert_eq!(rule.error, None, "{}", rule.name);
+            assert!(rule.compiled.is_some(), "{}", rule.name);
+        }
+        let names = HostNames::default();
+        let rst = test_packets::tcp(
+            "10.0.0.1:1000",
+            "10.0.0.2:80",
+            Segment {
+                flags: 0x04,
+                ..Segment::default()
+            },
+        );
+        assert_eq!(
+            colors(&rules, &rst, &names),
+            Some((Rgb(0xff, 0xfc, 0x9c), Rgb(0xa4, 0x00, 0x00)))
+        );
+        let other = test_packets::udp("10.0.0.1:1000", "10.0.0.2:1234", b"hello");
+        assert_eq!(colors(&rules, &other, &names), None);
+    }
+
+    #[test]
+    fn edits_recompile_only_the_edited_rule() {
+        let mut rules = ColoringRule::defaults();
+        let dns = rules[3].clone();
+
+        assert!(!edit(
+            &mut rules,
+            0,
+            RuleEdit::Filter("tcp.flags.".to_string())
+        ));
+        assert!(rules[0].error.is_some());
+        assert!(rules[0].compiled.is_none());
+        assert!(rules[1].error.is_none());
+
+        assert!(!edit(
+            &mut rules,
+            1,
+            RuleEdit::Background("#a400".to_string())
+        ));
+        assert!(rules[1].error.is_some());
+        assert!(!edit(
+            &mut rules,
+            1,
+            RuleEdit::Background("#a40000".to_string())
+        ));
+        assert!(rules[1].error.is_none());
+
+        assert!(edit(&mut rules, 2, RuleEdit::Enabled(false)));
+        assert!(!rules[2].enabled);
+
+        assert!(edit(&mut rules, 3, RuleEdit::MoveUp));
+        assert_eq!(rules[2].name, dns.name);
+        assert!(!edit(&mut rules, 0, RuleEdit::MoveUp));
+        assert!(!edit(&mut rules, 3, RuleEdit::MoveDown));
+        assert!(edit(&mut rules, 2, RuleEdit::Remove));
+        assert_eq!(rules.len(), 3);
+        assert!(rules.iter().all(|rule| rule.name != dns.name));
+        assert!(!edit(&mut rules, 3, RuleEdit::Remove));
+    }
+}
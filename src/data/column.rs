@@ -0,0 +1,284 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::time_formatter::format_local_time;
+
+use super::geoip;
+use super::host_names::HostNames;
+use super::oui;
+use super::parsed_packet::ParsedPacket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Field {
+    Number,
+    Timestamp,
+    Direction,
+    Protocol,
+    SourceMac,
+    DestinationMac,
+    SourceIp,
+    SourcePort,
+    DestinationIp,
+    DestinationPort,
+    Ttl,
+    TcpFlags,
+    Size,
+    Info,
+    Payload,
+    Process,
+    Pid,
+    SourceLocation,
+    DestinationLocation,
+    SourceAs,
+    DestinationAs,
+}
+
+impl Field {
+    pub const ALL: [Field; 21] = [
+        Field::Number,
+        Field::Timestamp,
+        Field::Direction,
+        Field::Protocol,
+        Field::SourceMac,
+        Field::DestinationMac,
+        Field::SourceIp,
+        Field::SourcePort,
+        Field::DestinationIp,
+        Field::DestinationPort,
+        Field::Ttl,
+        Field::TcpFlags,
+        Field::Size,
+        Field::Info,
+        Field::Payload,
+        Field::Process,
+        Field::Pid,
+        Field::SourceLocation,
+        Field::DestinationLocation,
+        Field::SourceAs,
+        Field::DestinationAs,
+    ];
+
+    fn default_width(&self) -> f32 {
+        match self {
+            Field::Number => 70.0,
+            Field::Timestamp => 220.0,
+            Field::Direction => 100.0,
+            Field::Protocol => 80.0,
+            Field::SourceMac | Field::DestinationMac => 160.0,
+            Field::SourceIp | Field::DestinationIp => 240.0,
+            Field::SourcePort | Field::DestinationPort => 90.0,
+            Field::Ttl => 50.0,
+            Field::TcpFlags => 110.0,
+            Field::Size => 90.0,
+            Field::Info => 360.0,
+            Field::Payload => 110.0,
+            Field::Process => 160.0,
+            Field::Pid => 80.0,
+            Field::SourceLocation | Field::DestinationLocation => 180.0,
+            Field::SourceAs | Field::DestinationAs => 260.0,
+        }
+    }
+
+    fn visible_by_default(&self) -> bool {
+        !matches!(
+            self,
+            Field::SourceMac
+                | Field::DestinationMac
+                | Field::Ttl
+                | Field::TcpFlags
+                | Field::Info
+                | Field::Process
+                | Field::Pid
+                | Field::SourceLocation
+                | Field::DestinationLocation
+                | Field::SourceAs
+                | Field::DestinationAs
+        )
+    }
+
+    /// Cell content of this column for the given packet, with host names in place of
+    /// known IP addresses if `names` are given.
+    /// The payload column is rendered as a tooltip and has no text of its own.
+    pub fn text(&self, packet: &ParsedPacket, names: Option<&HostNames>) -> String {
+        let or_empty = |v: Option<String>| v.unwrap_or_default();
+        let ip = |ip: Option<IpAddr>| {
+            ip.map(|ip| {
+                names
+                    .and_then(|names| names.lookup(ip))
+                    .map_or_else(|| ip.to_string(), str::to_string)
+            })
+        };
+        let geo = |ip: Option<IpAddr>| ip.and_then(geoip::lookup);
+        match self {
+            Field::Number => packet.number.to_string(),
+            Field::Timestamp => format_local_time(packet.timestamp),
+            Field::Direction => packet.direction.to_string(),
+            Field::Protocol => packet.transport.to_string(),
+            Field::SourceMac => oui::resolve(packet.eth.get_source()),
+            Field::DestinationMac => oui::resolve(packet.eth.get_destination()),
+            Field::SourceIp => or_empty(ip(packet.get_source_ip())),
+            Field::SourcePort => or_empty(packet.get_source_port().map(|p| format!(":{p}"))),
+            Field::DestinationIp => or_empty(ip(packet.get_destination_ip())),
+            Field::DestinationPort => {
+                or_empty(packet.get_destination_port().map(|p| format!(":{p}")))
+            }
+            Field::Ttl => or_empty(packet.get_ttl().map(|ttl| ttl.to_string())),
+            Field::TcpFlags => or_empty(packet.get_tcp_flags()),
+            Field::Size => crate::utils::byte_formatter::format_size(packet.data.len()),
+            Field::Info => packet.info(),
+            Field::Payload => String::new(),
+            Field::Process => or_empty(packet.process.as_ref().map(|p| p.name.clone())),
+            Field::Pid => or_empty(packet.process.as_ref().map(|p| p.pid.to_string())),
+            Field::SourceLocation => or_empty(geo(packet.get_source_ip()).map(|g| g.short_place())),
+            Field::DestinationLocation => {
+                or_empty(geo(packet.get_destination_ip()).map(|g| g.short_place()))
+            }
+            Field::SourceAs => or_empty(geo(packet.get_source_ip()).map(|g| g.autonomous_system())),
+            Field::DestinationAs => {
+                or_empty(geo(packet.get_destination_ip()).map(|g| g.autonomous_system()))
+            }
+        }
+    }
+
+    pub fn compare(&self, a: &ParsedPacket, b: &ParsedPacket) -> Ordering {
+        match self {
+            Field::Number => a.number.cmp(&b.number),
+            Field::Timestamp => a.timestamp.cmp(&b.timestamp),
+            Field::SourceIp => a.get_source_ip().cmp(&b.get_source_ip()),
+            Field::DestinationIp => a.get_destination_ip().cmp(&b.get_destination_ip()),
+            Field::SourcePort => a.get_source_port().cmp(&b.get_source_port()),
+            Field::DestinationPort => a.get_destination_port().cmp(&b.get_destination_port()),
+            Field::Ttl => a.get_ttl().cmp(&b.get_ttl()),
+            Field::Size | Field::Payload => a.data.len().cmp(&b.data.len()),
+            Field::Pid => {
+                let pid = |p: &ParsedPacket| p.process.as_ref().map(|p| p.pid);
+                pid(a).cmp(&pid(b))
+            }
+            _ => self.text(a, None).cmp(&self.text(b, None)),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Number => write!(f, "No."),
+            Field::Timestamp => write!(f, "Timestamp"),
+            Field::Direction => write!(f, "Direction"),
+            Field::Protocol => write!(f, "Protocol"),
+            Field::SourceMac => write!(f, "Source MAC"),
+            Field::DestinationMac => write!(f, "Destination MAC"),
+            Field::SourceIp => write!(f, "Source IP"),
+            Field::SourcePort => write!(f, "Src Port"),
+            Field::DestinationIp => write!(f, "Destination IP"),
+            Field::DestinationPort => write!(f, "Dst Port"),
+            Field::Ttl => write!(f, "TTL"),
+            Field::TcpFlags => write!(f, "TCP Flags"),
+            Field::Size => write!(f, "Size"),
+            Field::Info => write!(f, "Info"),
+            Field::Payload => write!(f, "Payload"),
+            Field::Process => write!(f, "Process"),
+            Field::Pid => write!(f, "PID"),
+            Field::SourceLocation => write!(f, "Src Location"),
+            Field::DestinationLocation => write!(f, "Dst Location"),
+            Field::SourceAs => write!(f, "Src AS"),
+            Field::DestinationAs => write!(f, "Dst AS"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Column {
+    pub field: Field,
+    pub width: f32,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnLayout {
+    pub columns: Vec<Column>,
+    pub sort: Option<(Field, SortOrder)>,
+}
+
+impl Default for ColumnLayout {
+    fn default() -> Self {
+        Self {
+            columns: Field::ALL
+                .iter()
+                .map(|&field| Column {
+                    field,
+                    width: field.default_width(),
+                    visible: field.visible_by_default(),
+                })
+                .collect(),
+            sort: None,
+        }
+    }
+}
+
+impl ColumnLayout {
+    const MIN_WIDTH: f32 = 40.0;
+
+    /// Visible columns together with their index into `columns`.
+    pub fn visible(&self) -> impl Iterator<Item = (usize, &Column)> {
+        self.columns.iter().enumerate().filter(|(_, c)| c.visible)
+    }
+
+    pub fn set_visible(&mut self, field: Field, visible: bool) {
+        if let Some(column) = self.columns.iter_mut().find(|c| c.field == field) {
+            column.visible = visible;
+        }
+    }
+
+    pub fn resize(&mut self, index: usize, delta: f32) {
+        if let Some(column) = self.columns.get_mut(index) {
+            column.width = (column.width + delta).max(Self::MIN_WIDTH);
+        }
+    }
+
+    pub fn move_column(&mut self, from: usize, to: usize) {
+        if from < self.columns.len() && to < self.columns.len() {
+            let column = self.columns.remove(from);
+            self.columns.insert(to, column);
+        }
+    }
+
+    /// Cycles a column through ascending, descending and unsorted (newest first).
+    pub fn toggle_sort(&mut self, field: Field) {
+        self.sort = match self.sort {
+            Some((f, SortOrder::Ascending)) if f == field => Some((field, SortOrder::Descending)),
+            Some((f, SortOrder::Descending)) if f == field => None,
+            _ => Some((field, SortOrder::Ascending)),
+        };
+    }
+
+    /// Layouts written by older versions may lack newer fields, append them hidden.
+    pub fn normalize(&mut self) {
+        for field in Field::ALL {
+            if !self.columns.iter().any(|c| c.field == field) {
+                self.columns.push(Column {
+                    field,
+                    width: field.default_width(),
+                    visible: false,
+                });
+            }
+        }
+    }
+}
+
+/// A drag gesture on the packet list header that is still in progress.
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnDrag {
+    Resize { index: usize, last_x: Option<f32> },
+    Move { from: usize, to: usize },
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::coloring::ColoringRule;
+use super::column::ColumnLayout;
+use super::direction::Direction;
+use super::port_filter::PortMatch;
+use super::postal_option::PostalOption;
+use super::ring_buffer::RingBufferConfig;
+use super::time_format::TimeFormat;
+
+/// User settings that survive a restart, stored as TOML in the platform config directory
+/// (`$XDG_CONFIG_HOME/postal/config.toml` on Linux).
+/// Every field has a default, so files written by older versions still load and
+/// missing entries simply fall back to the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub theme: String,
+    pub interface: Option<String>,
+    pub options: HashMap<PostalOption, bool>,
+    /// Transport protocol checkboxes, keyed by their display name.
+    pub protocols: HashMap<String, bool>,
+    pub directions: HashMap<Direction, bool>,
+    pub ports: Vec<u16>,
+    pub port_match: PortMatch,
+    pub view_limit: usize,
+    pub columns: ColumnLayout,
+    pub time_format: TimeFormat,
+    pub coloring_rules: Vec<ColoringRule>,
+    pub window: WindowSize,
+    pub ring_buffer: RingBufferConfig,
+    /// GeoIP database file or directory of `.mmdb` files, searched before the default locations.
+    pub geoip: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            theme: String::from("Light"),
+            interface: None,
+            options: HashMap::new(),
+            protocols: HashMap::new(),
+            directions: HashMap::new(),
+            ports: vec![],
+            port_match: PortMatch::default(),
+            view_limit: 200,
+            columns: ColumnLayout::default(),
+            time_format: TimeFormat::default(),
+            coloring_rules: ColoringRule::defaults(),
+            window: WindowSize {
+                width: 1600.0,
+                height: 900.0,
+            },
+            ring_buffer: RingBufferConfig::default(),
+            geoip: None,
+        }
+    }
+}
+
+impl Config {
+    const VERSION: u32 = 1;
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("postal").join("config.toml"))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let mut config = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let (config, errors) = Self::parse(&content);
+                if !errors.is_empty() {
+                    // the next save drops what could not be read, keep the file for the user
+                    let backup = path.with_extension("toml.bak");
+                    for e in &errors {
+                        eprintln!("Ignoring invalid settings in {}: {}", path.display(), e);
+                    }
+                    match fs::copy(&path, &backup) {
+                        Ok(_) => {
+                            eprintln!("The previous settings were kept in {}", backup.display())
+                        }
+                        Err(e) => eprintln!("Could not back up {}: {}", path.display(), e),
+                    }
+                }
+                config
+            }
+            Err(_) => Self::default(),
+        };
+        config.columns.normalize();
+        config
+            .coloring_rules
+            .iter_mut()
+            .for_each(ColoringRule::compile);
+        config.version = Self::VERSION;
+        config
+    }
+
+    /// Reads the settings that are valid and skips the others, which fall back to their
+    /// defaults. Returns what was skipped and why.
+    fn parse(content: &str) -> (Self, Vec<String>) {
+        let table = match toml::from_str::<toml::Table>(content) {
+            Ok(table) => table,
+            Err(e) => return (Self::default(), vec![e.to_string()]),
+        };
+        let mut config = Self::default();
+        let mut valid = toml::Table::new();
+        let mut errors = vec![];
+        for (key, value) in table {
+            valid.insert(key.clone(), value);
+            match valid.clone().try_into::<Config>() {
+                Ok(parsed) => config = parsed,
+                Err(e) => {
+                    errors.push(format!("{}: {}", key, e));
+                    valid.remove(&key);
+                }
+            }
+        }
+        (config, errors)
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        let result = toml::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            eprintln!("Could not save settings to {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_settings_that_parse() {
+        let (config, errors) = Config::parse(
+            "theme = \"Dark\"\n\
+             view_limit = \"many\"\n\
+             ports = [80, 443]\n\
+             [window]\n\
+             width = 800.0\n\
+             height = 600.0\n",
+        );
+        assert_eq!(config.theme, "Dark");
+        assert_eq!(config.view_limit, Config::default().view_limit);
+        assert_eq!(config.ports, vec![80, 443]);
+        assert_eq!(config.window.width, 800.0);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("view_limit"), "{}", errors[0]);
+
+        let (config, errors) = Config::parse("theme = ");
+        assert_eq!(config.theme, Config::default().theme);
+        assert_eq!(errors.len(), 1);
+
+        let (_, errors) = Config::parse(&toml::to_string(&Config::default()).unwrap());
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+}
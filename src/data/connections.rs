@@ -0,0 +1,595 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use pnet::packet::{
+    tcp::{TcpFlags, TcpOptionNumbers, TcpPacket},
+    Packet,
+};
+
+use super::column::SortOrder;
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+use super::process::Process;
+use super::tcp_analysis::{self, TcpAnalysis};
+
+/// Where a connection is in its lifecycle at the end of the capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConnectionState {
+    /// The SYN was sent but the handshake never completed.
+    HalfOpen,
+    /// The SYN was answered with a RST, nothing listens on the port.
+    Refused,
+    Established,
+    /// One side sent its FIN.
+    Closing,
+    ClosedFin,
+    ClosedRst,
+}
+
+impl ConnectionState {
+    /// The states worth hunting for, connections that never got anywhere.
+    pub fn is_failed(&self) -> bool {
+        matches!(self, ConnectionState::HalfOpen | ConnectionState::Refused)
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConnectionState::HalfOpen => "Half-open",
+            ConnectionState::Refused => "Refused",
+            ConnectionState::Established => "Established",
+            ConnectionState::Closing => "Closing",
+            ConnectionState::ClosedFin => "Closed (FIN)",
+            ConnectionState::ClosedRst => "Closed (RST)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Options one side announced in its SYN.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SynOptions {
+    pub mss: Option<u16>,
+    pub window_scale: Option<u8>,
+    pub sack_permitted: bool,
+}
+
+impl SynOptions {
+    fn new(tcp: &TcpPacket) -> Self {
+        let mut options = SynOptions {
+            window_scale: tcp_analysis::window_scale(tcp),
+            ..Default::default()
+        };
+        for option in tcp.get_options_iter() {
+            match option.get_number() {
+                TcpOptionNumbers::MSS => {
+                    if let [high, low] = option.payload() {
+                        options.mss = Some(u16::from_be_bytes([*high, *low]));
+                    }
+                }
+                TcpOptionNumbers::SACK_PERMITTED => options.sack_permitted = true,
+                _ => {}
+            }
+        }
+        options
+    }
+}
+
+/// One TCP connection, the client is the side that sent the SYN.
+#[derive(Debug, Clone)]
+pub struct Connection {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    /// Local process owning the connection, from live captures on Linux.
+    pub process: Option<Process>,
+    pub first_seen: Duration,
+    pub last_seen: Duration,
+    pub syn: Option<Duration>,
+    pub syn_ack: Option<Duration>,
+    /// The ACK of the client completing the handshake.
+    pub ack: Option<Duration>,
+    pub packets: usize,
+    /// Bytes of the frames sent by the client.
+    pub client_bytes: usize,
+    /// Bytes of the frames sent by the server.
+    pub server_bytes: usize,
+    pub client_options: Option<SynOptions>,
+    pub server_options: Option<SynOptions>,
+    /// Packets the TCP analysis found a problem with, resets aside.
+    pub anomalies: usize,
+    client_fin: bool,
+    server_fin: bool,
+    reset: bool,
+}
+
+impl Connection {
+    fn new(client: SocketAddr, server: SocketAddr, timestamp: Duration) -> Self {
+        Self {
+            client,
+            server,
+            process: None,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            syn: None,
+            syn_ack: None,
+            ack: None,
+            packets: 0,
+            client_bytes: 0,
+            server_bytes: 0,
+            client_options: None,
+            server_options: None,
+            anomalies: 0,
+            client_fin: false,
+            server_fin: false,
+            reset: false,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        if self.reset {
+            if self.syn.is_some() && self.syn_ack.is_none() {
+                ConnectionState::Refused
+            } else {
+                ConnectionState::ClosedRst
+            }
+        } else if self.client_fin && self.server_fin {
+            ConnectionState::ClosedFin
+        } else if self.client_fin || self.server_fin {
+            ConnectionState::Closing
+        } else if self.syn.is_some() && self.ack.is_none() {
+            ConnectionState::HalfOpen
+        } else {
+            // connections already running when the capture started count as established
+            ConnectionState::Established
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.last_seen.saturating_sub(self.first_seen)
+    }
+
+    /// Time from the SYN to the SYN-ACK, how long the server took to answer.
+    pub fn syn_ack_delay(&self) -> Option<Duration> {
+        Some(self.syn_ack?.saturating_sub(self.syn?))
+    }
+
+    /// Time from the SYN to the ACK completing the handshake.
+    pub fn handshake(&self) -> Option<Duration> {
+        Some(self.ack?.saturating_sub(self.syn?))
+    }
+
+    /// Window scale shifts of client and server, in effect only if both announced one.
+    pub fn window_scale(&self) -> Option<(u8, u8)> {
+        let (client, server) = (self.client_options?, self.server_options?);
+        Some((client.window_scale?, server.window_scale?))
+    }
+
+    /// Whether SACK is in use, unknown without both SYNs.
+    pub fn sack(&self) -> Option<bool> {
+        let (client, server) = (self.client_options?, self.server_options?);
+        Some(client.sack_permitted && server.sack_permitted)
+    }
+
+    /// Display filter selecting the packets of this connection.
+    pub fn filter(&self) -> String {
+        format!(
+            "ip.addr == {} && tcp.port == {} && ip.addr == {} && tcp.port == {}",
+            self.client.ip(),
+            self.client.port(),
+            self.server.ip(),
+            self.server.port()
+        )
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(
+            self.state(),
+            ConnectionState::Refused | ConnectionState::ClosedFin | ConnectionState::ClosedRst
+        )
+    }
+
+    fn add(&mut self, packet: &ParsedPacket, tcp: &TcpPacket, from_client: bool) {
+        let flags = tcp.get_flags();
+        let has = |flag: u8| flags & flag != 0;
+        let now = packet.timestamp;
+        self.packets += 1;
+        self.last_seen = self.last_seen.max(now);
+        self.first_seen = self.first_seen.min(now);
+        if self.process.is_none() {
+            self.process = packet.process.clone();
+        }
+
+        if from_client {
+            self.client_bytes += packet.data.len();
+        } else {
+            self.server_bytes += packet.data.len();
+        }
+
+        match (has(TcpFlags::SYN), has(TcpFlags::ACK)) {
+            (true, false) if from_client => {
+                self.syn.get_or_insert(now);
+                self.client_options = Some(SynOptions::new(tcp));
+            }
+            (true, true) if !from_client => {
+                self.syn_ack.get_or_insert(now);
+                self.server_options = Some(SynOptions::new(tcp));
+            }
+            (false, true) if from_client && self.syn_ack.is_some() => {
+                self.ack.get_or_insert(now);
+            }
+            _ => {}
+        }
+        if has(TcpFlags::FIN) {
+            if from_client {
+                self.client_fin = true;
+            } else {
+                self.server_fin = true;
+            }
+        }
+        self.reset |= has(TcpFlags::RST);
+
+        if is_anomaly(packet) {
+            self.anomalies += 1;
+        }
+    }
+
+    /// Takes back the counts of a packet trimmed from the store. What its flags told
+    /// about the handshake and the state of the connection is kept.
+    fn remove(&mut self, packet: &ParsedPacket, from_client: bool) {
+        self.packets = self.packets.saturating_sub(1);
+        if from_client {
+            self.client_bytes = self.client_bytes.saturating_sub(packet.data.len());
+        } else {
+            self.server_bytes = self.server_bytes.saturating_sub(packet.data.len());
+        }
+        // the oldest packets are trimmed first, the next one is no older
+        self.first_seen = self.first_seen.max(packet.timestamp).min(self.last_seen);
+        if is_anomaly(packet) {
+            self.anomalies = self.anomalies.saturating_sub(1);
+        }
+    }
+}
+
+fn is_anomaly(packet: &ParsedPacket) -> bool {
+    TcpAnalysis {
+        reset: false,
+        ..packet.tcp_analysis
+    }
+    .has_problem()
+}
+
+/// Sortable columns of the connections table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionColumn {
+    Client,
+    Server,
+    Process,
+    State,
+    Start,
+    Duration,
+    SynAck,
+    Handshake,
+    Packets,
+    ClientBytes,
+    ServerBytes,
+    Mss,
+    WindowScale,
+    Sack,
+    Anomalies,
+}
+
+impl ConnectionColumn {
+    pub const ALL: [ConnectionColumn; 15] = [
+        ConnectionColumn::Client,
+        ConnectionColumn::Server,
+        ConnectionColumn::Process,
+        ConnectionColumn::State,
+        ConnectionColumn::Start,
+        ConnectionColumn::Duration,
+        ConnectionColumn::SynAck,
+        ConnectionColumn::Handshake,
+        ConnectionColumn::Packets,
+        ConnectionColumn::ClientBytes,
+        ConnectionColumn::ServerBytes,
+        ConnectionColumn::Mss,
+        ConnectionColumn::WindowScale,
+        ConnectionColumn::Sack,
+        ConnectionColumn::Anomalies,
+    ];
+
+    /// Columns listed in order rather than as a ranking when first sorted by.
+    pub fn ascending_first(&self) -> bool {
+        matches!(
+            self,
+            ConnectionColumn::Client
+                | ConnectionColumn::Server
+                | ConnectionColumn::Process
+                | ConnectionColumn::State
+                | ConnectionColumn::Start
+        )
+    }
+
+    /// Sort key of the numeric columns, unknown values sort below every known one.
+    fn key(&self, connection: &Connection) -> u128 {
+        let known = |duration: Option<Duration>| duration.map_or(0, |d| d.as_nanos() + 1);
+        match self {
+            ConnectionColumn::Client
+            | ConnectionColumn::Server
+            | ConnectionColumn::Process
+            | ConnectionColumn::State => 0,
+            ConnectionColumn::Start => connection.first_seen.as_nanos(),
+            ConnectionColumn::Duration => connection.duration().as_nanos(),
+            ConnectionColumn::SynAck => known(connection.syn_ack_delay()),
+            ConnectionColumn::Handshake => known(connection.handshake()),
+            ConnectionColumn::Packets => connection.packets as u128,
+            ConnectionColumn::ClientBytes => connection.client_bytes as u128,
+            ConnectionColumn::ServerBytes => connection.server_bytes as u128,
+            ConnectionColumn::Mss => {
+                let mss = |options: Option<SynOptions>| options.and_then(|o| o.mss);
+                mss(connection.client_options)
+                    .into_iter()
+                    .chain(mss(connection.server_options))
+                    .min()
+                    .map_or(0, |mss| mss as u128 + 1)
+            }
+            ConnectionColumn::WindowScale => {
+                connection.window_scale().map_or(0, |(client, server)| {
+                    ((client as u128 + 1) << 8) | server as u128
+                })
+            }
+            ConnectionColumn::Sack => connection.sack().map_or(0, |sack| sack as u128 + 1),
+            ConnectionColumn::Anomalies => connection.anomalies as u128,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConnectionColumn::Client => "Client",
+            ConnectionColumn::Server => "Server",
+            ConnectionColumn::Process => "Process",
+            ConnectionColumn::State => "State",
+            ConnectionColumn::Start => "Start",
+            ConnectionColumn::Duration => "Duration",
+            ConnectionColumn::SynAck => "SYN-ACK",
+            ConnectionColumn::Handshake => "Handshake",
+            ConnectionColumn::Packets => "Packets",
+            ConnectionColumn::ClientBytes => "Bytes →",
+            ConnectionColumn::ServerBytes => "Bytes ←",
+            ConnectionColumn::Mss => "MSS",
+            ConnectionColumn::WindowScale => "Win Scale",
+            ConnectionColumn::Sack => "SACK",
+            ConnectionColumn::Anomalies => "Anomalies",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Follows the TCP connections as the packets arrive.
+/// A new SYN on the addresses of a closed connection starts another one.
+#[derive(Debug, Default)]
+pub struct ConnectionTable {
+    connections: Vec<Connection>,
+    /// Connections between two sockets by the lower and the higher one, the latest last.
+    index: HashMap<(SocketAddr, SocketAddr), VecDeque<usize>>,
+    /// Connections all packets of which were trimmed, dropped once they are half of all.
+    emptied: usize,
+}
+
+impl ConnectionTable {
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        let TransportPacket::Tcp(tcp) = &packet.transport else {
+            return;
+        };
+        let Some((source, destination)) = sockets(packet) else {
+            return;
+        };
+        let key = (source.min(destination), source.max(destination));
+        let flags = tcp.get_flags();
+        let syn = flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK == 0;
+
+        let existing = self
+            .index
+            .get(&key)
+            .and_then(VecDeque::back)
+            .copied()
+            .filter(|&i| !(syn && self.connections[i].is_closed()));
+        let i = match existing {
+            Some(i) => {
+                if self.connections[i].packets == 0 {
+                    self.emptied -= 1;
+                }
+                i
+            }
+            None => {
+                // without the SYN, a SYN-ACK still tells which side is the server
+                let (client, server) = if flags & TcpFlags::SYN != 0 && !syn {
+                    (destination, source)
+                } else {
+                    (source, destination)
+                };
+                self.connections
+                    .push(Connection::new(client, server, packet.timestamp));
+                self.index
+                    .entry(key)
+                    .or_default()
+                    .push_back(self.connections.len() - 1);
+                self.connections.len() - 1
+            }
+        };
+        let connection = &mut self.connections[i];
+        let from_client = connection.client == source;
+        connection.add(packet, tcp, from_client);
+    }
+
+    /// Takes back a packet counted before, once it is trimmed from the store. It belongs
+    /// to the oldest connection between its sockets that has packets left.
+    pub fn remove(&mut self, packet: &ParsedPacket) {
+        let Some((source, destination)) = sockets(packet) else {
+            return;
+        };
+        let key = (source.min(destination), source.max(destination));
+        let Some(i) = self.index.get(&key).and_then(|connections| {
+            connections
+                .iter()
+                .copied()
+                .find(|&i| self.connections[i].packets > 0)
+        }) else {
+            return;
+        };
+        let connection = &mut self.connections[i];
+        let from_client = connection.client == source;
+        connection.remove(packet, from_client);
+        if connection.packets == 0 {
+            self.emptied += 1;
+            if self.emptied * 2 > self.connections.len() {
+                self.compact();
+            }
+        }
+    }
+
+    /// Drops the connections without packets.
+    fn compact(&mut self) {
+        let mut kept = 0;
+        let moved = self
+            .connections
+            .iter()
+            .map(|connection| {
+                (connection.packets > 0).then(|| {
+                    kept += 1;
+                    kept - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        self.connections.retain(|connection| connection.packets > 0);
+        self.index.retain(|_, connections| {
+            connections.retain_mut(|i| moved[*i].map(|new| *i = new).is_some());
+            !connections.is_empty()
+        });
+        self.emptied = 0;
+    }
+
+    /// The connections sorted by `sort`.
+    pub fn sorted(&self, sort: (ConnectionColumn, SortOrder)) -> Vec<&Connection> {
+        let mut connections = self
+            .connections
+            .iter()
+            .filter(|connection| connection.packets > 0)
+            .collect::<Vec<_>>();
+        let (column, order) = sort;
+        connections.sort_by(|a, b| {
+            let ordering = match column {
+                ConnectionColumn::Client => a.client.cmp(&b.client),
+                ConnectionColumn::Server => a.server.cmp(&b.server),
+                ConnectionColumn::Process => a
+                    .process
+                    .as_ref()
+                    .map(Process::sort_key)
+                    .cmp(&b.process.as_ref().map(Process::sort_key)),
+                ConnectionColumn::State => a.state().cmp(&b.state()),
+                column => column.key(a).cmp(&column.key(b)),
+            };
+            match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            }
+        });
+        connections
+    }
+}
+
+/// Source and destination socket of a TCP packet.
+fn sockets(packet: &ParsedPacket) -> Option<(SocketAddr, SocketAddr)> {
+    let TransportPacket::Tcp(tcp) = &packet.transport else {
+        return None;
+    };
+    Some((
+        SocketAddr::new(packet.get_source_ip()?, tcp.get_source()),
+        SocketAddr::new(packet.get_destination_ip()?, tcp.get_destination()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_packets::{self, Segment};
+
+    const CLIENT: &str = "10.0.0.1:40000";
+    const SERVER: &str = "10.0.0.2:443";
+
+    fn segment(from_client: bool, flags: u8, at: u64) -> ParsedPacket {
+        let (source, destination) = if from_client {
+            (CLIENT, SERVER)
+        } else {
+            (SERVER, CLIENT)
+        };
+        let segment = Segment {
+            flags,
+            at,
+            ..Segment::default()
+        };
+        test_packets::tcp(source, destination, segment)
+    }
+
+    #[test]
+    fn trimmed_packets_leave_their_connections() {
+        let (syn, ack, fin) = (TcpFlags::SYN, TcpFlags::ACK, TcpFlags::FIN);
+        let packets = [
+            segment(true, syn, 0),
+            segment(false, syn | ack, 1),
+            segment(true, ack, 2),
+            segment(true, fin | ack, 3),
+            segment(false, fin | ack, 4),
+            // the same sockets again after the close
+            segment(true, syn, 10),
+            segment(false, syn | ack, 11),
+            test_packets::tcp("10.0.0.1:40001", "10.0.0.3:80", Segment::default()),
+        ];
+        let mut table = ConnectionTable::default();
+        packets.iter().for_each(|packet| table.add(packet));
+        let starts = |table: &ConnectionTable| {
+            table
+                .sorted((ConnectionColumn::Start, SortOrder::Ascending))
+                .iter()
+                .map(|c| (c.first_seen.as_millis(), c.packets, c.state()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            starts(&table),
+            [
+                (0, 5, ConnectionState::ClosedFin),
+                (0, 1, ConnectionState::Established),
+                (10, 2, ConnectionState::HalfOpen),
+            ]
+        );
+
+        // packets of the reused sockets are taken from the older connection first,
+        // which starts no earlier than the last of them now
+        packets[..4].iter().for_each(|packet| table.remove(packet));
+        assert_eq!(
+            starts(&table),
+            [
+                (0, 1, ConnectionState::Established),
+                (3, 1, ConnectionState::ClosedFin),
+                (10, 2, ConnectionState::HalfOpen),
+            ]
+        );
+        packets[4..7].iter().for_each(|packet| table.remove(packet));
+        assert_eq!(starts(&table), [(0, 1, ConnectionState::Established)]);
+        assert_eq!(table.connections.len(), 1);
+        assert_eq!(table.index.len(), 1);
+
+        // after being dropped, the sockets start a connection again
+        table.add(&segment(true, ack, 20));
+        assert_eq!(
+            table
+                .sorted((ConnectionColumn::Start, SortOrder::Ascending))
+                .len(),
+            2
+        );
+        assert_eq!(table.connections.len(), 2);
+    }
+}
@@ -0,0 +1,406 @@
+// Just enough cryptography to open QUIC Initial packets: SHA-256, HKDF and AES-128-GCM.
+// None of it is constant time, it only ever sees keys derived from captured traffic.
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const SHA256_INIT: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    let mut state = SHA256_INIT;
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut padded = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        padded[..32].copy_from_slice(&sha256(key));
+    } else {
+        padded[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = padded.map(|b| b ^ 0x36).to_vec();
+    inner.extend_from_slice(message);
+    let mut outer = padded.map(|b| b ^ 0x5c).to_vec();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+pub fn hkdf_extract(salt: &[u8], input: &[u8]) -> [u8; 32] {
+    hmac_sha256(salt, input)
+}
+
+/// HKDF-Expand-Label of TLS 1.3 with an empty context.
+pub fn hkdf_expand_label(secret: &[u8], label: &str, length: usize) -> Vec<u8> {
+    let label = format!("tls13 {}", label);
+    let mut info = (length as u16).to_be_bytes().to_vec();
+    info.push(label.len() as u8);
+    info.extend_from_slice(label.as_bytes());
+    info.push(0);
+
+    let mut output = vec![];
+    let mut block: Vec<u8> = vec![];
+    for counter in 1..=length.div_ceil(32) as u8 {
+        let mut message = block;
+        message.extend_from_slice(&info);
+        message.push(counter);
+        block = hmac_sha256(secret, &message).to_vec();
+        output.extend_from_slice(&block);
+    }
+    output.truncate(length);
+    output
+}
+
+const AES_SBOX: [u8; 256] = aes_sbox();
+
+/// The AES S-box, the multiplicative inverse in GF(2^8) followed by the affine transformation.
+const fn aes_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let (mut p, mut q) = (1u8, 1u8);
+    loop {
+        // p runs through the field multiplied by 3, q by its inverse
+        p ^= (p << 1) ^ if p & 0x80 != 0 { 0x1b } else { 0 };
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+        let affine = q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4);
+        sbox[p as usize] = affine ^ 0x63;
+        if p == 1 {
+            break;
+        }
+    }
+    sbox[0] = 0x63;
+    sbox
+}
+
+/// Multiplication by x in GF(2^8).
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// AES-128, encryption only, which is all that counter mode and header protection need.
+pub struct Aes128 {
+    round_keys: [[u8; 16]; 11],
+}
+
+impl Aes128 {
+    pub fn new(key: &[u8; 16]) -> Self {
+        let mut words = [[0u8; 4]; 44];
+        for (word, bytes) in words.iter_mut().zip(key.chunks(4)) {
+            word.copy_from_slice(bytes);
+        }
+        let mut rcon = 1u8;
+        for i in 4..44 {
+            let mut word = words[i - 1];
+            if i % 4 == 0 {
+                word = [
+                    AES_SBOX[word[1] as usize] ^ rcon,
+                    AES_SBOX[word[2] as usize],
+                    AES_SBOX[word[3] as usize],
+                    AES_SBOX[word[0] as usize],
+                ];
+                rcon = xtime(rcon);
+            }
+            for (byte, previous) in word.iter_mut().zip(words[i - 4]) {
+                *byte ^= previous;
+            }
+            words[i] = word;
+        }
+        let mut round_keys = [[0u8; 16]; 11];
+        for (round_key, four) in round_keys.iter_mut().zip(words.chunks(4)) {
+            for (bytes, word) in round_key.chunks_mut(4).zip(four) {
+                bytes.copy_from_slice(word);
+            }
+        }
+        Self { round_keys }
+    }
+
+    pub fn encrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+        let add_round_key = |state: &mut [u8; 16], round: usize| {
+            for (byte, key) in state.iter_mut().zip(self.round_keys[round]) {
+                *byte ^= key;
+            }
+        };
+        // column major, byte `4 * column + row`
+        let sub_shift = |state: &[u8; 16]| {
+            let mut shifted = [0u8; 16];
+            for column in 0..4 {
+                for row in 0..4 {
+                    shifted[4 * column + row] =
+                        AES_SBOX[state[4 * ((column + row) % 4) + row] as usize];
+                }
+            }
+            shifted
+        };
+
+        let mut state = *block;
+        add_round_key(&mut state, 0);
+        for round in 1..10 {
+            state = sub_shift(&state);
+            for column in state.chunks_mut(4) {
+                let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+                let all = a0 ^ a1 ^ a2 ^ a3;
+                column[0] ^= all ^ xtime(a0 ^ a1);
+                column[1] ^= all ^ xtime(a1 ^ a2);
+                column[2] ^= all ^ xtime(a2 ^ a3);
+                column[3] ^= all ^ xtime(a3 ^ a0);
+            }
+            add_round_key(&mut state, round);
+        }
+        state = sub_shift(&state);
+        add_round_key(&mut state, 10);
+        state
+    }
+}
+
+/// Multiplication in the GHASH field, blocks read as big endian numbers.
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    let mut product = 0;
+    let mut v = y;
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            product ^= v;
+        }
+        v = if v & 1 == 1 {
+            (v >> 1) ^ (0xe1 << 120)
+        } else {
+            v >> 1
+        };
+    }
+    product
+}
+
+/// Decrypts AES-128-GCM with a 96-bit nonce, `None` if the authentication tag does not match.
+pub fn aes128_gcm_open(
+    key: &[u8; 16],
+    nonce: &[u8; 12],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Option<Vec<u8>> {
+    const TAG: usize = 16;
+    let (ciphertext, tag) = sealed.split_at(sealed.len().checked_sub(TAG)?);
+    let aes = Aes128::new(key);
+    let hash_key = u128::from_be_bytes(aes.encrypt(&[0; 16]));
+    let counter = |n: u32| {
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(nonce);
+        block[12..].copy_from_slice(&n.to_be_bytes());
+        block
+    };
+
+    let mut hash = 0u128;
+    for data in [aad, ciphertext] {
+        for chunk in data.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            hash = gf128_mul(hash ^ u128::from_be_bytes(block), hash_key);
+        }
+    }
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    hash = gf128_mul(hash ^ lengths, hash_key);
+    let expected = (hash ^ u128::from_be_bytes(aes.encrypt(&counter(1)))).to_be_bytes();
+    if expected != tag {
+        return None;
+    }
+
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    for (i, chunk) in ciphertext.chunks(16).enumerate() {
+        let keystream = aes.encrypt(&counter(i as u32 + 2));
+        plaintext.extend(chunk.iter().zip(keystream).map(|(c, k)| c ^ k));
+    }
+    Some(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let digits = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hashes_match_the_published_vectors() {
+        // FIPS 180-2 appendix B.1
+        assert_eq!(
+            sha256(b"abc").to_vec(),
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?").to_vec(),
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        // RFC 5869 test case 1
+        assert_eq!(
+            hkdf_extract(&hex("000102030405060708090a0b0c"), &[0x0b; 22]).to_vec(),
+            hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+        );
+    }
+
+    #[test]
+    fn derives_the_initial_secrets_of_rfc_9001() {
+        // RFC 9001 appendix A.1, the client's DCID is 0x8394c8f03e515708
+        let salt = hex("38762cf7f55934b34d179ae6a4c80cadccbb7f0a");
+        let initial = hkdf_extract(&salt, &hex("8394c8f03e515708"));
+        assert_eq!(
+            initial.to_vec(),
+            hex("7db5df06e7a69e432496adedb00851923595221596ae2ae9fb8115c1e9ed0a44")
+        );
+        let client = hkdf_expand_label(&initial, "client in", 32);
+        assert_eq!(
+            client,
+            hex("c00cf151ca5be075ed0ebfb5c80323c42d6b7db67881289af4008f1f6c357aea")
+        );
+        assert_eq!(
+            hkdf_expand_label(&client, "quic key", 16),
+            hex("1f369613dd76d5467730efcbe3b1a22d")
+        );
+        assert_eq!(
+            hkdf_expand_label(&client, "quic iv", 12),
+            hex("fa044b2f42a3fd3b46fb255c")
+        );
+        assert_eq!(
+            hkdf_expand_label(&client, "quic hp", 16),
+            hex("9f50449e04a0e810283a1e9933adedd2")
+        );
+        let server = hkdf_expand_label(&initial, "server in", 32);
+        assert_eq!(
+            server,
+            hex("3c199828fd139efd216c155ad844cc81fb82fa8d7446fa7d78be803acdda951b")
+        );
+    }
+
+    #[test]
+    fn encrypts_the_fips_197_block() {
+        let key = hex("000102030405060708090a0b0c0d0e0f");
+        let block = hex("00112233445566778899aabbccddeeff");
+        let aes = Aes128::new(key.as_slice().try_into().unwrap());
+        assert_eq!(
+            aes.encrypt(block.as_slice().try_into().unwrap()).to_vec(),
+            hex("69c4e0d86a7b0430d8cdb78070b4c55a")
+        );
+        // RFC 9001 appendix A.2, the header protection mask of the client Initial
+        let hp = hex("9f50449e04a0e810283a1e9933adedd2");
+        let sample = hex("d1b1c98dd7689fb8ec11d242b123dc9b");
+        let mask = Aes128::new(hp.as_slice().try_into().unwrap())
+            .encrypt(sample.as_slice().try_into().unwrap());
+        assert_eq!(mask[..5].to_vec(), hex("437b9aec36"));
+    }
+
+    #[test]
+    fn opens_the_gcm_test_vectors() {
+        // test cases 1, 2 and 4 of the GCM specification, McGrew and Viega
+        let zero_key = [0; 16];
+        let zero_nonce = [0; 12];
+        assert_eq!(
+            aes128_gcm_open(
+                &zero_key,
+                &zero_nonce,
+                &[],
+                &hex("58e2fccefa7e3061367f1d57a4e7455a")
+            ),
+            Some(vec![])
+        );
+        assert_eq!(
+            aes128_gcm_open(
+                &zero_key,
+                &zero_nonce,
+                &[],
+                &hex("0388dace60b6a392f328c2b971b2fe78 ab6e47d42cec13bdf53a67b21257bddf")
+            ),
+            Some(vec![0; 16])
+        );
+
+        let key = hex("feffe9928665731c6d6a8f9467308308");
+        let key = key.as_slice().try_into().unwrap();
+        let nonce = hex("cafebabefacedbaddecaf888");
+        let nonce = nonce.as_slice().try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let mut sealed = hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091
+             5bc94fbc3221a5db94fae95ae7121a47",
+        );
+        let plaintext = hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        );
+        assert_eq!(aes128_gcm_open(key, nonce, &aad, &sealed), Some(plaintext));
+        // any change to the data or the tag fails the check
+        assert_eq!(aes128_gcm_open(key, nonce, &aad[1..], &sealed), None);
+        sealed[0] ^= 1;
+        assert_eq!(aes128_gcm_open(key, nonce, &aad, &sealed), None);
+        assert_eq!(aes128_gcm_open(key, nonce, &aad, &sealed[..15]), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+
+use pnet::datalink::NetworkInterface;
+use pnet::ipnetwork::IpNetwork;
+use pnet::util::MacAddr;
+use serde::{Deserialize, Serialize};
+
+use super::parsed_packet::ParsedPacket;
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+    Forwarded,
+    Broadcast,
+    Multicast,
+    Local,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Inbound,
+        Direction::Outbound,
+        Direction::Forwarded,
+        Direction::Broadcast,
+        Direction::Multicast,
+        Direction::Local,
+    ];
+
+    pub fn as_map() -> HashMap<Direction, bool> {
+        Self::ALL.iter().map(|d| (*d, true)).collect()
+    }
+
+    /// Classifies a packet relative to the capturing interface.
+    /// Broadcast and multicast destinations take precedence, otherwise the IP addresses decide
+    /// whether the packet belongs to this host. The MAC address is only consulted for packets
+    /// without an IP layer, so routed traffic addressed to our MAC still ends up as `Forwarded`.
+    pub fn classify(packet: &ParsedPacket, interface: &NetworkInterface) -> Self {
+        let src_ip = packet.get_source_ip();
+        let dst_ip = packet.get_destination_ip();
+        let dst_mac = packet.eth.get_destination();
+
+        if dst_mac.is_broadcast() || dst_ip.is_some_and(|ip| is_broadcast(ip, interface)) {
+            return Direction::Broadcast;
+        }
+        if dst_mac.is_multicast() || dst_ip.is_some_and(|ip| ip.is_multicast()) {
+            return Direction::Multicast;
+        }
+
+        let from_host = is_own(src_ip, packet.eth.get_source(), interface);
+        let to_host = is_own(dst_ip, dst_mac, interface);
+        match (from_host, to_host) {
+            (true, true) => Direction::Local,
+            (true, false) => Direction::Outbound,
+            (false, true) => Direction::Inbound,
+            (false, false) => Direction::Forwarded,
+        }
+    }
+}
+
+fn is_own(ip: Option<IpAddr>, mac: MacAddr, interface: &NetworkInterface) -> bool {
+    match ip {
+        Some(ip) => ip.is_loopback() || interface.ips.iter().any(|net| net.ip() == ip),
+        None => interface.mac == Some(mac),
+    }
+}
+
+fn is_broadcast(ip: IpAddr, interface: &NetworkInterface) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_broadcast()
+                || interface.ips.iter().any(|net| match net {
+                    IpNetwork::V4(net) => net.prefix() < 31 && net.broadcast() == v4,
+                    IpNetwork::V6(_) => false,
+                })
+        }
+        // IPv6 has no broadcast, the all-nodes multicast group takes its place
+        IpAddr::V6(_) => false,
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Inbound => write!(f, "Inbound"),
+            Direction::Outbound => write!(f, "Outbound"),
+            Direction::Forwarded => write!(f, "Forwarded"),
+            Direction::Broadcast => write!(f, "Broadcast"),
+            Direction::Multicast => write!(f, "Multicast"),
+            Direction::Local => write!(f, "Local"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::data::test_packets::{self, HOST, HOST_MAC};
+
+    const PEER_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 2);
+    const OTHER_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 3);
+    const BROADCAST_MAC: MacAddr = MacAddr(0xff, 0xff, 0xff, 0xff, 0xff, 0xff);
+    const MULTICAST_MAC: MacAddr = MacAddr(0x01, 0x00, 0x5e, 0, 0, 0xfb);
+
+    /// A UDP frame between the addresses, or an ARP reply naming the host as sender
+    /// without them.
+    fn frame(destination: MacAddr, source: MacAddr, ips: Option<(&str, &str)>) -> ParsedPacket {
+        let mut data = vec![];
+        data.extend(destination.octets());
+        data.extend(source.octets());
+        let udp = [0x30, 0x39, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00];
+        match ips.map(|(src, dst)| (src.parse().unwrap(), dst.parse().unwrap())) {
+            Some((IpAddr::V4(src), IpAddr::V4(dst))) => {
+                data.extend([0x08, 0x00, 0x45, 0, 0, 28, 0, 0, 0x40, 0, 64, 17, 0, 0]);
+                data.extend(src.octets());
+                data.extend(dst.octets());
+                data.extend(udp);
+            }
+            Some((IpAddr::V6(src), IpAddr::V6(dst))) => {
+                data.extend([0x86, 0xdd, 0x60, 0, 0, 0, 0, 8, 17, 64]);
+                data.extend(src.octets());
+                data.extend(dst.octets());
+                data.extend(udp);
+            }
+            Some(_) => panic!("mixed address families"),
+            None => {
+                data.extend([0x08, 0x06, 0, 1, 0x08, 0x00, 6, 4, 0, 2]);
+                data.extend(HOST_MAC.octets());
+                data.extend(HOST.parse::<Ipv4Addr>().unwrap().octets());
+                data.extend(PEER_MAC.octets());
+                data.extend([10, 0, 0, 2]);
+            }
+        }
+        ParsedPacket::parse(data, Duration::ZERO, &test_packets::interface()).unwrap()
+    }
+
+    #[test]
+    fn classifies_by_precedence() {
+        let ip = |src, dst| Some((src, dst));
+        let cases = [
+            // broadcast and multicast destinations come before the host's own addresses
+            (
+                BROADCAST_MAC,
+                HOST_MAC,
+                ip(HOST, "10.0.0.2"),
+                Direction::Broadcast,
+            ),
+            (
+                PEER_MAC,
+                HOST_MAC,
+                ip(HOST, "255.255.255.255"),
+                Direction::Broadcast,
+            ),
+            (
+                HOST_MAC,
+                PEER_MAC,
+                ip("10.0.0.2", "10.0.0.255"),
+                Direction::Broadcast,
+            ),
+            (
+                MULTICAST_MAC,
+                HOST_MAC,
+                ip(HOST, "224.0.0.251"),
+                Direction::Multicast,
+            ),
+            (
+                HOST_MAC,
+                PEER_MAC,
+                ip("10.0.0.2", "224.0.0.251"),
+                Direction::Multicast,
+            ),
+            (
+                PEER_MAC,
+                HOST_MAC,
+                ip("fe80::1", "ff02::1"),
+                Direction::Multicast,
+            ),
+            // with an IP layer the addresses decide, whatever the MAC addresses say
+            (
+                PEER_MAC,
+                HOST_MAC,
+                ip(HOST, "10.0.0.2"),
+                Direction::Outbound,
+            ),
+            (
+                PEER_MAC,
+                PEER_MAC,
+                ip(HOST, "192.0.2.1"),
+                Direction::Outbound,
+            ),
+            (
+                HOST_MAC,
+                PEER_MAC,
+                ip("192.0.2.1", HOST),
+                Direction::Inbound,
+            ),
+            (
+                PEER_MAC,
+                PEER_MAC,
+                ip("192.0.2.1", HOST),
+                Direction::Inbound,
+            ),
+            (HOST_MAC, HOST_MAC, ip(HOST, HOST), Direction::Local),
+            (
+                OTHER_MAC,
+                PEER_MAC,
+                ip("127.0.0.1", "127.0.0.1"),
+                Direction::Local,
+            ),
+            (
+                HOST_MAC,
+                PEER_MAC,
+                ip("192.0.2.1", "198.51.100.1"),
+                Direction::Forwarded,
+            ),
+            (
+                PEER_MAC,
+                HOST_MAC,
+                ip("198.51.100.1", "192.0.2.1"),
+                Direction::Forwarded,
+            ),
+            // without one, ARP frames go by their MAC addresses and not their contents
+            (BROADCAST_MAC, HOST_MAC, None, Direction::Broadcast),
+            (PEER_MAC, HOST_MAC, None, Direction::Outbound),
+            (HOST_MAC, PEER_MAC, None, Direction::Inbound),
+            (HOST_MAC, HOST_MAC, None, Direction::Local),
+            (OTHER_MAC, PEER_MAC, None, Direction::Forwarded),
+        ];
+        for (destination, source, ips, expected) in cases {
+            let packet = frame(destination, source, ips);
+            assert_eq!(
+                Direction::classify(&packet, &test_packets::interface()),
+                expected,
+                "{} -> {} {:?}",
+                source,
+                destination,
+                ips
+            );
+            assert_eq!(packet.direction, expected);
+        }
+    }
+}
@@ -0,0 +1,838 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use pnet::ipnetwork::IpNetwork;
+use pnet::packet::ethernet::EtherTypes;
+use pnet::packet::tcp::TcpFlags;
+use pnet::packet::Packet;
+use pnet::util::MacAddr;
+
+use super::host_names::HostNames;
+use super::parsed_packet::{AppProtocol, NetworkPacket, ParsedPacket, TransportPacket};
+use super::quic::{self, QuicPacket};
+use super::tcp_analysis::TcpAnalysis;
+
+/// A parsed filter expression in a small Wireshark-like syntax, e.g.
+/// `tcp.port == 443 && !(ip.addr == 10.0.0.0/8)` or `payload contains "GET"`.
+/// Fields without a comparison test for presence (`udp`) or a set flag (`tcp.flags.syn`).
+/// RTTs of the TCP analysis (`tcp.analysis.ack_rtt`) are in milliseconds.
+/// `proc.name` and `proc.pid` match the local process of live captures on Linux.
+/// `ip.host` matches the resolved host names, `ip.host contains "example"`.
+/// `quic.cid` matches connection IDs in hex, `quic.sni` the server name of a client's Initial.
+#[derive(Debug, Clone)]
+pub struct DisplayFilter {
+    expr: Expr,
+}
+
+impl DisplayFilter {
+    /// Host names are needed for the `ip.host` fields, the names known by then are used.
+    pub fn matches(&self, packet: &ParsedPacket, names: &HostNames) -> bool {
+        self.expr.eval(packet, names)
+    }
+
+    /// Whether the filter matches host names, it may match other packets once more are known.
+    pub fn uses_host_names(&self) -> bool {
+        self.expr.uses_host_names()
+    }
+}
+
+impl FromStr for DisplayFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(Self { expr }),
+            Some(token) => Err(format!("Unexpected '{}'", token)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Present(FilterField),
+    Compare(FilterField, CmpOp, Literal),
+}
+
+impl Expr {
+    fn eval(&self, packet: &ParsedPacket, names: &HostNames) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(packet, names) && b.eval(packet, names),
+            Expr::Or(a, b) => a.eval(packet, names) || b.eval(packet, names),
+            Expr::Not(e) => !e.eval(packet, names),
+            Expr::Present(field) => field
+                .values(packet, names)
+                .iter()
+                .any(|v| !matches!(v, Value::Bool(false))),
+            // a field can occur more than once (ip.addr), `!=` holds if no occurrence is equal
+            Expr::Compare(field, CmpOp::Ne, literal) => !field
+                .values(packet, names)
+                .iter()
+                .any(|v| v.compare(CmpOp::Eq, literal)),
+            Expr::Compare(field, op, literal) => field
+                .values(packet, names)
+                .iter()
+                .any(|v| v.compare(*op, literal)),
+        }
+    }
+
+    fn uses_host_names(&self) -> bool {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => a.uses_host_names() || b.uses_host_names(),
+            Expr::Not(e) => e.uses_host_names(),
+            Expr::Present(field) | Expr::Compare(field, _, _) => matches!(
+                field,
+                FilterField::IpSrcHost | FilterField::IpDstHost | FilterField::IpHost
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CmpOp::Eq => write!(f, "=="),
+            CmpOp::Ne => write!(f, "!="),
+            CmpOp::Lt => write!(f, "<"),
+            CmpOp::Le => write!(f, "<="),
+            CmpOp::Gt => write!(f, ">"),
+            CmpOp::Ge => write!(f, ">="),
+            CmpOp::Contains => write!(f, "contains"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Number,
+    Ip,
+    Mac,
+    Text,
+    Bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(u64),
+    Net(IpNetwork),
+    Mac(MacAddr),
+    Text(String),
+    Bool(bool),
+}
+
+impl Literal {
+    fn parse(kind: Kind, op: CmpOp, raw: &str) -> Result<Self, String> {
+        if op == CmpOp::Contains {
+            return Ok(Literal::Text(raw.to_lowercase()));
+        }
+        match kind {
+            Kind::Number => {
+                let parsed = match raw.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => raw.parse::<u64>(),
+                };
+                parsed
+                    .map(Literal::Number)
+                    .map_err(|_| format!("'{}' is not a number", raw))
+            }
+            Kind::Ip => raw
+                .parse::<IpNetwork>()
+                .map(Literal::Net)
+                .map_err(|_| format!("'{}' is not an IP address or network", raw)),
+            Kind::Mac => raw
+                .parse::<MacAddr>()
+                .map(Literal::Mac)
+                .map_err(|_| format!("'{}' is not a MAC address", raw)),
+            Kind::Text => Ok(Literal::Text(raw.to_lowercase())),
+            Kind::Bool => match raw {
+                "1" | "true" => Ok(Literal::Bool(true)),
+                "0" | "false" => Ok(Literal::Bool(false)),
+                _ => Err(format!("'{}' is not a boolean", raw)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(u64),
+    Ip(IpAddr),
+    Mac(MacAddr),
+    Text(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn compare(&self, op: CmpOp, literal: &Literal) -> bool {
+        if let (CmpOp::Contains, Literal::Text(needle)) = (op, literal) {
+            return self.to_string().to_lowercase().contains(needle.as_str());
+        }
+        let ordering = match (self, literal) {
+            (Value::Number(v), Literal::Number(l)) => v.cmp(l),
+            (Value::Ip(v), Literal::Net(net)) if op == CmpOp::Eq => return net.contains(*v),
+            (Value::Ip(v), Literal::Net(net)) => v.cmp(&net.ip()),
+            (Value::Mac(v), Literal::Mac(l)) => v.octets().cmp(&l.octets()),
+            (Value::Text(v), Literal::Text(l)) => v.to_lowercase().cmp(l),
+            (Value::Bool(v), Literal::Bool(l)) => v.cmp(l),
+            _ => return false,
+        };
+        match op {
+            CmpOp::Eq => ordering.is_eq(),
+            CmpOp::Ne => ordering.is_ne(),
+            CmpOp::Lt => ordering.is_lt(),
+            CmpOp::Le => ordering.is_le(),
+            CmpOp::Gt => ordering.is_gt(),
+            CmpOp::Ge => ordering.is_ge(),
+            CmpOp::Contains => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Ip(ip) => write!(f, "{}", ip),
+            Value::Mac(mac) => write!(f, "{}", mac),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterField {
+    Eth,
+    Ip,
+    Ipv4,
+    Ipv6,
+    Tcp,
+    Udp,
+    FrameNumber,
+    FrameLen,
+    FrameMarked,
+    FrameComment,
+    EthSrc,
+    EthDst,
+    EthAddr,
+    IpSrc,
+    IpDst,
+    IpAddr,
+    IpTtl,
+    IpVersion,
+    Port,
+    TcpSrcPort,
+    TcpDstPort,
+    TcpPort,
+    TcpSeq,
+    TcpAck,
+    TcpWindow,
+    TcpLen,
+    TcpFlag(u8),
+    UdpSrcPort,
+    UdpDstPort,
+    UdpPort,
+    UdpLen,
+    Icmp,
+    IcmpType,
+    IcmpCode,
+    Icmpv6,
+    Icmpv6Type,
+    Icmpv6Code,
+    Arp,
+    Dns,
+    Http,
+    Tls,
+    TcpRetransmission,
+    TcpOutOfOrder,
+    TcpLostSegment,
+    TcpDuplicateAck,
+    TcpZeroWindow,
+    TcpWindowFull,
+    TcpAnalysisFlags,
+    TcpAckRtt,
+    TcpHandshakeRtt,
+    Direction,
+    Payload,
+    ProcessName,
+    ProcessPid,
+    IpSrcHost,
+    IpDstHost,
+    IpHost,
+    Quic,
+    QuicVersion,
+    QuicPacketType,
+    QuicDcid,
+    QuicScid,
+    QuicCid,
+    QuicSni,
+    QuicAlpn,
+}
+
+impl FilterField {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "eth" => Self::Eth,
+            "ip" => Self::Ip,
+            "ipv4" => Self::Ipv4,
+            "ipv6" => Self::Ipv6,
+            "tcp" => Self::Tcp,
+            "udp" => Self::Udp,
+            "frame.number" => Self::FrameNumber,
+            "frame.len" => Self::FrameLen,
+            "frame.marked" => Self::FrameMarked,
+            "frame.comment" => Self::FrameComment,
+            "eth.src" => Self::EthSrc,
+            "eth.dst" => Self::EthDst,
+            "eth.addr" => Self::EthAddr,
+            "ip.src" => Self::IpSrc,
+            "ip.dst" => Self::IpDst,
+            "ip.addr" => Self::IpAddr,
+            "ip.src_host" => Self::IpSrcHost,
+            "ip.dst_host" => Self::IpDstHost,
+            "ip.host" => Self::IpHost,
+            "ip.ttl" => Self::IpTtl,
+            "ip.version" => Self::IpVersion,
+            "port" => Self::Port,
+            "tcp.srcport" => Self::TcpSrcPort,
+            "tcp.dstport" => Self::TcpDstPort,
+            "tcp.port" => Self::TcpPort,
+            "tcp.seq" => Self::TcpSeq,
+            "tcp.ack" => Self::TcpAck,
+            "tcp.window_size" => Self::TcpWindow,
+            "tcp.len" => Self::TcpLen,
+            "tcp.flags.syn" => Self::TcpFlag(TcpFlags::SYN),
+            "tcp.flags.ack" => Self::TcpFlag(TcpFlags::ACK),
+            "tcp.flags.fin" => Self::TcpFlag(TcpFlags::FIN),
+            "tcp.flags.rst" | "tcp.flags.reset" => Self::TcpFlag(TcpFlags::RST),
+            "tcp.flags.psh" | "tcp.flags.push" => Self::TcpFlag(TcpFlags::PSH),
+            "tcp.flags.urg" => Self::TcpFlag(TcpFlags::URG),
+            "udp.srcport" => Self::UdpSrcPort,
+            "udp.dstport" => Self::UdpDstPort,
+            "udp.port" => Self::UdpPort,
+            "udp.length" => Self::UdpLen,
+            "icmp" => Self::Icmp,
+            "icmp.type" => Self::IcmpType,
+            "icmp.code" => Self::IcmpCode,
+            "icmpv6" => Self::Icmpv6,
+            "icmpv6.type" => Self::Icmpv6Type,
+            "icmpv6.code" => Self::Icmpv6Code,
+            "arp" => Self::Arp,
+            "dns" => Self::Dns,
+            "http" => Self::Http,
+            "tls" => Self::Tls,
+            "quic" => Self::Quic,
+            "quic.version" => Self::QuicVersion,
+            "quic.packet_type" => Self::QuicPacketType,
+            "quic.dcid" => Self::QuicDcid,
+            "quic.scid" => Self::QuicScid,
+            "quic.cid" => Self::QuicCid,
+            "quic.sni" => Self::QuicSni,
+            "quic.alpn" => Self::QuicAlpn,
+            "tcp.analysis.retransmission" => Self::TcpRetransmission,
+            "tcp.analysis.out_of_order" => Self::TcpOutOfOrder,
+            "tcp.analysis.lost_segment" => Self::TcpLostSegment,
+            "tcp.analysis.duplicate_ack" => Self::TcpDuplicateAck,
+            "tcp.analysis.zero_window" => Self::TcpZeroWindow,
+            "tcp.analysis.window_full" => Self::TcpWindowFull,
+            "tcp.analysis.flags" => Self::TcpAnalysisFlags,
+            "tcp.analysis.ack_rtt" => Self::TcpAckRtt,
+            "tcp.analysis.initial_rtt" => Self::TcpHandshakeRtt,
+            "direction" => Self::Direction,
+            "payload" => Self::Payload,
+            "proc.name" => Self::ProcessName,
+            "proc.pid" => Self::ProcessPid,
+            _ => return None,
+        })
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Self::Eth | Self::Ip | Self::Ipv4 | Self::Ipv6 | Self::Tcp | Self::Udp => Kind::Bool,
+            Self::TcpFlag(_) | Self::TcpRetransmission | Self::TcpOutOfOrder => Kind::Bool,
+            Self::TcpLostSegment | Self::TcpZeroWindow | Self::TcpWindowFull => Kind::Bool,
+            Self::TcpAnalysisFlags => Kind::Bool,
+            Self::Icmp | Self::Icmpv6 | Self::FrameMarked => Kind::Bool,
+            Self::Arp | Self::Dns | Self::Http | Self::Tls | Self::Quic => Kind::Bool,
+            Self::EthSrc | Self::EthDst | Self::EthAddr => Kind::Mac,
+            Self::IpSrc | Self::IpDst | Self::IpAddr => Kind::Ip,
+            Self::Direction | Self::Payload | Self::FrameComment => Kind::Text,
+            Self::ProcessName | Self::IpSrcHost | Self::IpDstHost | Self::IpHost => Kind::Text,
+            Self::QuicPacketType | Self::QuicDcid | Self::QuicScid | Self::QuicCid => Kind::Text,
+            Self::QuicSni | Self::QuicAlpn => Kind::Text,
+            _ => Kind::Number,
+        }
+    }
+
+    fn values(&self, packet: &ParsedPacket, names: &HostNames) -> Vec<Value> {
+        let tcp = match &packet.transport {
+            TransportPacket::Tcp(tcp) => Some(tcp),
+            _ => None,
+        };
+        let udp = match &packet.transport {
+            TransportPacket::Udp(udp) => Some(udp),
+            _ => None,
+        };
+        let number = |n: Option<u64>| n.map(Value::Number).into_iter().collect::<Vec<_>>();
+        let ip = |ip: Option<IpAddr>| ip.map(Value::Ip).into_iter().collect::<Vec<_>>();
+        let host = |ip: Option<IpAddr>| {
+            ip.and_then(|ip| names.lookup(ip))
+                .map(|name| Value::Text(name.to_string()))
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        // TCP analysis fields exist on TCP packets only
+        let analysis = |value: fn(&TcpAnalysis) -> Value| {
+            tcp.map(|_| value(&packet.tcp_analysis))
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        let quic = |value: fn(&QuicPacket) -> Vec<Value>| {
+            packet
+                .quic
+                .packets
+                .iter()
+                .flat_map(value)
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Self::Eth => vec![Value::Bool(true)],
+            Self::Ip => vec![Value::Bool(packet.net != NetworkPacket::Other)],
+            Self::Ipv4 => vec![Value::Bool(matches!(packet.net, NetworkPacket::Ipv4(_)))],
+            Self::Ipv6 => vec![Value::Bool(matches!(packet.net, NetworkPacket::Ipv6(_)))],
+            Self::Tcp => vec![Value::Bool(tcp.is_some())],
+            Self::Udp => vec![Value::Bool(udp.is_some())],
+            Self::FrameNumber => vec![Value::Number(packet.number as u64)],
+            Self::FrameLen => vec![Value::Number(packet.data.len() as u64)],
+            Self::FrameMarked => vec![Value::Bool(packet.marked)],
+            Self::FrameComment => packet.comment.iter().cloned().map(Value::Text).collect(),
+            Self::EthSrc => vec![Value::Mac(packet.eth.get_source())],
+            Self::EthDst => vec![Value::Mac(packet.eth.get_destination())],
+            Self::EthAddr => vec![
+                Value::Mac(packet.eth.get_source()),
+                Value::Mac(packet.eth.get_destination()),
+            ],
+            Self::IpSrc => ip(packet.get_source_ip()),
+            Self::IpDst => ip(packet.get_destination_ip()),
+            Self::IpAddr => [ip(packet.get_source_ip()), ip(packet.get_destination_ip())].concat(),
+            Self::IpSrcHost => host(packet.get_source_ip()),
+            Self::IpDstHost => host(packet.get_destination_ip()),
+            Self::IpHost => [
+                host(packet.get_source_ip()),
+                host(packet.get_destination_ip()),
+            ]
+            .concat(),
+            Self::IpTtl => number(packet.get_ttl().map(u64::from)),
+            Self::IpVersion => number(match packet.net {
+                NetworkPacket::Ipv4(_) => Some(4),
+                NetworkPacket::Ipv6(_) => Some(6),
+                NetworkPacket::Other => None,
+            }),
+            Self::Port => [
+                number(packet.get_source_port().map(u64::from)),
+                number(packet.get_destination_port().map(u64::from)),
+            ]
+            .concat(),
+            Self::TcpSrcPort => number(tcp.map(|t| t.get_source().into())),
+            Self::TcpDstPort => number(tcp.map(|t| t.get_destination().into())),
+            Self::TcpPort => [
+                number(tcp.map(|t| t.get_source().into())),
+                number(tcp.map(|t| t.get_destination().into())),
+            ]
+            .concat(),
+            Self::TcpSeq => number(tcp.map(|t| t.get_sequence().into())),
+            Self::TcpAck => number(tcp.map(|t| t.get_acknowledgement().into())),
+            Self::TcpWindow => number(tcp.map(|t| t.get_window().into())),
+            Self::TcpLen => number(tcp.map(|t| t.payload().len() as u64)),
+            Self::TcpFlag(flag) => tcp
+                .map(|t| Value::Bool(t.get_flags() & flag != 0))
+                .into_iter()
+                .collect(),
+            Self::UdpSrcPort => number(udp.map(|u| u.get_source().into())),
+            Self::UdpDstPort => number(udp.map(|u| u.get_destination().into())),
+            Self::UdpPort => [
+                number(udp.map(|u| u.get_source().into())),
+                number(udp.map(|u| u.get_destination().into())),
+            ]
+            .concat(),
+            Self::UdpLen => number(udp.map(|u| u.get_length().into())),
+            Self::Icmp => vec![Value::Bool(packet.get_icmp(false).is_some())],
+            Self::IcmpType => number(packet.get_icmp(false).map(|(t, _)| t.into())),
+            Self::IcmpCode => number(packet.get_icmp(false).map(|(_, c)| c.into())),
+            Self::Icmpv6 => vec![Value::Bool(packet.get_icmp(true).is_some())],
+            Self::Icmpv6Type => number(packet.get_icmp(true).map(|(t, _)| t.into())),
+            Self::Icmpv6Code => number(packet.get_icmp(true).map(|(_, c)| c.into())),
+            Self::Arp => vec![Value::Bool(packet.eth.get_ethertype() == EtherTypes::Arp)],
+            Self::Dns => vec![Value::Bool(packet.app_protocol() == Some(AppProtocol::Dns))],
+            Self::Http => vec![Value::Bool(
+                packet.app_protocol() == Some(AppProtocol::Http),
+            )],
+            Self::Tls => vec![Value::Bool(packet.app_protocol() == Some(AppProtocol::Tls))],
+            Self::Quic => vec![Value::Bool(!packet.quic.packets.is_empty())],
+            Self::QuicVersion => quic(|p| {
+                p.version
+                    .map(|v| Value::Number(v.into()))
+                    .into_iter()
+                    .collect()
+            }),
+            Self::QuicPacketType => quic(|p| vec![Value::Text(p.packet_type.to_string())]),
+            Self::QuicDcid => {
+                quic(|p| p.dcid.iter().map(|id| Value::Text(quic::hex(id))).collect())
+            }
+            Self::QuicScid => {
+                quic(|p| p.scid.iter().map(|id| Value::Text(quic::hex(id))).collect())
+            }
+            Self::QuicCid => quic(|p| {
+                p.dcid
+                    .iter()
+                    .chain(&p.scid)
+                    .map(|id| Value::Text(quic::hex(id)))
+                    .collect()
+            }),
+            Self::QuicSni => packet
+                .quic
+                .client_hello
+                .iter()
+                .filter_map(|hello| hello.server_name.clone())
+                .map(Value::Text)
+                .collect(),
+            Self::QuicAlpn => packet
+                .quic
+                .client_hello
+                .iter()
+                .flat_map(|hello| hello.alpn.iter().cloned().map(Value::Text))
+                .collect(),
+            Self::TcpRetransmission => analysis(|a| Value::Bool(a.retransmission)),
+            Self::TcpOutOfOrder => analysis(|a| Value::Bool(a.out_of_order)),
+            Self::TcpLostSegment => analysis(|a| Value::Bool(a.lost_segment)),
+            Self::TcpDuplicateAck => {
+                number(tcp.and(packet.tcp_analysis.duplicate_ack).map(u64::from))
+            }
+            Self::TcpZeroWindow => analysis(|a| Value::Bool(a.zero_window)),
+            Self::TcpWindowFull => analysis(|a| Value::Bool(a.window_full)),
+            Self::TcpAnalysisFlags => analysis(|a| Value::Bool(a.has_problem())),
+            Self::TcpAckRtt => number(
+                tcp.and(packet.tcp_analysis.ack_rtt)
+                    .map(|rtt| rtt.as_millis() as u64),
+            ),
+            Self::TcpHandshakeRtt => number(
+                tcp.and(packet.tcp_analysis.handshake_rtt)
+                    .map(|rtt| rtt.as_millis() as u64),
+            ),
+            Self::Direction => vec![Value::Text(packet.direction.to_string())],
+            Self::ProcessName => packet
+                .process
+                .iter()
+                .map(|p| Value::Text(p.name.clone()))
+                .collect(),
+            Self::ProcessPid => number(packet.process.as_ref().map(|p| p.pid.into())),
+            Self::Payload => {
+                let payload = match &packet.transport {
+                    TransportPacket::Tcp(tcp) => tcp.payload(),
+                    TransportPacket::Udp(udp) => udp.payload(),
+                    TransportPacket::Other => &[],
+                };
+                vec![Value::Text(String::from_utf8_lossy(payload).into_owned())]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(CmpOp),
+    Word(String),
+    Str(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Not => write!(f, "!"),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::Word(word) | Token::Str(word) => write!(f, "{}", word),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' {
+                    Token::LParen
+                } else {
+                    Token::RParen
+                });
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => s.extend(chars.next()),
+                        Some(c) => s.push(c),
+                        None => return Err(String::from("Unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '&' | '|' | '=' | '!' | '<' | '>' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let (token, double) = match (c, next) {
+                    ('&', Some('&')) => (Token::And, true),
+                    ('|', Some('|')) => (Token::Or, true),
+                    ('=', Some('=')) => (Token::Op(CmpOp::Eq), true),
+                    ('!', Some('=')) => (Token::Op(CmpOp::Ne), true),
+                    ('<', Some('=')) => (Token::Op(CmpOp::Le), true),
+                    ('>', Some('=')) => (Token::Op(CmpOp::Ge), true),
+                    ('!', _) => (Token::Not, false),
+                    ('<', _) => (Token::Op(CmpOp::Lt), false),
+                    ('>', _) => (Token::Op(CmpOp::Gt), false),
+                    ('=', _) => (Token::Op(CmpOp::Eq), false),
+                    _ => return Err(format!("Unexpected '{}'", c)),
+                };
+                if double {
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"&|=!<>".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "eq" => Token::Op(CmpOp::Eq),
+                    "ne" => Token::Op(CmpOp::Ne),
+                    "lt" => Token::Op(CmpOp::Lt),
+                    "le" => Token::Op(CmpOp::Le),
+                    "gt" => Token::Op(CmpOp::Gt),
+                    "ge" => Token::Op(CmpOp::Ge),
+                    "contains" => Token::Op(CmpOp::Contains),
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(String::from("Missing ')'")),
+                }
+            }
+            Some(Token::Word(name)) => {
+                let field = FilterField::from_name(&name.to_lowercase())
+                    .ok_or_else(|| format!("Unknown field '{}'", name))?;
+                let Some(Token::Op(op)) = self.peek().cloned() else {
+                    return Ok(Expr::Present(field));
+                };
+                self.next();
+                let raw = match self.next() {
+                    Some(Token::Word(raw)) | Some(Token::Str(raw)) => raw,
+                    _ => return Err(format!("Missing value after '{}'", name)),
+                };
+                Ok(Expr::Compare(
+                    field,
+                    op,
+                    Literal::parse(field.kind(), op, &raw)?,
+                ))
+            }
+            Some(token) => Err(format!("Unexpected '{}'", token)),
+            None => Err(String::from("Unexpected end of filter")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::DisplayFilter;
+    use crate::data::host_names::HostNames;
+    use crate::data::parsed_packet::ParsedPacket;
+    use crate::data::test_packets::{self, Segment};
+
+    fn matches(filter: &str, packet: &ParsedPacket) -> bool {
+        filter
+            .parse::<DisplayFilter>()
+            .unwrap_or_else(|e| panic!("{}: {}", filter, e))
+            .matches(packet, &HostNames::default())
+    }
+
+    fn syn() -> ParsedPacket {
+        test_packets::tcp(
+            "10.0.0.1:50000",
+            "93.184.216.34:443",
+            Segment {
+                seq: 100,
+                flags: TcpFlags::SYN,
+                ..Segment::default()
+            },
+        )
+    }
+
+    #[test]
+    fn fields_and_comparisons() {
+        let packet = syn();
+        for filter in [
+            "tcp",
+            "ip && ipv4 && !ipv6 && !udp",
+            "tcp.port == 443",
+            "tcp.dstport == 443 && tcp.srcport > 1024",
+            "tcp.seq == 100 && tcp.seq == 0x64",
+            "tcp.flags.syn && !tcp.flags.ack",
+            "ip.addr == 93.184.216.0/24",
+            "ip.src == 10.0.0.1",
+            "ip.ttl >= 64 && ip.version == 4",
+            "eth.src == 02:00:00:00:00:01",
+            "direction == outbound",
+            "frame.len == 54",
+        ] {
+            assert!(matches(filter, &packet), "{}", filter);
+        }
+        for filter in [
+            "udp",
+            "tcp.port == 80",
+            "ip.dst == 10.0.0.0/8",
+            "tcp.analysis.flags",
+            "frame.marked",
+        ] {
+            assert!(!matches(filter, &packet), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn repeated_fields() {
+        let packet = syn();
+        // `!=` holds only if no occurrence is equal, like Wireshark's `!==`
+        assert!(!matches("ip.addr != 10.0.0.1", &packet));
+        assert!(matches("ip.addr != 10.9.9.9", &packet));
+        assert!(matches("port == 50000 && port == 443", &packet));
+    }
+
+    #[test]
+    fn precedence_and_words() {
+        let packet = syn();
+        assert!(matches("udp || tcp && tcp.port == 443", &packet));
+        assert!(!matches("(udp || tcp) && tcp.port == 80", &packet));
+        assert!(matches("not udp and tcp.port eq 443", &packet));
+        assert!(matches("!!tcp", &packet));
+    }
+
+    #[test]
+    fn payload_contains() {
+        let packet = test_packets::udp("10.0.0.1:40000", "10.0.0.2:9999", b"Hello World");
+        assert!(matches(r#"payload contains "hello""#, &packet));
+        assert!(matches("udp.length == 19 && udp.dstport == 9999", &packet));
+        assert!(!matches(r#"payload contains "bye""#, &packet));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (filter, error) in [
+            ("tcp.port ==", "Missing value after 'tcp.port'"),
+            ("bogus", "Unknown field 'bogus'"),
+            ("(tcp", "Missing ')'"),
+            ("tcp.port == http", "'http' is not a number"),
+            (
+                "ip.addr == 300.1.1.1",
+                "'300.1.1.1' is not an IP address or network",
+            ),
+            ("tcp )", "Unexpected ')'"),
+            (r#"payload contains "x"#, "Unterminated string"),
+            ("", "Unexpected end of filter"),
+        ] {
+            assert_eq!(filter.parse::<DisplayFilter>().unwrap_err(), error);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use pnet::util::MacAddr;
+
+use super::column::SortOrder;
+use super::direction::Direction;
+use super::geoip::{self, GeoInfo};
+use super::host_names::HostNames;
+use super::oui;
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+
+/// Layer at which traffic is attributed to endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EndpointKind {
+    Ethernet,
+    #[default]
+    Ip,
+    Tcp,
+    Udp,
+    /// Local processes owning the sockets, from live captures on Linux.
+    Process,
+}
+
+impl EndpointKind {
+    pub const ALL: [EndpointKind; 5] = [
+        EndpointKind::Ethernet,
+        EndpointKind::Ip,
+        EndpointKind::Tcp,
+        EndpointKind::Udp,
+        EndpointKind::Process,
+    ];
+
+    /// Source and destination address of the packet at this layer, whether it sent the packet.
+    /// A process is only one side of a packet, the one owning the local socket.
+    fn addresses(&self, packet: &ParsedPacket) -> [Option<(bool, Address)>; 2] {
+        let both = |source, destination| [Some((true, source)), Some((false, destination))];
+        let ips = packet.get_source_ip().zip(packet.get_destination_ip());
+        let ports = packet.get_source_port().zip(packet.get_destination_port());
+        match (self, &packet.transport) {
+            (EndpointKind::Ethernet, _) => both(
+                Address::Mac(packet.eth.get_source()),
+                Address::Mac(packet.eth.get_destination()),
+            ),
+            (EndpointKind::Ip, _) => match ips {
+                Some((source, destination)) => both(Address::Ip(source), Address::Ip(destination)),
+                None => [None, None],
+            },
+            (EndpointKind::Process, _) => match &packet.process {
+                Some(process) => {
+                    let sent = packet.get_source_ip() == Some(process.local.ip())
+                        && packet.get_source_port() == Some(process.local.port());
+                    [Some((sent, Address::Process(process.pid))), None]
+                }
+                None => [None, None],
+            },
+            (EndpointKind::Tcp, TransportPacket::Tcp(_))
+            | (EndpointKind::Udp, TransportPacket::Udp(_)) => match ips.zip(ports) {
+                Some(((source, destination), (source_port, destination_port))) => {
+                    let socket = |ip, port| match self {
+                        EndpointKind::Tcp => Address::Tcp(SocketAddr::new(ip, port)),
+                        _ => Address::Udp(SocketAddr::new(ip, port)),
+                    };
+                    both(
+                        socket(source, source_port),
+                        socket(destination, destination_port),
+                    )
+                }
+                None => [None, None],
+            },
+            (EndpointKind::Tcp | EndpointKind::Udp, _) => [None, None],
+        }
+    }
+}
+
+impl fmt::Display for EndpointKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EndpointKind::Ethernet => write!(f, "Ethernet"),
+            EndpointKind::Ip => write!(f, "IP"),
+            EndpointKind::Tcp => write!(f, "TCP"),
+            EndpointKind::Udp => write!(f, "UDP"),
+            EndpointKind::Process => write!(f, "Process"),
+        }
+    }
+}
+
+/// Address of an endpoint at its layer. Names are only looked up when shown,
+/// many of them are learned while the capture runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Address {
+    Mac(MacAddr),
+    Ip(IpAddr),
+    Tcp(SocketAddr),
+    Udp(SocketAddr),
+    Process(u32),
+}
+
+impl Address {
+    fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Address::Ip(ip) => Some(*ip),
+            Address::Tcp(socket) | Address::Udp(socket) => Some(socket.ip()),
+            Address::Mac(_) | Address::Process(_) => None,
+        }
+    }
+}
+
+/// Traffic of one address, sent and received from its own point of view.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub address: Address,
+    /// Name of process endpoints, as of their first packet.
+    process_name: Option<String>,
+    pub tx_packets: usize,
+    pub tx_bytes: usize,
+    pub rx_packets: usize,
+    pub rx_bytes: usize,
+    /// Bytes of inbound packets this endpoint sent to the capturing host.
+    pub inbound_bytes: usize,
+    /// Bytes of outbound packets the capturing host sent to this endpoint.
+    pub outbound_bytes: usize,
+    pub first_seen: Duration,
+    pub last_seen: Duration,
+}
+
+impl Endpoint {
+    pub fn packets(&self) -> usize {
+        self.tx_packets + self.rx_packets
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.tx_bytes + self.rx_bytes
+    }
+
+    /// Location of IP endpoints, if the GeoIP databases know it. Looked up when shown,
+    /// as the databases may still be loading when the endpoint is first seen.
+    pub fn geo(&self) -> Option<GeoInfo> {
+        self.address.ip().and_then(geoip::lookup)
+    }
+
+    /// The address as shown, with the names known by now.
+    pub fn label(&self, names: &HostNames) -> String {
+        match self.address {
+            Address::Mac(mac) => oui::describe(mac),
+            Address::Ip(ip) => names.describe(ip),
+            Address::Tcp(socket) | Address::Udp(socket) => socket.to_string(),
+            Address::Process(pid) => {
+                format!(
+                    "{} ({})",
+                    self.process_name.as_deref().unwrap_or_default(),
+                    pid
+                )
+            }
+        }
+    }
+
+    /// Display filter selecting the packets of this endpoint.
+    pub fn filter(&self) -> String {
+        match self.address {
+            Address::Mac(mac) => format!("eth.addr == {}", mac),
+            Address::Ip(ip) => format!("ip.addr == {}", ip),
+            Address::Tcp(socket) => {
+                format!(
+                    "ip.addr == {} && tcp.port == {}",
+                    socket.ip(),
+                    socket.port()
+                )
+            }
+            Address::Udp(socket) => {
+                format!(
+                    "ip.addr == {} && udp.port == {}",
+                    socket.ip(),
+                    socket.port()
+                )
+            }
+            Address::Process(pid) => format!("proc.pid == {}", pid),
+        }
+    }
+}
+
+/// Sortable columns of the endpoints table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointColumn {
+    Address,
+    Packets,
+    Bytes,
+    TxPackets,
+    TxBytes,
+    RxPackets,
+    RxBytes,
+    Inbound,
+    Outbound,
+    FirstSeen,
+    LastSeen,
+    Location,
+    AutonomousSystem,
+}
+
+impl EndpointColumn {
+    pub const ALL: [EndpointColumn; 13] = [
+        EndpointColumn::Address,
+        EndpointColumn::Packets,
+        EndpointColumn::Bytes,
+        EndpointColumn::TxPackets,
+        EndpointColumn::TxBytes,
+        EndpointColumn::RxPackets,
+        EndpointColumn::RxBytes,
+        EndpointColumn::Inbound,
+        EndpointColumn::Outbound,
+        EndpointColumn::FirstSeen,
+        EndpointColumn::LastSeen,
+        EndpointColumn::Location,
+        EndpointColumn::AutonomousSystem,
+    ];
+
+    fn key(&self, endpoint: &Endpoint) -> u128 {
+        match self {
+            EndpointColumn::Address
+            | EndpointColumn::Location
+            | EndpointColumn::AutonomousSystem => 0,
+            EndpointColumn::Packets => endpoint.packets() as u128,
+            EndpointColumn::Bytes => endpoint.bytes() as u128,
+            EndpointColumn::TxPackets => endpoint.tx_packets as u128,
+            EndpointColumn::TxBytes => endpoint.tx_bytes as u128,
+            EndpointColumn::RxPackets => endpoint.rx_packets as u128,
+            EndpointColumn::RxBytes => endpoint.rx_bytes as u128,
+            EndpointColumn::Inbound => endpoint.inbound_bytes as u128,
+            EndpointColumn::Outbound => endpoint.outbound_bytes as u128,
+            EndpointColumn::FirstSeen => endpoint.first_seen.as_nanos(),
+            EndpointColumn::LastSeen => endpoint.last_seen.as_nanos(),
+        }
+    }
+}
+
+impl fmt::Display for EndpointColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EndpointColumn::Address => "Address",
+            EndpointColumn::Packets => "Packets",
+            EndpointColumn::Bytes => "Bytes",
+            EndpointColumn::TxPackets => "Tx Packets",
+            EndpointColumn::TxBytes => "Tx Bytes",
+            EndpointColumn::RxPackets => "Rx Packets",
+            EndpointColumn::RxBytes => "Rx Bytes",
+            EndpointColumn::Inbound => "Inbound",
+            EndpointColumn::Outbound => "Outbound",
+            EndpointColumn::FirstSeen => "First Seen",
+            EndpointColumn::LastSeen => "Last Seen",
+            EndpointColumn::Location => "Location",
+            EndpointColumn::AutonomousSystem => "AS",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Traffic per endpoint of one kind, counted as the packets arrive.
+#[derive(Debug, Default)]
+pub struct EndpointTable {
+    kind: EndpointKind,
+    endpoints: HashMap<Address, Endpoint>,
+}
+
+impl EndpointTable {
+    pub fn new(kind: EndpointKind) -> Self {
+        Self {
+            kind,
+            endpoints: HashMap::new(),
+        }
+    }
+
+    pub fn kind(&self) -> EndpointKind {
+        self.kind
+    }
+
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        let bytes = packet.data.len();
+        for (sent, address) in self.kind.addresses(packet).into_iter().flatten() {
+            let endpoint = self.endpoints.entry(address).or_insert_with(|| Endpoint {
+                address,
+                process_name: packet.process.as_ref().map(|p| p.name.clone()),
+                tx_packets: 0,
+                tx_bytes: 0,
+                rx_packets: 0,
+                rx_bytes: 0,
+                inbound_bytes: 0,
+                outbound_bytes: 0,
+                first_seen: packet.timestamp,
+                last_seen: packet.timestamp,
+            });
+            if sent {
+                endpoint.tx_packets += 1;
+                endpoint.tx_bytes += bytes;
+            } else {
+                endpoint.rx_packets += 1;
+                endpoint.rx_bytes += bytes;
+            }
+            match (packet.direction, sent) {
+                (Direction::Inbound, true) => endpoint.inbound_bytes += bytes,
+                (Direction::Outbound, false) => endpoint.outbound_bytes += bytes,
+                _ => {}
+            }
+            endpoint.first_seen = endpoint.first_seen.min(packet.timestamp);
+            endpoint.last_seen = endpoint.last_seen.max(packet.timestamp);
+        }
+    }
+
+    /// Takes back a packet counted before, once it is trimmed from the store.
+    /// Endpoints left without packets are dropped.
+    pub fn remove(&mut self, packet: &ParsedPacket) {
+        let bytes = packet.data.len();
+        for (sent, address) in self.kind.addresses(packet).into_iter().flatten() {
+            let Some(endpoint) = self.endpoints.get_mut(&address) else {
+                continue;
+            };
+            if sent {
+                endpoint.tx_packets = endpoint.tx_packets.saturating_sub(1);
+                endpoint.tx_bytes = endpoint.tx_bytes.saturating_sub(bytes);
+            } else {
+                endpoint.rx_packets = endpoint.rx_packets.saturating_sub(1);
+                endpoint.rx_bytes = endpoint.rx_bytes.saturating_sub(bytes);
+            }
+            match (packet.direction, sent) {
+                (Direction::Inbound, true) => {
+                    endpoint.inbound_bytes = endpoint.inbound_bytes.saturating_sub(bytes)
+                }
+                (Direction::Outbound, false) => {
+                    endpoint.outbound_bytes = endpoint.outbound_bytes.saturating_sub(bytes)
+                }
+                _ => {}
+            }
+            // the oldest packets are trimmed first, the next one is no older
+            endpoint.first_seen = endpoint
+                .first_seen
+                .max(packet.timestamp)
+                .min(endpoint.last_seen);
+            if endpoint.packets() == 0 {
+                self.endpoints.remove(&address);
+            }
+        }
+    }
+
+    /// The endpoints with their labels, sorted by `sort`.
+    pub fn sorted(
+        &self,
+        sort: (EndpointColumn, SortOrder),
+        names: &HostNames,
+    ) -> Vec<(String, &Endpoint)> {
+        let mut endpoints = self
+            .endpoints
+            .values()
+            .map(|endpoint| (endpoint.label(names), endpoint))
+            .collect::<Vec<_>>();
+        let (column, order) = sort;
+        endpoints.sort_by(|(a_label, a), (b_label, b)| {
+            let ordering = match column {
+                EndpointColumn::Address => a_label.cmp(b_label),
+                EndpointColumn::Location => {
+                    let place = |e: &Endpoint| e.geo().as_ref().map(GeoInfo::place);
+                    place(a).cmp(&place(b))
+                }
+                EndpointColumn::AutonomousSystem => {
+                    let system = |e: &Endpoint| e.geo().as_ref().map(GeoInfo::autonomous_system);
+                    system(a).cmp(&system(b))
+                }
+                column => column.key(a).cmp(&column.key(b)),
+            };
+            match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            }
+        });
+        endpoints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_packets::{self, Segment};
+
+    #[test]
+    fn trimmed_packets_leave_their_endpoints() {
+        let at = |at| Segment {
+            at,
+            ..Segment::default()
+        };
+        let packets = [
+            test_packets::tcp("10.0.0.1:1000", "10.0.0.2:80", at(1)),
+            test_packets::tcp("10.0.0.2:80", "10.0.0.1:1000", at(2)),
+            test_packets::tcp("10.0.0.1:1001", "10.0.0.3:80", at(3)),
+        ];
+        let mut table = EndpointTable::new(EndpointKind::Ip);
+        packets.iter().for_each(|packet| table.add(packet));
+        table.remove(&packets[0]);
+        table.remove(&packets[1]);
+
+        let mut trimmed = EndpointTable::new(EndpointKind::Ip);
+        trimmed.add(&packets[2]);
+        let counts = |table: &EndpointTable| {
+            let sort = (EndpointColumn::Address, SortOrder::Ascending);
+            table
+                .sorted(sort, &HostNames::default())
+                .into_iter()
+                .map(|(label, e)| {
+                    let counts = [e.tx_packets, e.tx_bytes, e.rx_packets, e.rx_bytes];
+                    let traffic = [e.inbound_bytes, e.outbound_bytes];
+                    (label, counts, traffic, e.first_seen, e.last_seen)
+                })
+                .collect::<Vec<_>>()
+        };
+        // the first packet left is unknown, the last trimmed one is no later
+        let host = Address::Ip(test_packets::HOST.parse().unwrap());
+        trimmed.endpoints.get_mut(&host).unwrap().first_seen = Duration::from_millis(2);
+        assert_eq!(counts(&table), counts(&trimmed));
+        assert_eq!(table.endpoints.len(), 2);
+    }
+}
@@ -0,0 +1,410 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use pnet::packet::{tcp::TcpFlags, Packet};
+
+use crate::utils::time_formatter::format_rfc3339;
+
+use super::column::{ColumnLayout, Field};
+use super::har;
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+use super::pcap::PcapngWriter;
+use super::time_format::{TimeFormat, TimeOrigin};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Ndjson,
+    Har,
+    Pcapng,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Csv,
+        ExportFormat::Json,
+        ExportFormat::Ndjson,
+        ExportFormat::Har,
+        ExportFormat::Pcapng,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Har => "har",
+            ExportFormat::Pcapng => "pcapng",
+        }
+    }
+
+    /// Guesses the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "jsonl" => Some(ExportFormat::Ndjson),
+            e => Self::ALL.into_iter().find(|f| f.extension() == e),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Csv => write!(f, "CSV"),
+            ExportFormat::Json => write!(f, "JSON"),
+            ExportFormat::Ndjson => write!(f, "NDJSON"),
+            ExportFormat::Har => write!(f, "HAR"),
+            ExportFormat::Pcapng => write!(f, "pcapng"),
+        }
+    }
+}
+
+enum Value {
+    Null,
+    Bool(bool),
+    Number(u64),
+    Text(String),
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+macro_rules! number_value {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Value {
+            fn from(value: $t) -> Self {
+                Value::Number(value as u64)
+            }
+        })*
+    };
+}
+
+number_value!(u8, u16, u32, u64, usize);
+
+/// Names of the exported fields: the visible columns as shown in the list,
+/// followed by the dissected fields named like their display filter fields.
+fn header(layout: &ColumnLayout) -> Vec<String> {
+    visible_fields(layout)
+        .map(|field| field.to_string())
+        .chain(DISSECTED.iter().map(|name| name.to_string()))
+        .collect()
+}
+
+const DISSECTED: [&str; 22] = [
+    "frame.number",
+    "frame.time",
+    "frame.len",
+    "direction",
+    "eth.src",
+    "eth.dst",
+    "ip.version",
+    "ip.src",
+    "ip.dst",
+    "ip.ttl",
+    "protocol",
+    "srcport",
+    "dstport",
+    "tcp.flags",
+    "tcp.flags.syn",
+    "tcp.flags.ack",
+    "tcp.flags.fin",
+    "tcp.flags.rst",
+    "tcp.seq",
+    "tcp.ack",
+    "tcp.window_size",
+    "payload.len",
+];
+
+fn visible_fields(layout: &ColumnLayout) -> impl Iterator<Item = Field> + '_ {
+    layout
+        .visible()
+        .map(|(_, column)| column.field)
+        .filter(|&field| field != Field::Payload)
+}
+
+/// Timestamp column of the exported packets in the selected time format,
+/// relative ones measured from the packet exported before.
+#[derive(Debug, Clone, Copy)]
+pub struct Timestamps {
+    pub format: TimeFormat,
+    pub origin: TimeOrigin,
+}
+
+impl Timestamps {
+    fn next(&mut self, packet: &ParsedPacket) -> String {
+        let text = self.format.format(packet.timestamp, &self.origin);
+        self.origin.previous = Some(packet.timestamp);
+        text
+    }
+}
+
+fn record(packet: &ParsedPacket, layout: &ColumnLayout, timestamps: &mut Timestamps) -> Vec<Value> {
+    let tcp = match &packet.transport {
+        TransportPacket::Tcp(tcp) => Some(tcp),
+        _ => None,
+    };
+    let flag = |mask: u8| tcp.map(|t| t.get_flags() & mask != 0);
+    let payload_len = match &packet.transport {
+        TransportPacket::Tcp(tcp) => Some(tcp.payload().len()),
+        TransportPacket::Udp(udp) => Some(udp.payload().len()),
+        TransportPacket::Other => None,
+    };
+    let ip_version = packet
+        .get_source_ip()
+        .map(|ip| if ip.is_ipv4() { 4u8 } else { 6 });
+
+    let time = timestamps.next(packet);
+    let mut values: Vec<Value> = visible_fields(layout)
+        .map(|field| match field {
+            Field::Timestamp => time.clone().into(),
+            field => field.text(packet, None).into(),
+        })
+        .collect();
+    values.extend([
+        packet.number.into(),
+        format_rfc3339(packet.timestamp).into(),
+        packet.data.len().into(),
+        packet.direction.to_string().into(),
+        packet.eth.get_source().to_string().into(),
+        packet.eth.get_destination().to_string().into(),
+        ip_version.into(),
+        packet.get_source_ip().map(|ip| ip.to_string()).into(),
+        packet.get_destination_ip().map(|ip| ip.to_string()).into(),
+        packet.get_ttl().into(),
+        packet.transport.to_string().into(),
+        packet.get_source_port().into(),
+        packet.get_destination_port().into(),
+        packet.get_tcp_flags().into(),
+        flag(TcpFlags::SYN).into(),
+        flag(TcpFlags::ACK).into(),
+        flag(TcpFlags::FIN).into(),
+        flag(TcpFlags::RST).into(),
+        tcp.map(|t| t.get_sequence()).into(),
+        tcp.map(|t| t.get_acknowledgement()).into(),
+        tcp.map(|t| t.get_window()).into(),
+        payload_len.into(),
+    ]);
+    values
+}
+
+/// Serializes the packets with the fields of the visible columns and the dissected fields.
+/// HAR instead contains the HTTP requests and responses carried by the packets,
+/// pcapng the frames themselves with marks and comments as packet comments.
+pub fn write<'a>(
+    out: &mut impl Write,
+    format: ExportFormat,
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+    layout: &ColumnLayout,
+    timestamps: Timestamps,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => write_csv(out, packets, layout, timestamps),
+        ExportFormat::Json => write_json(out, packets, layout, timestamps),
+        ExportFormat::Ndjson => write_ndjson(out, packets, layout, timestamps),
+        ExportFormat::Har => har::write(out, packets),
+        ExportFormat::Pcapng => write_pcapng(out, packets),
+    }
+}
+
+fn write_csv<'a>(
+    out: &mut impl Write,
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+    layout: &ColumnLayout,
+    mut timestamps: Timestamps,
+) -> io::Result<()> {
+    let names = header(layout).into_iter().map(|name| csv_field(&name));
+    writeln!(out, "{}", names.collect::<Vec<_>>().join(","))?;
+    for packet in packets {
+        let values = record(packet, layout, &mut timestamps)
+            .into_iter()
+            .map(|value| match value {
+                Value::Null => String::new(),
+                Value::Bool(b) => b.to_string(),
+                Value::Number(n) => n.to_string(),
+                Value::Text(text) => csv_field(&text),
+            });
+        writeln!(out, "{}", values.collect::<Vec<_>>().join(","))?;
+    }
+    Ok(())
+}
+
+fn write_json<'a>(
+    out: &mut impl Write,
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+    layout: &ColumnLayout,
+    mut timestamps: Timestamps,
+) -> io::Result<()> {
+    let header = header(layout);
+    write!(out, "[")?;
+    for (i, packet) in packets.into_iter().enumerate() {
+        let separator = if i == 0 { "\n  " } else { ",\n  " };
+        write!(
+            out,
+            "{}{}",
+            separator,
+            json_object(&header, record(packet, layout, &mut timestamps))
+        )?;
+    }
+    writeln!(out, "\n]")
+}
+
+fn write_ndjson<'a>(
+    out: &mut impl Write,
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+    layout: &ColumnLayout,
+    mut timestamps: Timestamps,
+) -> io::Result<()> {
+    let header = header(layout);
+    for packet in packets {
+        writeln!(
+            out,
+            "{}",
+            json_object(&header, record(packet, layout, &mut timestamps))
+        )?;
+    }
+    Ok(())
+}
+
+fn write_pcapng<'a>(
+    out: &mut impl Write,
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+) -> io::Result<()> {
+    let mut writer = PcapngWriter::new(out)?;
+    for packet in packets {
+        writer.write_packet(packet.timestamp, &packet.data, &packet.pcapng_comments())?;
+    }
+    writer.flush()
+}
+
+/// Quotes a field if it contains a separator, quote or line break (RFC 4180).
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_object(names: &[String], values: Vec<Value>) -> String {
+    let members = names.iter().zip(values).map(|(name, value)| {
+        let value = match value {
+            Value::Null => String::from("null"),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Text(text) => json_string(&text),
+        };
+        format!("{}:{}", json_string(name), value)
+    });
+    format!("{{{}}}", members.collect::<Vec<_>>().join(","))
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::data::process::Process;
+    use crate::data::test_packets;
+
+    /// Text that needs every kind of escaping, as a process name.
+    const NAME: &str = "say \"hi\", bye\r\nnext\t\u{1}ünï 名前";
+
+    fn export(format: ExportFormat) -> String {
+        let mut packet = test_packets::udp("10.0.0.1:5000", "192.0.2.7:53", &[]);
+        packet.process = Some(Process {
+            pid: 42,
+            name: String::from(NAME),
+            local: "10.0.0.1:5000".parse().unwrap(),
+        });
+        let mut layout = ColumnLayout::default();
+        for field in Field::ALL {
+            layout.set_visible(field, matches!(field, Field::Process | Field::Pid));
+        }
+        let timestamps = Timestamps {
+            format: TimeFormat::SinceStart,
+            origin: TimeOrigin {
+                start: Duration::ZERO,
+                previous: None,
+                reference: None,
+            },
+        };
+        let mut out = vec![];
+        write(&mut out, format, [&packet, &packet], &layout, timestamps).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("plain ünï"), "plain ünï");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+
+        let csv = export(ExportFormat::Csv);
+        assert!(csv.starts_with("Process,PID,frame.number,"));
+        let record = "\"say \"\"hi\"\", bye\r\nnext\t\u{1}ünï 名前\",42,0,";
+        assert_eq!(csv.matches(record).count(), 2, "{}", csv);
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_string("ünï 名前"), "\"ünï 名前\"");
+        assert_eq!(json_string("a\\b"), "\"a\\\\b\"");
+        assert_eq!(json_string("\u{0}\u{1f}\u{7f}"), "\"\\u0000\\u001f\u{7f}\"");
+
+        let name = "\"Process\":\"say \\\"hi\\\", bye\\r\\nnext\\t\\u0001ünï 名前\"";
+        let json = export(ExportFormat::Json);
+        assert!(json.starts_with("[\n  {"));
+        assert!(json.ends_with("}\n]\n"));
+        assert_eq!(json.matches(name).count(), 2, "{}", json);
+        assert!(json.contains("\"PID\":\"42\""));
+        assert!(json.contains("\"tcp.seq\":null"));
+
+        // one object per line, the escaped line breaks do not split them
+        let ndjson = export(ExportFormat::Ndjson);
+        let lines = ndjson.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            assert!(line.starts_with('{') && line.ends_with('}'));
+            assert!(line.contains(name));
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::mmdb::Database;
+
+/// The databases once opened, until then only the special ranges are known.
+static DATABASES: OnceLock<Vec<Database>> = OnceLock::new();
+/// Answers looked up in the databases so far.
+static CACHE: Lazy<RwLock<HashMap<IpAddr, Option<GeoInfo>>>> = Lazy::new(Default::default);
+/// Addresses cached at most, the cache starts over beyond.
+const CACHE_SIZE: usize = 1 << 16;
+
+/// What is known about where an address is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoInfo {
+    /// Addresses that never leave the local network are labeled instead of looked up.
+    Special(&'static str),
+    Located(Location),
+}
+
+/// Country, city and autonomous system of a public address, as far as the databases tell.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u64>,
+    pub as_organization: Option<String>,
+}
+
+impl GeoInfo {
+    /// Place of the address, e.g. `Berlin, Germany (DE)`.
+    pub fn place(&self) -> String {
+        match self {
+            GeoInfo::Special(label) => label.to_string(),
+            GeoInfo::Located(location) => {
+                let country = match (&location.country, &location.country_code) {
+                    (Some(name), Some(code)) => Some(format!("{} ({})", name, code)),
+                    (name, code) => name.clone().or(code.clone()),
+                };
+                [location.city.clone(), country]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        }
+    }
+
+    /// Autonomous system of the address, e.g. `AS3320 Deutsche Telekom AG`.
+    pub fn autonomous_system(&self) -> String {
+        match self {
+            GeoInfo::Special(label) => label.to_string(),
+            GeoInfo::Located(location) => match (location.asn, &location.as_organization) {
+                (Some(asn), Some(organization)) => format!("AS{} {}", asn, organization),
+                (Some(asn), None) => format!("AS{}", asn),
+                (None, organization) => organization.clone().unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Short form for table cells, the city with the country code.
+    pub fn short_place(&self) -> String {
+        match self {
+            GeoInfo::Special(label) => label.to_string(),
+            GeoInfo::Located(location) => [location.city.clone(), location.country_code.clone()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+impl fmt::Display for GeoInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoInfo::Special(label) => write!(f, "{}", label),
+            GeoInfo::Located(_) => {
+                let parts = [self.place(), self.autonomous_system()]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>();
+                write!(f, "{}", parts.join(", "))
+            }
+        }
+    }
+}
+
+/// Directories searched for `.mmdb` files: the postal config directory and
+/// the default locations of MaxMind's geoipupdate.
+fn directories() -> Vec<PathBuf> {
+    let mut directories = dirs::config_dir()
+        .map(|dir| vec![dir.join("postal")])
+        .unwrap_or_default();
+    directories.extend(
+        [
+            "/usr/share/GeoIP",
+            "/usr/local/share/GeoIP",
+            "/var/lib/GeoIP",
+        ]
+        .into_iter()
+        .map(PathBuf::from),
+    );
+    directories
+}
+
+fn is_mmdb(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("mmdb"))
+}
+
+/// Opens the `.mmdb` files at `path`, a file or a directory, and in the default directories.
+/// Reading them takes a while, so this is done once and off the UI thread.
+/// Returns what was loaded or skipped, to be reported.
+pub fn open(path: Option<&Path>) -> Vec<String> {
+    let mut files = vec![];
+    let directories = path
+        .filter(|path| path.is_dir())
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(directories());
+    files.extend(path.filter(|path| !path.is_dir()).map(Path::to_path_buf));
+    for directory in directories {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        files.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| is_mmdb(path)),
+        );
+    }
+
+    let mut databases = vec![];
+    let mut report = vec![];
+    for file in files {
+        match Database::open(&file) {
+            Ok(database) => {
+                report.push(format!(
+                    "Loaded {} from {}",
+                    database.database_type,
+                    file.display()
+                ));
+                databases.push(database);
+            }
+            Err(e) => report.push(format!("Skipping GeoIP database {}", e)),
+        }
+    }
+    if DATABASES.set(databases).is_err() {
+        return vec![];
+    }
+    report
+}
+
+/// Label of addresses that are not routed on the internet.
+fn special(ip: IpAddr) -> Option<&'static str> {
+    if ip.is_loopback() {
+        return Some("Loopback");
+    }
+    if ip.is_unspecified() {
+        return Some("Unspecified");
+    }
+    if ip.is_multicast() {
+        return Some("Multicast");
+    }
+    match ip {
+        IpAddr::V4(v4) if v4.is_private() => Some("Private"),
+        IpAddr::V4(v4) if v4.is_link_local() => Some("Link-local"),
+        IpAddr::V4(v4) if v4.is_broadcast() => Some("Broadcast"),
+        IpAddr::V6(v6) if v6.segments()[0] & 0xfe00 == 0xfc00 => Some("Private"),
+        IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80 => Some("Link-local"),
+        _ => None,
+    }
+}
+
+/// Where the address is, `None` for public addresses the databases do not know,
+/// or if no database is open.
+pub fn lookup(ip: IpAddr) -> Option<GeoInfo> {
+    if let Some(label) = special(ip) {
+        return Some(GeoInfo::Special(label));
+    }
+    let databases = DATABASES.get()?;
+    if let Some(cached) = CACHE.read().ok().and_then(|cache| cache.get(&ip).cloned()) {
+        return cached;
+    }
+    let info = locate(databases, ip);
+    if let Ok(mut cache) = CACHE.write() {
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(ip, info.clone());
+    }
+    info
+}
+
+fn locate(databases: &[Database], ip: IpAddr) -> Option<GeoInfo> {
+    let mut location = Location::default();
+    for record in databases.iter().filter_map(|database| database.lookup(ip)) {
+        let text = |path: &[&str]| {
+            record
+                .get(path)
+                .and_then(|v| v.as_str().map(str::to_string))
+        };
+        let country = |key: &str| (text(&[key, "iso_code"]), text(&[key, "names", "en"]));
+        if location.country_code.is_none() {
+            // the country of the registration stands in where the location is unknown
+            let (code, name) = match country("country") {
+                (None, None) => country("registered_country"),
+                found => found,
+            };
+            location.country_code = code;
+            location.country = name;
+        }
+        location.city = location.city.or(text(&["city", "names", "en"]));
+        location.asn = location.asn.or(record
+            .get(&["autonomous_system_number"])
+            .and_then(|v| v.as_u64()));
+        location.as_organization = location
+            .as_organization
+            .or(text(&["autonomous_system_organization"]));
+    }
+    (location != Location::default()).then_some(GeoInfo::Located(location))
+}
@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use pnet::packet::{tcp::TcpFlags, Packet};
+
+use crate::utils::time_formatter::format_rfc3339;
+
+use super::export::json_string;
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+
+const METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "PATCH", "CONNECT", "TRACE",
+];
+
+/// One direction of a TCP connection, reassembled in sequence order.
+#[derive(Default)]
+struct Stream {
+    /// (sequence number, capture time, payload) of every data segment.
+    segments: Vec<(u32, Duration, Vec<u8>)>,
+    /// Sequence number of the first payload byte, known when the SYN was captured.
+    isn: Option<u32>,
+}
+
+impl Stream {
+    /// The contiguous bytes from the start of the stream and, by offset, the capture time
+    /// by which the stream was complete up to there. Bytes that overtook a missing segment
+    /// only count once it arrived. Retransmitted bytes are dropped, reassembly stops at a gap.
+    fn reassemble(mut self) -> (Vec<u8>, Vec<(usize, Duration)>) {
+        // without the SYN the stream starts at the lowest sequence number, compared
+        // relative to the first segment as the numbers may wrap around
+        let first = self.segments.first().map(|s| s.0);
+        let Some(base) = self.isn.or_else(|| {
+            let first = first?;
+            self.segments
+                .iter()
+                .map(|s| s.0)
+                .min_by_key(|&s| s.wrapping_sub(first) as i32)
+        }) else {
+            return (vec![], vec![]);
+        };
+        self.segments
+            .sort_by_key(|(seq, ts, _)| (seq.wrapping_sub(base), *ts));
+        let mut bytes = vec![];
+        let mut times: Vec<(usize, Duration)> = vec![];
+        for (seq, ts, payload) in self.segments {
+            let offset = seq.wrapping_sub(base) as usize;
+            let end = offset + payload.len();
+            if offset > bytes.len() {
+                break;
+            }
+            if end > bytes.len() {
+                let complete = times.last().map_or(ts, |&(_, last)| last.max(ts));
+                times.push((bytes.len(), complete));
+                bytes.extend_from_slice(&payload[bytes.len() - offset..]);
+            }
+        }
+        (bytes, times)
+    }
+}
+
+struct HttpMessage {
+    start_line: Vec<String>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    headers_size: usize,
+    started: Duration,
+    finished: Duration,
+}
+
+impl HttpMessage {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Splits a reassembled stream into HTTP messages. `no_body` tells for each response
+/// whether its request forbids a body (HEAD).
+fn parse_messages(
+    bytes: &[u8],
+    times: &[(usize, Duration)],
+    request: bool,
+    mut no_body: impl FnMut(usize) -> bool,
+) -> Vec<HttpMessage> {
+    let time_at = |offset: usize| {
+        let i = times.partition_point(|&(start, _)| start <= offset);
+        times[i.saturating_sub(1)].1
+    };
+    let mut messages = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let Some(header_end) = find(&bytes[pos..], b"\r\n\r\n").map(|i| pos + i + 4) else {
+            break;
+        };
+        let head = String::from_utf8_lossy(&bytes[pos..header_end - 4]).into_owned();
+        let mut lines = head.split("\r\n");
+        let start_line = lines
+            .next()
+            .unwrap_or_default()
+            .splitn(3, ' ')
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect::<Vec<_>>();
+        let mut message = HttpMessage {
+            start_line,
+            headers,
+            body: vec![],
+            headers_size: header_end - pos,
+            started: time_at(pos),
+            finished: time_at(header_end - 1),
+        };
+
+        let status = message
+            .start_line
+            .get(1)
+            .and_then(|s| s.parse::<u16>().ok())
+            .unwrap_or(0);
+        let chunked = message
+            .header("Transfer-Encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"));
+        let length = message
+            .header("Content-Length")
+            .and_then(|v| v.parse::<usize>().ok());
+        let bodiless = (100..200).contains(&status) || status == 204 || status == 304;
+        let end = if !request && (bodiless || no_body(messages.len())) {
+            header_end
+        } else if chunked {
+            let (body, end) = dechunk(&bytes[header_end..]);
+            message.body = body;
+            header_end + end
+        } else if let Some(length) = length {
+            (header_end + length).min(bytes.len())
+        } else if request {
+            header_end
+        } else {
+            // without a length the response lasts until the connection closes
+            bytes.len()
+        };
+        if !chunked {
+            message.body = bytes[header_end..end].to_vec();
+        }
+        if end > header_end {
+            message.finished = time_at(end - 1);
+        }
+        pos = end;
+        // interim responses like 100 Continue don't answer the request
+        if !(100..200).contains(&status) || request {
+            messages.push(message);
+        }
+    }
+    messages
+}
+
+/// Decodes a chunked body, returns it with the number of bytes consumed.
+fn dechunk(bytes: &[u8]) -> (Vec<u8>, usize) {
+    let mut body = vec![];
+    let mut pos = 0;
+    while let Some(line_end) = find(&bytes[pos..], b"\r\n").map(|i| pos + i) {
+        let size_line = String::from_utf8_lossy(&bytes[pos..line_end]);
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let Ok(size) = usize::from_str_radix(size_hex, 16) else {
+            break;
+        };
+        let data_start = line_end + 2;
+        if size == 0 {
+            // skip trailers up to the final empty line
+            pos = find(&bytes[line_end..], b"\r\n\r\n")
+                .map(|i| line_end + i + 4)
+                .unwrap_or(bytes.len());
+            break;
+        }
+        let data_end = (data_start + size).min(bytes.len());
+        body.extend_from_slice(&bytes[data_start..data_end]);
+        pos = (data_end + 2).min(bytes.len());
+    }
+    (body, pos)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn starts_with_request(bytes: &[u8]) -> bool {
+    METHODS.iter().any(|method| {
+        bytes.starts_with(method.as_bytes()) && bytes.get(method.len()) == Some(&b' ')
+    })
+}
+
+/// A request with its response, both parsed from one TCP connection.
+struct Exchange {
+    client: SocketAddr,
+    server: SocketAddr,
+    request: HttpMessage,
+    response: Option<HttpMessage>,
+}
+
+/// Reassembles the TCP connections of the given packets and pairs the HTTP/1.x
+/// requests with their responses in the order they were sent.
+fn exchanges<'a>(packets: impl IntoIterator<Item = &'a ParsedPacket>) -> Vec<Exchange> {
+    let mut streams: HashMap<(SocketAddr, SocketAddr), Stream> = HashMap::new();
+    let mut order = vec![];
+    for packet in packets {
+        let TransportPacket::Tcp(tcp) = &packet.transport else {
+            continue;
+        };
+        let (Some(src), Some(dst)) = (packet.get_source_ip(), packet.get_destination_ip()) else {
+            continue;
+        };
+        let key = (
+            SocketAddr::new(src, tcp.get_source()),
+            SocketAddr::new(dst, tcp.get_destination()),
+        );
+        let stream = streams.entry(key).or_insert_with(|| {
+            order.push(key);
+            Stream::default()
+        });
+        if tcp.get_flags() & TcpFlags::SYN != 0 {
+            stream.isn = Some(tcp.get_sequence().wrapping_add(1));
+        }
+        if !tcp.payload().is_empty() {
+            stream
+                .segments
+                .push((tcp.get_sequence(), packet.timestamp, tcp.payload().to_vec()));
+        }
+    }
+
+    let mut reassembled = streams
+        .into_iter()
+        .map(|(key, stream)| (key, stream.reassemble()))
+        .collect::<HashMap<_, _>>();
+    let mut exchanges = vec![];
+    for (client, server) in order {
+        // the direction that starts with a request line is the client's
+        let is_client = reassembled
+            .get(&(client, server))
+            .is_some_and(|(bytes, _)| starts_with_request(bytes));
+        if !is_client {
+            continue;
+        }
+        let (request_bytes, request_times) = reassembled.remove(&(client, server)).unwrap();
+        let requests = parse_messages(&request_bytes, &request_times, true, |_| false);
+        let (response_bytes, response_times) =
+            reassembled.remove(&(server, client)).unwrap_or_default();
+        let mut responses = parse_messages(&response_bytes, &response_times, false, |i| {
+            requests
+                .get(i)
+                .and_then(|r| r.start_line.first())
+                .is_some_and(|m| m == "HEAD")
+        })
+        .into_iter();
+        exchanges.extend(requests.into_iter().map(|request| Exchange {
+            client,
+            server,
+            request,
+            response: responses.next(),
+        }));
+    }
+    exchanges.sort_by_key(|e| e.request.started);
+    exchanges
+}
+
+/// Writes the plain HTTP/1.x traffic among the packets as a HAR 1.2 log.
+pub fn write<'a>(
+    out: &mut impl Write,
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+) -> io::Result<()> {
+    let entries = exchanges(packets)
+        .iter()
+        .map(entry)
+        .collect::<Vec<_>>()
+        .join(",\n");
+    writeln!(
+        out,
+        "{{\"log\":{{\"version\":\"1.2\",\"creator\":{{\"name\":\"Postal\",\"version\":{}}},\"pages\":[],\"entries\":[\n{}\n]}}}}",
+        json_string(env!("CARGO_PKG_VERSION")),
+        entries
+    )
+}
+
+fn entry(exchange: &Exchange) -> String {
+    let request = &exchange.request;
+    let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+    let (wait, receive, finished) = match &exchange.response {
+        Some(response) => (
+            millis(response.started.saturating_sub(request.finished)),
+            millis(response.finished.saturating_sub(response.started)),
+            response.finished,
+        ),
+        None => (-1.0, -1.0, request.finished),
+    };
+    let send = millis(request.finished.saturating_sub(request.started));
+    let total = millis(finished.saturating_sub(request.started));
+    format!(
+        "{{\"startedDateTime\":{},\"time\":{:.3},\"request\":{},\"response\":{},\"cache\":{{}},\
+         \"timings\":{{\"blocked\":-1,\"dns\":-1,\"connect\":-1,\"ssl\":-1,\"send\":{:.3},\"wait\":{:.3},\"receive\":{:.3}}},\
+         \"serverIPAddress\":{},\"connection\":{}}}",
+        json_string(&format_rfc3339(request.started)),
+        total,
+        har_request(exchange),
+        har_response(exchange.response.as_ref()),
+        send,
+        wait,
+        receive,
+        json_string(&exchange.server.ip().to_string()),
+        json_string(&exchange.client.port().to_string()),
+    )
+}
+
+fn har_request(exchange: &Exchange) -> String {
+    let request = &exchange.request;
+    let part = |i: usize| {
+        request
+            .start_line
+            .get(i)
+            .map(String::as_str)
+            .unwrap_or_default()
+    };
+    let target = part(1);
+    let url = if target.starts_with("http://") {
+        target.to_string()
+    } else {
+        let host = request
+            .header("Host")
+            .map(String::from)
+            .unwrap_or_else(|| host(exchange.server));
+        format!("http://{}{}", host, target)
+    };
+    let query = target
+        .split_once('?')
+        .map(|(_, query)| {
+            query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    name_value(name, value)
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default();
+    let post_data = if request.body.is_empty() {
+        String::new()
+    } else {
+        format!(
+            ",\"postData\":{{\"mimeType\":{},{}}}",
+            json_string(request.header("Content-Type").unwrap_or_default()),
+            body_text(&request.body)
+        )
+    };
+    format!(
+        "{{\"method\":{},\"url\":{},\"httpVersion\":{},\"cookies\":[],\"headers\":{},\"queryString\":[{}]{},\"headersSize\":{},\"bodySize\":{}}}",
+        json_string(part(0)),
+        json_string(&url),
+        json_string(part(2)),
+        headers(request),
+        query,
+        post_data,
+        request.headers_size,
+        request.body.len()
+    )
+}
+
+fn har_response(response: Option<&HttpMessage>) -> String {
+    let Some(response) = response else {
+        // HAR has no notion of a missing response, status 0 is what browsers log
+        return String::from(
+            "{\"status\":0,\"statusText\":\"\",\"httpVersion\":\"\",\"cookies\":[],\"headers\":[],\
+             \"content\":{\"size\":0,\"mimeType\":\"\"},\"redirectURL\":\"\",\"headersSize\":-1,\"bodySize\":-1}",
+        );
+    };
+    let part = |i: usize| {
+        response
+            .start_line
+            .get(i)
+            .map(String::as_str)
+            .unwrap_or_default()
+    };
+    format!(
+        "{{\"status\":{},\"statusText\":{},\"httpVersion\":{},\"cookies\":[],\"headers\":{},\
+         \"content\":{{\"size\":{},\"mimeType\":{},{}}},\"redirectURL\":{},\"headersSize\":{},\"bodySize\":{}}}",
+        part(1).parse::<u16>().unwrap_or(0),
+        json_string(part(2)),
+        json_string(part(0)),
+        headers(response),
+        response.body.len(),
+        json_string(response.header("Content-Type").unwrap_or_default()),
+        body_text(&response.body),
+        json_string(response.header("Location").unwrap_or_default()),
+        response.headers_size,
+        response.body.len()
+    )
+}
+
+fn host(server: SocketAddr) -> String {
+    match (server.ip(), server.port()) {
+        (IpAddr::V4(ip), 80) => ip.to_string(),
+        (IpAddr::V6(ip), 80) => format!("[{}]", ip),
+        _ => server.to_string(),
+    }
+}
+
+fn name_value(name: &str, value: &str) -> String {
+    format!(
+        "{{\"name\":{},\"value\":{}}}",
+        json_string(name),
+        json_string(value)
+    )
+}
+
+fn headers(message: &HttpMessage) -> String {
+    let headers = message
+        .headers
+        .iter()
+        .map(|(name, value)| name_value(name, value))
+        .collect::<Vec<_>>();
+    format!("[{}]", headers.join(","))
+}
+
+/// Text bodies are embedded as is, anything else base64 encoded.
+fn body_text(body: &[u8]) -> String {
+    match std::str::from_utf8(body) {
+        Ok(text) if !text.contains('\0') => format!("\"text\":{}", json_string(text)),
+        _ => format!(
+            "\"text\":{},\"encoding\":\"base64\"",
+            json_string(&base64(body))
+        ),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_packets::{self, Segment};
+
+    const CLIENT: &str = "10.0.0.1:50000";
+    const SERVER: &str = "192.0.2.7:80";
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn encodes_base64_with_padding() {
+        // RFC 4648 section 10
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(plain.as_bytes()), encoded);
+        }
+        assert_eq!(base64(&[0xfb, 0xff, 0x00]), "+/8A");
+        assert_eq!(
+            body_text(&[0x00, 0xff]),
+            "\"text\":\"AP8=\",\"encoding\":\"base64\""
+        );
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        let bytes = b"4\r\nWiki\r\n5;name=value\r\npedia\r\n0\r\nExpires: never\r\n\r\nHTTP/1.1";
+        let (body, consumed) = dechunk(bytes);
+        assert_eq!(body, b"Wikipedia");
+        assert_eq!(&bytes[consumed..], b"HTTP/1.1");
+
+        // cut off inside a chunk, what arrived is kept
+        let (body, consumed) = dechunk(b"4\r\nWiki\r\n5\r\nped");
+        assert_eq!(body, b"Wikiped");
+        assert_eq!(consumed, 15);
+        // not a chunk size
+        assert_eq!(dechunk(b"zz\r\nWiki\r\n"), (vec![], 0));
+    }
+
+    #[test]
+    fn reassembles_streams_in_sequence_order() {
+        let stream = Stream {
+            segments: vec![
+                (1006, ms(3), b"world".to_vec()),
+                (1000, ms(1), b"hello ".to_vec()),
+                // a retransmission that also carries new bytes
+                (1003, ms(4), b"lo world!".to_vec()),
+                // a duplicate of the first segment
+                (1000, ms(5), b"hello ".to_vec()),
+            ],
+            isn: Some(1000),
+        };
+        // the 3ms segment only fills bytes already there once the 4ms one is in
+        let (bytes, times) = stream.reassemble();
+        assert_eq!(bytes, b"hello world!");
+        assert_eq!(times, vec![(0, ms(1)), (6, ms(4))]);
+
+        // reassembly stops at the first gap
+        let stream = Stream {
+            segments: vec![
+                (1000, ms(1), b"abc".to_vec()),
+                (1005, ms(2), b"xyz".to_vec()),
+            ],
+            isn: Some(1000),
+        };
+        assert_eq!(stream.reassemble().0, b"abc");
+
+        // without the SYN the stream starts at the lowest sequence number, across wraparound
+        let stream = Stream {
+            segments: vec![
+                (2, ms(2), b"cd".to_vec()),
+                (u32::MAX - 1, ms(1), b"ab".to_vec()),
+            ],
+            isn: None,
+        };
+        assert_eq!(stream.reassemble().0, b"ab");
+    }
+
+    #[test]
+    fn pairs_a_request_with_a_chunked_response_split_across_segments() {
+        let request = b"GET /wiki?lang=en HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let response: &[&[u8]] = &[
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n",
+            b"Content-Type: text/plain\r\n\r\n4\r\nWi",
+            b"ki\r\n5\r\npedia\r\n0\r\n\r\n",
+        ];
+        let data = |seq: u32, payload: &[u8], at: u64| Segment {
+            seq,
+            flags: TcpFlags::ACK | TcpFlags::PSH,
+            payload: payload.to_vec(),
+            at,
+            ..Segment::default()
+        };
+        let first = 5001 + response[0].len() as u32;
+        let second = first + response[1].len() as u32;
+        let packets = vec![
+            test_packets::tcp(
+                CLIENT,
+                SERVER,
+                Segment {
+                    seq: 100,
+                    flags: TcpFlags::SYN,
+                    ..Segment::default()
+                },
+            ),
+            test_packets::tcp(
+                SERVER,
+                CLIENT,
+                Segment {
+                    seq: 5000,
+                    flags: TcpFlags::SYN | TcpFlags::ACK,
+                    at: 1,
+                    ..Segment::default()
+                },
+            ),
+            test_packets::tcp(CLIENT, SERVER, data(101, request, 2)),
+            test_packets::tcp(SERVER, CLIENT, data(5001, response[0], 10)),
+            // the last segment overtakes the middle one, which is then sent twice
+            test_packets::tcp(SERVER, CLIENT, data(second, response[2], 11)),
+            test_packets::tcp(SERVER, CLIENT, data(first, response[1], 12)),
+            test_packets::tcp(SERVER, CLIENT, data(first, response[1], 13)),
+        ];
+
+        let exchanges = exchanges(&packets);
+        let [exchange] = exchanges.as_slice() else {
+            panic!("one exchange expected");
+        };
+        assert_eq!(exchange.request.start_line[1], "/wiki?lang=en");
+        assert_eq!(exchange.request.started, ms(2));
+        let response = exchange.response.as_ref().unwrap();
+        assert_eq!(response.body, b"Wikipedia");
+        assert_eq!(response.header("content-type"), Some("text/plain"));
+        assert_eq!(response.started, ms(10));
+        assert_eq!(response.finished, ms(12));
+
+        let mut har = vec![];
+        write(&mut har, &packets).unwrap();
+        let har = String::from_utf8(har).unwrap();
+        assert!(har.contains("\"url\":\"http://example.com/wiki?lang=en\""));
+        assert!(har.contains("\"queryString\":[{\"name\":\"lang\",\"value\":\"en\"}]"));
+        assert!(har.contains("\"text\":\"Wikipedia\""));
+    }
+}
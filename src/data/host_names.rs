@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use pnet::packet::Packet;
+
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+
+/// Entries of the hosts file, they take precedence over the names of a capture.
+static HOSTS: Lazy<HashMap<IpAddr, String>> = Lazy::new(load_hosts);
+
+fn hosts_path() -> &'static str {
+    if cfg!(windows) {
+        r"C:\Windows\System32\drivers\etc\hosts"
+    } else {
+        "/etc/hosts"
+    }
+}
+
+fn load_hosts() -> HashMap<IpAddr, String> {
+    let mut hosts = HashMap::new();
+    let Ok(content) = fs::read_to_string(hosts_path()) else {
+        return hosts;
+    };
+    for line in content.lines() {
+        let mut fields = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let (Some(Ok(ip)), Some(name)) = (fields.next().map(str::parse), fields.next()) else {
+            continue;
+        };
+        // the first entry of an address wins, as with the system resolver
+        hosts.entry(ip).or_insert_with(|| name.to_string());
+    }
+    hosts
+}
+
+/// Host names of IP addresses, from the hosts file, DNS answers seen in the capture
+/// and reverse lookups.
+#[derive(Debug, Default)]
+pub struct HostNames {
+    learned: HashMap<IpAddr, String>,
+    /// Addresses a reverse lookup is running for.
+    requested: HashSet<IpAddr>,
+    /// Addresses the last reverse lookup found no name for, with the time it failed.
+    failed: HashMap<IpAddr, Instant>,
+}
+
+impl HostNames {
+    /// Time after which a failed reverse lookup is tried again.
+    const RETRY: Duration = Duration::from_secs(60);
+
+    /// Forgets the names of the capture, the hosts file stays.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Name of the address, if one is known.
+    pub fn lookup(&self, ip: IpAddr) -> Option<&str> {
+        HOSTS
+            .get(&ip)
+            .or_else(|| self.learned.get(&ip))
+            .map(String::as_str)
+    }
+
+    /// The name followed by the address, e.g. `example.com (93.184.216.34)`, or just the address.
+    pub fn describe(&self, ip: IpAddr) -> String {
+        match self.lookup(ip) {
+            Some(name) => format!("{} ({})", name, ip),
+            None => ip.to_string(),
+        }
+    }
+
+    /// Remembers the addresses announced in a DNS or mDNS response.
+    pub fn learn(&mut self, packet: &ParsedPacket) {
+        let payload = match &packet.transport {
+            TransportPacket::Udp(udp) if matches!(udp.get_source(), 53 | 5353) => udp.payload(),
+            // DNS over TCP prefixes each message with its length
+            TransportPacket::Tcp(tcp) if tcp.get_source() == 53 => {
+                tcp.payload().get(2..).unwrap_or(&[])
+            }
+            _ => return,
+        };
+        self.learned.extend(dns_answers(payload));
+    }
+
+    /// Addresses of the packets that have no name and no lookup running,
+    /// they count as requested from now on. Failed ones are tried again after a while.
+    pub fn unrequested<'a>(
+        &mut self,
+        packets: impl IntoIterator<Item = &'a ParsedPacket>,
+    ) -> Vec<IpAddr> {
+        let now = Instant::now();
+        let mut ips = vec![];
+        for packet in packets {
+            for ip in [packet.get_source_ip(), packet.get_destination_ip()]
+                .into_iter()
+                .flatten()
+            {
+                let lookupable = !(ip.is_unspecified() || ip.is_multicast() || ip.is_loopback())
+                    && ip != IpAddr::V4(Ipv4Addr::BROADCAST);
+                let retry = self
+                    .failed
+                    .get(&ip)
+                    .is_none_or(|&failed| now - failed >= Self::RETRY);
+                if lookupable && retry && self.lookup(ip).is_none() && self.requested.insert(ip) {
+                    self.failed.remove(&ip);
+                    ips.push(ip);
+                }
+            }
+        }
+        ips
+    }
+
+    /// Stores the results of `reverse_lookup`. Those of lookups requested before the
+    /// names were cleared are dropped.
+    pub fn insert(&mut self, resolved: Vec<(IpAddr, Option<String>)>) {
+        let now = Instant::now();
+        for (ip, name) in resolved {
+            if !self.requested.remove(&ip) {
+                continue;
+            }
+            match name {
+                Some(name) => {
+                    self.learned.insert(ip, name);
+                }
+                None => {
+                    self.failed.insert(ip, now);
+                }
+            }
+        }
+    }
+}
+
+/// Looks the addresses up through the system resolver, one after the other.
+/// Addresses without a name come back with `None`.
+pub async fn reverse_lookup(ips: Vec<IpAddr>) -> Vec<(IpAddr, Option<String>)> {
+    tokio::task::spawn_blocking(move || ips.into_iter().map(|ip| (ip, resolve(ip))).collect())
+        .await
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn resolve(ip: IpAddr) -> Option<String> {
+    use std::ffi::CStr;
+    use std::mem::size_of;
+
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let length = match ip {
+        IpAddr::V4(v4) => {
+            let addr = &mut storage as *mut _ as *mut libc::sockaddr_in;
+            unsafe {
+                (*addr).sin_family = libc::AF_INET as libc::sa_family_t;
+                #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+                {
+                    (*addr).sin_len = size_of::<libc::sockaddr_in>() as u8;
+                }
+                (*addr).sin_addr.s_addr = u32::from_ne_bytes(v4.octets());
+            }
+            size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(v6) => {
+            let addr = &mut storage as *mut _ as *mut libc::sockaddr_in6;
+            unsafe {
+                (*addr).sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+                {
+                    (*addr).sin6_len = size_of::<libc::sockaddr_in6>() as u8;
+                }
+                (*addr).sin6_addr.s6_addr = v6.octets();
+            }
+            size_of::<libc::sockaddr_in6>()
+        }
+    };
+    let mut host = [0 as libc::c_char; 1025];
+    let result = unsafe {
+        libc::getnameinfo(
+            &storage as *const _ as *const libc::sockaddr,
+            length as libc::socklen_t,
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    name.to_str().ok().map(str::to_string)
+}
+
+#[cfg(not(unix))]
+fn resolve(_ip: IpAddr) -> Option<String> {
+    None
+}
+
+/// Address records of a DNS response, named after the question, and the names of
+/// reverse lookups. mDNS responses come without questions, their records name themselves.
+fn dns_answers(message: &[u8]) -> Vec<(IpAddr, String)> {
+    const RESPONSE: u16 = 0x8000;
+    const RCODE: u16 = 0x000f;
+    let word = |at: usize| {
+        message
+            .get(at..at + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+    let (Some(flags), Some(questions), Some(answers)) = (word(2), word(4), word(6)) else {
+        return vec![];
+    };
+    if flags & RESPONSE == 0 || flags & RCODE != 0 {
+        return vec![];
+    }
+
+    let mut at = 12;
+    let mut question = None;
+    for _ in 0..questions {
+        let Some((name, next)) = read_name(message, at) else {
+            return vec![];
+        };
+        question.get_or_insert(name);
+        at = next + 4;
+    }
+
+    let mut records = vec![];
+    for _ in 0..answers {
+        let Some((owner, next)) = read_name(message, at) else {
+            break;
+        };
+        let (Some(kind), Some(length)) = (word(next), word(next + 8)) else {
+            break;
+        };
+        let start = next + 10;
+        let Some(data) = message.get(start..start + length as usize) else {
+            break;
+        };
+        let name = || question.clone().unwrap_or_else(|| owner.clone());
+        match (kind, data.len()) {
+            (1, 4) => records.push((IpAddr::from(<[u8; 4]>::try_from(data).unwrap()), name())),
+            (28, 16) => records.push((IpAddr::from(<[u8; 16]>::try_from(data).unwrap()), name())),
+            (12, _) => {
+                if let (Some(ip), Some((target, _))) =
+                    (reverse_name(&owner), read_name(message, start))
+                {
+                    records.push((ip, target));
+                }
+            }
+            _ => {}
+        }
+        at = start + length as usize;
+    }
+    records
+}
+
+/// Reads a possibly compressed domain name, returns it with the offset after it.
+fn read_name(message: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let mut labels = vec![];
+    let mut end = None;
+    // bounds the pointers followed, a loop of them would never end otherwise
+    for _ in 0..128 {
+        let length = *message.get(at)? as usize;
+        match length {
+            0 => {
+                let name = labels.join(".");
+                return Some((name, end.unwrap_or(at + 1)));
+            }
+            _ if length & 0xc0 == 0xc0 => {
+                let pointer = ((length & 0x3f) << 8) | *message.get(at + 1)? as usize;
+                end.get_or_insert(at + 2);
+                at = pointer;
+            }
+            _ if length < 64 => {
+                let label = message.get(at + 1..at + 1 + length)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                at += 1 + length;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Address of a reverse lookup name, e.g. `4.3.2.1.in-addr.arpa` for 1.2.3.4.
+fn reverse_name(name: &str) -> Option<IpAddr> {
+    let name = name.to_lowercase();
+    if let Some(octets) = name.strip_suffix(".in-addr.arpa") {
+        let mut octets = octets
+            .split('.')
+            .map(|octet| octet.parse::<u8>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        octets.reverse();
+        let octets = <[u8; 4]>::try_from(octets).ok()?;
+        return Some(IpAddr::V4(Ipv4Addr::from(octets)));
+    }
+    let nibbles = name
+        .strip_suffix(".ip6.arpa")?
+        .split('.')
+        .rev()
+        .map(|nibble| {
+            u8::from_str_radix(nibble, 16)
+                .ok()
+                .filter(|_| nibble.len() == 1)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if nibbles.len() != 32 {
+        return None;
+    }
+    let mut octets = [0; 16];
+    for (octet, pair) in octets.iter_mut().zip(nibbles.chunks(2)) {
+        *octet = (pair[0] << 4) | pair[1];
+    }
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_packets;
+
+    #[test]
+    fn failed_lookups_are_retried_and_stale_results_dropped() {
+        let mut names = HostNames::default();
+        let packet = test_packets::udp("10.0.0.1:5000", "192.0.2.7:53", &[]);
+        let peer: IpAddr = "192.0.2.7".parse().unwrap();
+        let ips = names.unrequested([&packet]);
+        assert!(ips.contains(&peer));
+        // running lookups are not started twice
+        assert!(names.unrequested([&packet]).is_empty());
+
+        names.insert(vec![(peer, None)]);
+        assert!(names.unrequested([&packet]).is_empty());
+        let failed = names.failed.get_mut(&peer).unwrap();
+        *failed -= HostNames::RETRY;
+        assert_eq!(names.unrequested([&packet]), vec![peer]);
+
+        names.clear();
+        names.insert(vec![(peer, Some(String::from("stale.example")))]);
+        assert_eq!(names.lookup(peer), None);
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// What the main area next to the sidebar shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MainView {
+    #[default]
+    Packets,
+    ProtocolHierarchy,
+    Endpoints,
+    Connections,
+    QuicConnections,
+}
+
+impl MainView {
+    pub const ALL: [MainView; 5] = [
+        MainView::Packets,
+        MainView::ProtocolHierarchy,
+        MainView::Endpoints,
+        MainView::Connections,
+        MainView::QuicConnections,
+    ];
+}
+
+impl fmt::Display for MainView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MainView::Packets => write!(f, "Packets"),
+            MainView::ProtocolHierarchy => write!(f, "Protocol Hierarchy"),
+            MainView::Endpoints => write!(f, "Endpoints"),
+            MainView::Connections => write!(f, "TCP Connections"),
+            MainView::QuicConnections => write!(f, "QUIC Connections"),
+        }
+    }
+}
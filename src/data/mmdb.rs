@@ -0,0 +1,541 @@
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Reader of MaxMind DB files (`.mmdb`), the format of the GeoLite2 and GeoIP2 databases,
+/// see https://maxmind.github.io/MaxMind-DB/
+#[derive(Debug)]
+pub struct Database {
+    data: Vec<u8>,
+    node_count: u32,
+    record_size: u16,
+    ip_version: u16,
+    /// Size of the search tree in bytes, the data section follows 16 bytes later.
+    tree_size: usize,
+    /// Node at which IPv4 addresses start in an IPv6 tree, after 96 zero bits.
+    ipv4_start: u32,
+    /// E.g. `GeoLite2-City` or `GeoLite2-ASN`.
+    pub database_type: String,
+}
+
+/// A value of the data section.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Double(f64),
+    Bytes(Vec<u8>),
+    Uint(u128),
+    Int(i32),
+    Map(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    Bool(bool),
+    Float(f32),
+}
+
+impl Value {
+    /// The value at the path of map keys, e.g. `["country", "names", "en"]`.
+    pub fn get(&self, path: &[&str]) -> Option<&Value> {
+        path.iter().try_fold(self, |value, key| match value {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        })
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Uint(n) => u64::try_from(*n).ok(),
+            Value::Int(n) => u64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Database {
+    const METADATA_MARKER: &'static [u8] = b"\xab\xcd\xefMaxMind.com";
+    /// The metadata is found within this many bytes from the end of the file.
+    const METADATA_MAX_SIZE: usize = 128 * 1024;
+    const DATA_SEPARATOR: usize = 16;
+
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_bytes(data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        let search_from = data.len().saturating_sub(Self::METADATA_MAX_SIZE);
+        let marker = data[search_from..]
+            .windows(Self::METADATA_MARKER.len())
+            .rposition(|window| window == Self::METADATA_MARKER)
+            .ok_or("Not a MaxMind DB file, the metadata is missing")?;
+        let start = search_from + marker + Self::METADATA_MARKER.len();
+        let metadata = Decoder::new(&data, start).decode(start)?.0;
+
+        let number = |key: &str| {
+            metadata
+                .get(&[key])
+                .and_then(Value::as_u64)
+                .ok_or(format!("The metadata lacks {}", key))
+        };
+        let node_count = u32::try_from(number("node_count")?).map_err(|e| e.to_string())?;
+        let record_size = number("record_size")? as u16;
+        let ip_version = number("ip_version")? as u16;
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(format!("Unsupported record size {}", record_size));
+        }
+        let tree_size = node_count as usize * record_size as usize * 2 / 8;
+        if tree_size + Self::DATA_SEPARATOR > start {
+            return Err(String::from("The search tree exceeds the file"));
+        }
+
+        let mut database = Self {
+            data,
+            node_count,
+            record_size,
+            ip_version,
+            tree_size,
+            ipv4_start: 0,
+            database_type: metadata
+                .get(&["database_type"])
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        };
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = database.record(node, 0);
+            }
+            database.ipv4_start = node;
+        }
+        Ok(database)
+    }
+
+    /// The record of the address, `None` if the database has none.
+    pub fn lookup(&self, ip: IpAddr) -> Option<Value> {
+        let (bytes, mut node) = match ip {
+            IpAddr::V4(v4) if self.ip_version == 6 => (v4.octets().to_vec(), self.ipv4_start),
+            IpAddr::V4(v4) => (v4.octets().to_vec(), 0),
+            IpAddr::V6(v6) if self.ip_version == 6 => (v6.octets().to_vec(), 0),
+            IpAddr::V6(v6) => (v6.to_ipv4_mapped()?.octets().to_vec(), 0),
+        };
+        for i in 0..bytes.len() * 8 {
+            if node >= self.node_count {
+                break;
+            }
+            let bit = (bytes[i / 8] >> (7 - i % 8)) & 1;
+            node = self.record(node, bit);
+        }
+        if node <= self.node_count {
+            // equal to the node count means no data
+            return None;
+        }
+        let offset = self.tree_size + (node - self.node_count) as usize;
+        let base = self.tree_size + Self::DATA_SEPARATOR;
+        Decoder::new(&self.data, base)
+            .decode(offset)
+            .ok()
+            .map(|(value, _)| value)
+    }
+
+    /// Left (`bit` 0) or right record of a node of the search tree.
+    fn record(&self, node: u32, bit: u8) -> u32 {
+        let bytes = self.record_size as usize * 2 / 8;
+        let at = node as usize * bytes;
+        let Some(node) = self.data.get(at..at + bytes) else {
+            return self.node_count;
+        };
+        let be = |bytes: &[u8]| bytes.iter().fold(0u32, |n, &b| (n << 8) | b as u32);
+        match (self.record_size, bit) {
+            (24, 0) => be(&node[0..3]),
+            (24, _) => be(&node[3..6]),
+            // the middle byte holds the high nibbles of both records
+            (28, 0) => ((node[3] as u32 & 0xf0) << 20) | be(&node[0..3]),
+            (28, _) => ((node[3] as u32 & 0x0f) << 24) | be(&node[4..7]),
+            (_, 0) => be(&node[0..4]),
+            _ => be(&node[4..8]),
+        }
+    }
+}
+
+/// Decodes values of a data section, pointers in it are relative to `base`.
+struct Decoder<'a> {
+    data: &'a [u8],
+    base: usize,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Deepest nesting of maps and arrays followed, protects against malformed files.
+    const MAX_DEPTH: usize = 64;
+
+    fn new(data: &'a [u8], base: usize) -> Self {
+        Self {
+            data,
+            base,
+            depth: 0,
+        }
+    }
+
+    fn bytes(&self, at: usize, len: usize) -> Result<&'a [u8], String> {
+        self.data
+            .get(at..at + len)
+            .ok_or_else(|| String::from("The data section is truncated"))
+    }
+
+    fn uint(&self, at: usize, len: usize) -> Result<u128, String> {
+        Ok(self
+            .bytes(at, len)?
+            .iter()
+            .fold(0u128, |n, &b| (n << 8) | b as u128))
+    }
+
+    /// Decodes the value at `at`, returns it with the offset after it.
+    fn decode(&mut self, at: usize) -> Result<(Value, usize), String> {
+        self.depth += 1;
+        if self.depth > Self::MAX_DEPTH {
+            return Err(String::from("The data is nested too deeply"));
+        }
+        let result = self.decode_value(at);
+        self.depth -= 1;
+        result
+    }
+
+    fn decode_value(&mut self, at: usize) -> Result<(Value, usize), String> {
+        let control = self.bytes(at, 1)?[0];
+        let mut at = at + 1;
+        // extended types are numbered from 8 in the byte after the control byte
+        let mut kind = (control >> 5) as u16;
+        if kind == 1 {
+            let (pointer, next) = self.pointer(control, at)?;
+            // the value pointed to must not be a pointer itself, decoding goes on after it
+            let (value, _) = self.decode(self.base + pointer)?;
+            return Ok((value, next));
+        }
+        if kind == 0 {
+            kind = 7 + self.bytes(at, 1)?[0] as u16;
+            at += 1;
+        }
+        let (size, at) = match control & 0x1f {
+            29 => (29 + self.uint(at, 1)? as usize, at + 1),
+            30 => (285 + self.uint(at, 2)? as usize, at + 2),
+            31 => (65_821 + self.uint(at, 3)? as usize, at + 3),
+            size => (size as usize, at),
+        };
+        match kind {
+            2 => {
+                let bytes = self.bytes(at, size)?;
+                let string = String::from_utf8_lossy(bytes).into_owned();
+                Ok((Value::String(string), at + size))
+            }
+            3 => {
+                let bytes = self.bytes(at, 8)?;
+                let value = f64::from_be_bytes(bytes.try_into().unwrap());
+                Ok((Value::Double(value), at + 8))
+            }
+            4 => Ok((Value::Bytes(self.bytes(at, size)?.to_vec()), at + size)),
+            5 | 6 | 9 | 10 if size <= 16 => Ok((Value::Uint(self.uint(at, size)?), at + size)),
+            7 => {
+                let mut entries = Vec::with_capacity(size.min(64));
+                let mut at = at;
+                for _ in 0..size {
+                    let (key, next) = self.decode(at)?;
+                    let Value::String(key) = key else {
+                        return Err(String::from("A map key is not a string"));
+                    };
+                    let (value, next) = self.decode(next)?;
+                    entries.push((key, value));
+                    at = next;
+                }
+                Ok((Value::Map(entries), at))
+            }
+            8 if size <= 4 => {
+                let value = self.uint(at, size)? as u32;
+                Ok((Value::Int(value as i32), at + size))
+            }
+            11 => {
+                let mut values = Vec::with_capacity(size.min(64));
+                let mut at = at;
+                for _ in 0..size {
+                    let (value, next) = self.decode(at)?;
+                    values.push(value);
+                    at = next;
+                }
+                Ok((Value::Array(values), at))
+            }
+            14 => Ok((Value::Bool(size != 0), at)),
+            15 => {
+                let bytes = self.bytes(at, 4)?;
+                let value = f32::from_be_bytes(bytes.try_into().unwrap());
+                Ok((Value::Float(value), at + 4))
+            }
+            kind => Err(format!("Unsupported data type {}", kind)),
+        }
+    }
+
+    /// Target and end of a pointer, its size bits choose the length.
+    fn pointer(&self, control: u8, at: usize) -> Result<(usize, usize), String> {
+        let high = (control & 0x07) as usize;
+        Ok(match (control >> 3) & 0x03 {
+            0 => ((high << 8) | self.uint(at, 1)? as usize, at + 1),
+            1 => (((high << 16) | self.uint(at, 2)? as usize) + 2048, at + 2),
+            2 => (
+                ((high << 24) | self.uint(at, 3)? as usize) + 526_336,
+                at + 3,
+            ),
+            _ => (self.uint(at, 4)? as usize, at + 4),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{Database, Decoder, Value};
+
+    fn string(text: &str) -> Vec<u8> {
+        let mut bytes = vec![(2 << 5) | text.len() as u8];
+        bytes.extend(text.as_bytes());
+        bytes
+    }
+
+    fn uint16(n: u16) -> Vec<u8> {
+        let mut bytes = vec![(5 << 5) | 2];
+        bytes.extend(n.to_be_bytes());
+        bytes
+    }
+
+    fn uint32(n: u32) -> Vec<u8> {
+        let mut bytes = vec![(6 << 5) | 4];
+        bytes.extend(n.to_be_bytes());
+        bytes
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = vec![(7 << 5) | entries.len() as u8];
+        for (key, value) in entries {
+            bytes.extend(string(key));
+            bytes.extend(value);
+        }
+        bytes
+    }
+
+    /// Pointer to `offset` of the data section, below 2048.
+    fn pointer(offset: usize) -> Vec<u8> {
+        vec![(1 << 5) | (offset >> 8) as u8, offset as u8]
+    }
+
+    fn country(code: &str, name: &str) -> Vec<u8> {
+        map(&[
+            ("iso_code", string(code)),
+            ("names", map(&[("en", string(name))])),
+        ])
+    }
+
+    #[derive(Clone, Copy)]
+    enum Record {
+        Empty,
+        Node(usize),
+        Data(usize),
+    }
+
+    /// A database with a search tree leading from the networks, given as address and
+    /// prefix length, to the offsets of their records in `data`.
+    fn database(
+        ip_version: u16,
+        record_size: u16,
+        networks: &[(IpAddr, usize, usize)],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut nodes = vec![[Record::Empty; 2]];
+        for &(ip, prefix, offset) in networks {
+            let bytes = match ip {
+                IpAddr::V4(v4) if ip_version == 6 => v4.to_ipv6_compatible().octets().to_vec(),
+                IpAddr::V4(v4) => v4.octets().to_vec(),
+                IpAddr::V6(v6) => v6.octets().to_vec(),
+            };
+            let prefix = prefix
+                + if ip.is_ipv4() && ip_version == 6 {
+                    96
+                } else {
+                    0
+                };
+            let mut node = 0;
+            for i in 0..prefix {
+                let bit = ((bytes[i / 8] >> (7 - i % 8)) & 1) as usize;
+                if i == prefix - 1 {
+                    nodes[node][bit] = Record::Data(offset);
+                } else if let Record::Node(next) = nodes[node][bit] {
+                    node = next;
+                } else {
+                    nodes.push([Record::Empty; 2]);
+                    nodes[node][bit] = Record::Node(nodes.len() - 1);
+                    node = nodes.len() - 1;
+                }
+            }
+        }
+
+        let node_count = nodes.len() as u32;
+        let value = |record: Record| match record {
+            Record::Empty => node_count,
+            Record::Node(node) => node as u32,
+            Record::Data(offset) => node_count + 16 + offset as u32,
+        };
+        let mut file = vec![];
+        for [left, right] in nodes {
+            let (left, right) = (value(left), value(right));
+            match record_size {
+                24 => {
+                    file.extend(&left.to_be_bytes()[1..]);
+                    file.extend(&right.to_be_bytes()[1..]);
+                }
+                28 => {
+                    file.extend(&left.to_be_bytes()[1..]);
+                    file.push((((left >> 24) as u8) << 4) | (right >> 24) as u8);
+                    file.extend(&right.to_be_bytes()[1..]);
+                }
+                _ => {
+                    file.extend(left.to_be_bytes());
+                    file.extend(right.to_be_bytes());
+                }
+            }
+        }
+        file.extend([0; 16]);
+        file.extend(data);
+        file.extend(Database::METADATA_MARKER);
+        file.extend(map(&[
+            ("node_count", uint32(node_count)),
+            ("record_size", uint16(record_size)),
+            ("ip_version", uint16(ip_version)),
+            ("database_type", string("Test-City")),
+        ]));
+        file
+    }
+
+    /// Two records, the second pointing to the country of the first.
+    fn records() -> (Vec<u8>, usize) {
+        let mut data = vec![];
+        data.extend(map(&[("country", country("DE", "Germany"))]));
+        let second = data.len();
+        // the country map starts after the outer map's control byte and key
+        data.extend(map(&[(
+            "registered_country",
+            pointer(1 + string("country").len()),
+        )]));
+        (data, second)
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    fn country_code<'a>(record: &'a Value, key: &str) -> Option<&'a str> {
+        record.get(&[key, "iso_code"]).and_then(Value::as_str)
+    }
+
+    #[test]
+    fn finds_records_of_networks_with_every_record_size() {
+        let (data, second) = records();
+        for record_size in [24, 28, 32] {
+            let file = database(
+                4,
+                record_size,
+                &[(ip("1.2.3.0"), 24, 0), (ip("5.0.0.0"), 8, second)],
+                &data,
+            );
+            let database = Database::from_bytes(file).unwrap();
+            assert_eq!(database.database_type, "Test-City");
+
+            let record = database.lookup(ip("1.2.3.4")).unwrap();
+            assert_eq!(country_code(&record, "country"), Some("DE"));
+            assert_eq!(
+                record
+                    .get(&["country", "names", "en"])
+                    .and_then(Value::as_str),
+                Some("Germany")
+            );
+            let record = database.lookup(ip("5.6.7.8")).unwrap();
+            assert_eq!(country_code(&record, "registered_country"), Some("DE"));
+            assert_eq!(database.lookup(ip("1.2.4.1")), None);
+            assert_eq!(database.lookup(ip("9.9.9.9")), None);
+            // IPv4-mapped IPv6 addresses are looked up as IPv4
+            assert!(database.lookup(ip("::ffff:1.2.3.4")).is_some());
+        }
+    }
+
+    #[test]
+    fn finds_ipv4_addresses_in_ipv6_trees() {
+        let (data, second) = records();
+        let file = database(
+            6,
+            24,
+            &[(ip("1.2.3.0"), 24, 0), (ip("2001:db8::"), 32, second)],
+            &data,
+        );
+        let database = Database::from_bytes(file).unwrap();
+
+        let record = database.lookup(ip("1.2.3.4")).unwrap();
+        assert_eq!(country_code(&record, "country"), Some("DE"));
+        let record = database.lookup(ip("2001:db8::1")).unwrap();
+        assert_eq!(country_code(&record, "registered_country"), Some("DE"));
+        assert_eq!(database.lookup(ip("2001:db9::1")), None);
+        assert_eq!(database.lookup(ip("1.2.4.1")), None);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(Database::from_bytes(vec![0; 100]).is_err());
+
+        let (data, _) = records();
+        let mut file = database(4, 24, &[(ip("1.2.3.0"), 24, 0)], &data);
+        // a node count beyond the file
+        let marker = file
+            .windows(Database::METADATA_MARKER.len())
+            .position(|window| window == Database::METADATA_MARKER)
+            .unwrap();
+        file.truncate(marker + Database::METADATA_MARKER.len());
+        file.extend(map(&[
+            ("node_count", uint32(1 << 20)),
+            ("record_size", uint16(24)),
+            ("ip_version", uint16(4)),
+        ]));
+        assert!(Database::from_bytes(file.clone()).is_err());
+
+        file.truncate(marker + Database::METADATA_MARKER.len());
+        file.extend(map(&[
+            ("node_count", uint32(1)),
+            ("record_size", uint16(20)),
+            ("ip_version", uint16(4)),
+        ]));
+        assert!(Database::from_bytes(file.clone()).is_err());
+
+        // extended types are a byte added to 7, the largest ones are past any type
+        assert!(Decoder::new(&[0x00, 0xff], 0).decode(0).is_err());
+        file.truncate(marker + Database::METADATA_MARKER.len());
+        file.extend([0x00, 0xf9]);
+        assert!(Database::from_bytes(file).is_err());
+
+        let mut data = map(&[("country", vec![0x00, 0xf9])]);
+        let record = data.len();
+        data.extend(map(&[("country", country("DE", "Germany"))]));
+        let file = database(
+            4,
+            24,
+            &[(ip("1.2.3.0"), 24, 0), (ip("5.0.0.0"), 8, record)],
+            &data,
+        );
+        let database = Database::from_bytes(file).unwrap();
+        assert_eq!(database.lookup(ip("1.2.3.4")), None);
+        assert!(database.lookup(ip("5.6.7.8")).is_some());
+    }
+}
@@ -1,4 +1,34 @@
+pub mod coloring;
+pub mod column;
+pub mod config;
+pub mod connections;
+pub mod crypto;
+pub mod direction;
+pub mod display_filter;
+pub mod endpoints;
+pub mod export;
+pub mod geoip;
+pub mod har;
+pub mod host_names;
+pub mod main_view;
+pub mod mmdb;
+pub mod os_network_interface;
+pub mod oui;
+pub mod packet_details;
+pub mod packet_index;
 pub mod packet_subscription;
 pub mod parsed_packet;
+pub mod pcap;
+pub mod port_filter;
 pub mod postal_option;
-pub mod os_network_interface;
+pub mod process;
+pub mod protocol_hierarchy;
+pub mod quic;
+pub mod quic_connections;
+pub mod ring_buffer;
+pub mod search;
+pub mod shortcut;
+pub mod tcp_analysis;
+#[cfg(test)]
+pub mod test_packets;
+pub mod time_format;
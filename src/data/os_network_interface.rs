@@ -1,5 +1,5 @@
-use std::fmt;
 use pnet::datalink::NetworkInterface;
+use std::fmt;
 
 #[derive(Debug, Clone)]
 pub struct OSNetworkInterface {
@@ -8,8 +8,19 @@ pub struct OSNetworkInterface {
 
 impl OSNetworkInterface {
     pub fn new(interface: NetworkInterface) -> Self {
-        Self {
-            interface
+        Self { interface }
+    }
+
+    /// Stand-in for reading files on a machine without a usable interface.
+    /// No address belongs to it, so no packet is classified as sent or received by the host.
+    pub fn detached() -> NetworkInterface {
+        NetworkInterface {
+            name: String::new(),
+            description: String::new(),
+            index: 0,
+            mac: None,
+            ips: vec![],
+            flags: 0,
         }
     }
 
@@ -31,8 +42,8 @@ impl fmt::Display for OSNetworkInterface {
 }
 
 #[cfg(any(target_os = "macos", target_os = "linux"))]
-impl fmt::Display for MyStruct {
+impl fmt::Display for OSNetworkInterface {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.interface.name)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use once_cell::sync::Lazy;
+use pnet::util::MacAddr;
+
+/// Table bundled with the binary, see the file for its format.
+const BUNDLED: &str = include_str!("manuf");
+
+static VENDORS: Lazy<VendorTable> = Lazy::new(VendorTable::load);
+
+/// Vendor names of MAC address prefixes.
+#[derive(Debug, Default)]
+struct VendorTable {
+    /// Short names by prefix length in bits and the masked address.
+    names: HashMap<(u8, u64), String>,
+    /// Prefix lengths present, longest first.
+    lengths: Vec<u8>,
+}
+
+impl VendorTable {
+    /// Files in the postal config directory that extend and override the bundled table,
+    /// Wireshark's `manuf` or the IEEE `oui.txt`.
+    fn paths() -> Vec<PathBuf> {
+        dirs::config_dir()
+            .map(|dir| {
+                let dir = dir.join("postal");
+                vec![dir.join("oui.txt"), dir.join("manuf")]
+            })
+            .unwrap_or_default()
+    }
+
+    fn load() -> Self {
+        let mut table = Self::default();
+        table.parse(BUNDLED);
+        for path in Self::paths() {
+            match fs::read_to_string(&path) {
+                Ok(content) => table.parse(&content),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => eprintln!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+        table
+    }
+
+    /// Adds the entries of a `manuf` or `oui.txt` file, lines that fit neither are skipped.
+    fn parse(&mut self, content: &str) {
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((prefix, rest)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let rest = rest.trim_start();
+            let organization = rest
+                .strip_prefix("(hex)")
+                .or_else(|| rest.strip_prefix("(base 16)"));
+            let name = match organization {
+                // oui.txt only has the full name, its first word makes the short one
+                Some(organization) => organization
+                    .split_whitespace()
+                    .next()
+                    .map(|word| word.trim_end_matches([',', '.'])),
+                // the address lines of oui.txt must not pass for manuf entries
+                None if !prefix.contains(':') => continue,
+                None => rest.split_whitespace().next(),
+            };
+            let (Some(name), Some((bits, address))) = (name, parse_prefix(prefix)) else {
+                continue;
+            };
+            if !self.lengths.contains(&bits) {
+                self.lengths.push(bits);
+            }
+            self.names.insert((bits, address), name.to_string());
+        }
+        self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+    }
+
+    /// Short vendor name and prefix length of the longest prefix matching the address.
+    fn lookup(&self, mac: MacAddr) -> Option<(&str, u8)> {
+        let address = mac_to_u64(mac);
+        self.lengths.iter().find_map(|&bits| {
+            self.names
+                .get(&(bits, mask(address, bits)))
+                .map(|name| (name.as_str(), bits))
+        })
+    }
+}
+
+/// Parses `00:1B:21`, `00-1B-21`, `001B21` or a masked `00:50:C2:00:00:00/36`
+/// into the prefix length and the masked address.
+fn parse_prefix(prefix: &str) -> Option<(u8, u64)> {
+    let (hex, bits) = match prefix.split_once('/') {
+        Some((hex, bits)) => (hex, Some(bits.parse::<u8>().ok().filter(|&b| b <= 48)?)),
+        None => (prefix, None),
+    };
+    let digits = hex.replace([':', '-'], "");
+    if digits.is_empty() || digits.len() > 12 || digits.len() % 2 != 0 {
+        return None;
+    }
+    let value = u64::from_str_radix(&digits, 16).ok()?;
+    let address = value << (48 - digits.len() * 4);
+    let bits = bits.unwrap_or(digits.len() as u8 * 4);
+    Some((bits, mask(address, bits)))
+}
+
+fn mac_to_u64(mac: MacAddr) -> u64 {
+    mac.octets()
+        .iter()
+        .fold(0, |address, &octet| (address << 8) | octet as u64)
+}
+
+fn mask(address: u64, bits: u8) -> u64 {
+    match bits {
+        0 => 0,
+        bits => address & !((1u64 << (48 - bits as u32)) - 1) & 0xffff_ffff_ffff,
+    }
+}
+
+/// Short name of the vendor the address was assigned to, e.g. `Intel`.
+pub fn vendor(mac: MacAddr) -> Option<&'static str> {
+    VENDORS.lookup(mac).map(|(name, _)| name)
+}
+
+/// The address with its vendor prefix replaced by the vendor name, e.g. `Intel_3a:4f:12`.
+/// Addresses of unknown vendors come back unchanged.
+pub fn resolve(mac: MacAddr) -> String {
+    let Some((name, bits)) = VENDORS.lookup(mac) else {
+        return mac.to_string();
+    };
+    let octets = mac.octets();
+    let rest = octets[(bits as usize / 8).min(6)..]
+        .iter()
+        .map(|octet| format!("{:02x}", octet))
+        .collect::<Vec<_>>();
+    if rest.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", name, rest.join(":"))
+    }
+}
+
+/// The resolved name followed by the raw address, e.g. `Intel_3a:4f:12 (00:1b:21:3a:4f:12)`.
+pub fn describe(mac: MacAddr) -> String {
+    match vendor(mac) {
+        Some(_) => format!("{} ({})", resolve(mac), mac),
+        None => mac.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mac(text: &str) -> MacAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_prefixes_in_every_notation() {
+        let intel = Some((24, 0x001b21 << 24));
+        assert_eq!(parse_prefix("00:1B:21"), intel);
+        assert_eq!(parse_prefix("00-1b-21"), intel);
+        assert_eq!(parse_prefix("001B21"), intel);
+        // the mask drops the bits after the prefix
+        assert_eq!(
+            parse_prefix("00:50:C2:12:3F:FF/36"),
+            Some((36, 0x0050_c212_3000))
+        );
+        assert_eq!(
+            parse_prefix("70:B3:D5:F2:00:00/28"),
+            Some((28, 0x70b3_d5f0_0000))
+        );
+        for malformed in [
+            "",
+            "00:1B:2",
+            "00:1B:21/49",
+            "00:1B:21/x",
+            "00:1G:21",
+            "00112233445566",
+        ] {
+            assert_eq!(parse_prefix(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn finds_the_longest_matching_prefix() {
+        let mut table = VendorTable::default();
+        table.parse(
+            "# comment\n\
+             00:50:C2\tIeeeRegi\tIEEE Registration Authority\n\
+             00:50:C2:12:30:00/36\tSmallCo\tSmall Company\n\
+             70:B3:D5\tIeeeRegi\tIEEE Registration Authority\n\
+             70:B3:D5:F0:00:00/28\tMidCo\tMid Company  # trailing comment\n\
+             not a prefix line\n",
+        );
+        let name = |text| table.lookup(mac(text));
+        assert_eq!(name("00:50:c2:12:3a:bc"), Some(("SmallCo", 36)));
+        assert_eq!(name("00:50:c2:12:40:00"), Some(("IeeeRegi", 24)));
+        assert_eq!(name("70:b3:d5:f7:12:34"), Some(("MidCo", 28)));
+        assert_eq!(name("70:b3:d5:e7:12:34"), Some(("IeeeRegi", 24)));
+        assert_eq!(name("02:00:00:00:00:01"), None);
+    }
+
+    #[test]
+    fn reads_the_ieee_oui_file() {
+        let mut table = VendorTable::default();
+        table.parse(
+            "00-1B-21   (hex)\t\tIntel Corporate\n\
+             001B21     (base 16)\t\tIntel Corporate\n\
+             \t\t\t\tLot 8, Jalan Hi-Tech 2/3\n\
+             00-00-0C   (hex)\t\tCisco Systems, Inc\n",
+        );
+        assert_eq!(table.lookup(mac("00:1b:21:3a:4f:12")), Some(("Intel", 24)));
+        assert_eq!(table.lookup(mac("00:00:0c:00:00:01")), Some(("Cisco", 24)));
+        assert_eq!(table.names.len(), 2);
+    }
+}
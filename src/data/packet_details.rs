@@ -0,0 +1,380 @@
+use pnet::packet::{
+    ethernet::EtherTypes,
+    tcp::{TcpOptionNumbers, TcpPacket},
+    Packet,
+};
+
+use crate::utils::time_formatter::{format_local_time, format_utc_datetime};
+
+use super::geoip;
+use super::host_names::HostNames;
+use super::oui;
+use super::parsed_packet::{format_tcp_flags, NetworkPacket, ParsedPacket, TransportPacket};
+use super::quic::{self, QuicPacket};
+
+/// One protocol layer in the detail pane, a summary line with its dissected fields.
+#[derive(Debug, Clone)]
+pub struct DetailSection {
+    /// Stable key of the layer, remembers whether it is expanded across packets.
+    pub id: &'static str,
+    pub summary: String,
+    pub fields: Vec<String>,
+}
+
+impl DetailSection {
+    fn new(id: &'static str, summary: String) -> Self {
+        Self {
+            id,
+            summary,
+            fields: vec![],
+        }
+    }
+
+    fn field(&mut self, name: &str, value: impl ToString) {
+        self.fields.push(format!("{}: {}", name, value.to_string()));
+    }
+}
+
+impl ParsedPacket {
+    /// Dissection of every layer of the packet, outermost first.
+    pub fn details(&self, names: &HostNames) -> Vec<DetailSection> {
+        let mut sections = vec![self.frame_details(), self.ethernet_details()];
+        sections.extend(self.network_details(names));
+        sections.extend(self.transport_details());
+        sections
+    }
+
+    fn frame_details(&self) -> DetailSection {
+        let mut frame = DetailSection::new(
+            "frame",
+            format!("Frame {}: {} bytes", self.number, self.data.len()),
+        );
+        frame.field(
+            "Arrival time",
+            format!("{} UTC", format_utc_datetime(self.timestamp)),
+        );
+        frame.field("Local time", format_local_time(self.timestamp));
+        frame.field("Frame number", self.number);
+        frame.field("Frame length", format!("{} bytes", self.data.len()));
+        frame.field("Direction", self.direction);
+        if let Some(process) = &self.process {
+            frame.field("Process", format!("{} on {}", process, process.local));
+        }
+        frame.field("Marked", self.marked);
+        if let Some(comment) = &self.comment {
+            frame.field("Comment", comment);
+        }
+        frame
+    }
+
+    fn ethernet_details(&self) -> DetailSection {
+        let ethertype = self.eth.get_ethertype();
+        let mut eth = DetailSection::new(
+            "eth",
+            format!(
+                "Ethernet II, Src: {}, Dst: {}",
+                oui::describe(self.eth.get_source()),
+                oui::describe(self.eth.get_destination())
+            ),
+        );
+        eth.field("Destination", oui::describe(self.eth.get_destination()));
+        eth.field("Source", oui::describe(self.eth.get_source()));
+        let name = match ethertype {
+            EtherTypes::Ipv4 => "IPv4",
+            EtherTypes::Ipv6 => "IPv6",
+            EtherTypes::Arp => "ARP",
+            _ => "Unknown",
+        };
+        eth.field("Type", format!("{} (0x{:04x})", name, ethertype.0));
+        eth
+    }
+
+    fn network_details(&self, names: &HostNames) -> Option<DetailSection> {
+        let mut section = match &self.net {
+            NetworkPacket::Ipv4(ip) => {
+                let mut section = DetailSection::new(
+                    "ip",
+                    format!(
+                        "Internet Protocol Version 4, Src: {}, Dst: {}",
+                        ip.get_source(),
+                        ip.get_destination()
+                    ),
+                );
+                let flags = ip.get_flags();
+                let flag_names = [(0b010, "Don't fragment"), (0b001, "More fragments")]
+                    .iter()
+                    .filter(|(mask, _)| flags & mask != 0)
+                    .map(|(_, name)| *name)
+                    .collect::<Vec<_>>();
+                section.field("Version", ip.get_version());
+                section.field(
+                    "Header length",
+                    format!("{} bytes", ip.get_header_length() as usize * 4),
+                );
+                section.field(
+                    "Differentiated services",
+                    format!("DSCP {}, ECN {}", ip.get_dscp(), ip.get_ecn()),
+                );
+                section.field("Total length", ip.get_total_length());
+                section.field(
+                    "Identification",
+                    format!("0x{:04x}", ip.get_identification()),
+                );
+                section.field(
+                    "Flags",
+                    format!("0x{:x} ({})", flags, flag_names.join(", ")),
+                );
+                section.field("Fragment offset", ip.get_fragment_offset());
+                section.field("Time to live", ip.get_ttl());
+                section.field(
+                    "Protocol",
+                    format!(
+                        "{} ({})",
+                        ip.get_next_level_protocol(),
+                        ip.get_next_level_protocol().0
+                    ),
+                );
+                section.field("Header checksum", format!("0x{:04x}", ip.get_checksum()));
+                section.field("Source", names.describe(ip.get_source().into()));
+                section.field("Destination", names.describe(ip.get_destination().into()));
+                section
+            }
+            NetworkPacket::Ipv6(ip) => {
+                let mut section = DetailSection::new(
+                    "ip",
+                    format!(
+                        "Internet Protocol Version 6, Src: {}, Dst: {}",
+                        ip.get_source(),
+                        ip.get_destination()
+                    ),
+                );
+                section.field("Version", ip.get_version());
+                section.field("Traffic class", format!("0x{:02x}", ip.get_traffic_class()));
+                section.field("Flow label", format!("0x{:05x}", ip.get_flow_label()));
+                section.field("Payload length", ip.get_payload_length());
+                section.field(
+                    "Next header",
+                    format!("{} ({})", ip.get_next_header(), ip.get_next_header().0),
+                );
+                section.field("Hop limit", ip.get_hop_limit());
+                section.field("Source", names.describe(ip.get_source().into()));
+                section.field("Destination", names.describe(ip.get_destination().into()));
+                section
+            }
+            NetworkPacket::Other => return None,
+        };
+        let addresses = [
+            ("Source GeoIP", self.get_source_ip()),
+            ("Destination GeoIP", self.get_destination_ip()),
+        ];
+        for (name, ip) in addresses {
+            if let Some(geo) = ip.and_then(geoip::lookup) {
+                section.field(name, geo);
+            }
+        }
+        Some(section)
+    }
+
+    fn transport_details(&self) -> Vec<DetailSection> {
+        let (mut section, payload) = match &self.transport {
+            TransportPacket::Tcp(tcp) => (tcp_details(tcp), tcp.payload()),
+            TransportPacket::Udp(udp) => {
+                let mut section = DetailSection::new(
+                    "udp",
+                    format!(
+                        "User Datagram Protocol, Src Port: {}, Dst Port: {}",
+                        udp.get_source(),
+                        udp.get_destination()
+                    ),
+                );
+                section.field("Source port", udp.get_source());
+                section.field("Destination port", udp.get_destination());
+                section.field("Length", udp.get_length());
+                section.field("Checksum", format!("0x{:04x}", udp.get_checksum()));
+                (section, udp.payload())
+            }
+            TransportPacket::Other => return vec![],
+        };
+        section.field("Payload length", format!("{} bytes", payload.len()));
+        let mut sections = vec![section];
+        sections.extend(self.tcp_analysis_details());
+        sections.extend(self.quic.packets.iter().map(quic_details));
+        if !payload.is_empty() {
+            let mut data = DetailSection::new("data", format!("Data ({} bytes)", payload.len()));
+            let text = String::from_utf8_lossy(&payload[..payload.len().min(64)])
+                .chars()
+                .map(|c| if c.is_control() { '.' } else { c })
+                .collect::<String>();
+            data.field("Text", text);
+            sections.push(data);
+        }
+        sections
+    }
+
+    fn tcp_analysis_details(&self) -> Option<DetailSection> {
+        let analysis = &self.tcp_analysis;
+        let labels = analysis.labels();
+        if labels.is_empty() && analysis.ack_rtt.is_none() && analysis.handshake_rtt.is_none() {
+            return None;
+        }
+        let mut section = DetailSection::new("tcp.analysis", String::from("[SEQ/ACK analysis]"));
+        if let Some(rtt) = analysis.handshake_rtt {
+            section.field("Handshake RTT", format!("{:.6} seconds", rtt.as_secs_f64()));
+        }
+        if let Some(rtt) = analysis.ack_rtt {
+            section.field("ACK RTT", format!("{:.6} seconds", rtt.as_secs_f64()));
+        }
+        for label in labels {
+            section.field("Problem", label);
+        }
+        Some(section)
+    }
+}
+
+fn tcp_details(tcp: &TcpPacket) -> DetailSection {
+    let mut section = DetailSection::new(
+        "tcp",
+        format!(
+            "Transmission Control Protocol, Src Port: {}, Dst Port: {}, Seq: {}, Ack: {}, Len: {}",
+            tcp.get_source(),
+            tcp.get_destination(),
+            tcp.get_sequence(),
+            tcp.get_acknowledgement(),
+            tcp.payload().len()
+        ),
+    );
+    section.field("Source port", tcp.get_source());
+    section.field("Destination port", tcp.get_destination());
+    section.field("Sequence number", tcp.get_sequence());
+    section.field("Acknowledgment number", tcp.get_acknowledgement());
+    section.field(
+        "Header length",
+        format!("{} bytes", tcp.get_data_offset() as usize * 4),
+    );
+    section.field(
+        "Flags",
+        format!(
+            "0x{:03x} ({})",
+            tcp.get_flags(),
+            format_tcp_flags(tcp.get_flags())
+        ),
+    );
+    section.field("Window", tcp.get_window());
+    section.field("Checksum", format!("0x{:04x}", tcp.get_checksum()));
+    section.field("Urgent pointer", tcp.get_urgent_ptr());
+    for option in tcp.get_options_iter() {
+        let data = option.payload();
+        let value = match option.get_number() {
+            TcpOptionNumbers::NOP | TcpOptionNumbers::EOL => continue,
+            TcpOptionNumbers::MSS if data.len() == 2 => {
+                format!(
+                    "Maximum segment size: {}",
+                    u16::from_be_bytes([data[0], data[1]])
+                )
+            }
+            TcpOptionNumbers::WSCALE if data.len() == 1 => {
+                format!(
+                    "Window scale: {} (multiply by {})",
+                    data[0],
+                    1u32 << data[0].min(14)
+                )
+            }
+            TcpOptionNumbers::SACK_PERMITTED => String::from("SACK permitted"),
+            TcpOptionNumbers::SACK => format!("SACK ({} blocks)", data.len() / 8),
+            TcpOptionNumbers::TIMESTAMPS if data.len() == 8 => format!(
+                "Timestamps: TSval {}, TSecr {}",
+                u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+                u32::from_be_bytes([data[4], data[5], data[6], data[7]])
+            ),
+            number => format!("Kind {} ({} bytes)", number.0, data.len()),
+        };
+        section.field("Option", value);
+    }
+    section
+}
+
+fn quic_details(packet: &QuicPacket) -> DetailSection {
+    let mut section = DetailSection::new("quic", format!("QUIC, {}", packet));
+    let header_form = if packet.is_long_header() {
+        "Long"
+    } else {
+        "Short"
+    };
+    section.field("Header form", header_form);
+    section.field("Packet type", packet.packet_type);
+    let version = |version| format!("{} (0x{:08x})", quic::version_name(version), version);
+    match packet.version {
+        Some(0) => {
+            for supported in packet.supported_versions() {
+                section.field("Supported version", version(supported));
+            }
+        }
+        Some(v) => section.field("Version", version(v)),
+        None => {}
+    }
+    let connection_id = |id: &[u8]| match id.len() {
+        0 => String::from("empty"),
+        length => format!("{} ({} bytes)", quic::hex(id), length),
+    };
+    match &packet.dcid {
+        Some(dcid) => section.field("Destination connection ID", connection_id(dcid)),
+        None => section.field("Destination connection ID", "unknown"),
+    }
+    if let Some(scid) = &packet.scid {
+        section.field("Source connection ID", connection_id(scid));
+    }
+    if let Some(token_length) = packet.token_length {
+        section.field("Token length", token_length);
+    }
+    section.field("Packet length", format!("{} bytes", packet.data.len()));
+    match &packet.initial {
+        None => {}
+        Some(Ok(payload)) => {
+            section.field("Packet number", payload.packet_number);
+            for frame in &payload.frames {
+                section.field("Frame", frame);
+            }
+            if let Some(hello) = quic::client_hello(&quic::crypto_stream(&payload.frames)) {
+                let message = if hello.complete {
+                    "ClientHello"
+                } else {
+                    "ClientHello (continued in another packet)"
+                };
+                section.field("TLS handshake", message);
+                if let Some(name) = hello.server_name {
+                    section.field("Server name", name);
+                }
+                if !hello.alpn.is_empty() {
+                    section.field("ALPN", hello.alpn.join(", "));
+                }
+            }
+        }
+        Some(Err(e)) => section.field("Payload", e),
+    }
+    section
+}
+
+/// Classic hex dump lines: offset, 16 bytes in hex and their printable characters.
+pub fn hex_dump(data: &[u8]) -> Vec<String> {
+    data.chunks(16)
+        .enumerate()
+        .map(|(line, bytes)| {
+            let hex = bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = bytes
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            format!("{:04x}  {:<47}  {}", line * 16, hex, ascii)
+        })
+        .collect()
+}
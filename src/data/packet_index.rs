@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::column::{ColumnLayout, SortOrder};
+use super::parsed_packet::ParsedPacket;
+
+/// Positions in the packet store of all packets passing the display filters.
+/// It is updated incrementally as packets arrive, so a redraw only has to look up the rows
+/// that are actually on screen instead of filtering the whole capture.
+#[derive(Debug, Default)]
+pub struct PacketIndex {
+    /// Matching packets in capture order.
+    matching: Vec<usize>,
+    /// The newest `limit` matching packets in column sort order, empty while unsorted.
+    sorted: Vec<usize>,
+    /// Row of each store index in `sorted`.
+    rows: HashMap<usize, usize>,
+    /// Number of store packets that have been checked against the filters.
+    indexed: usize,
+}
+
+impl PacketIndex {
+    pub fn clear(&mut self) {
+        self.matching.clear();
+        self.sorted.clear();
+        self.rows.clear();
+        self.indexed = 0;
+    }
+
+    pub fn indexed(&self) -> usize {
+        self.indexed
+    }
+
+    /// Drops the first `count` store packets, which the caller removed from the store.
+    pub fn forget(&mut self, count: usize) {
+        for positions in [&mut self.matching, &mut self.sorted] {
+            positions.retain(|&i| i >= count);
+            positions.iter_mut().for_each(|i| *i -= count);
+        }
+        self.indexed = self.indexed.saturating_sub(count);
+        self.index_rows();
+    }
+
+    pub fn rebuild(
+        &mut self,
+        packets: &[ParsedPacket],
+        filter: impl Fn(&ParsedPacket) -> bool,
+        limit: usize,
+        layout: &ColumnLayout,
+    ) {
+        self.matching = packets
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| filter(p))
+            .map(|(i, _)| i)
+            .collect();
+        self.indexed = packets.len();
+        self.resort(packets, limit, layout);
+    }
+
+    /// Recomputes the sorted window, needed after the sort column or the view limit changed.
+    pub fn resort(&mut self, packets: &[ParsedPacket], limit: usize, layout: &ColumnLayout) {
+        self.sorted.clear();
+        if layout.sort.is_some() {
+            self.sorted = self.window(limit).to_vec();
+            self.sorted
+                .sort_by(|&a, &b| compare(layout, &packets[a], &packets[b]));
+        }
+        self.index_rows();
+    }
+
+    /// Indexes all store packets that arrived since the last call.
+    pub fn catch_up(
+        &mut self,
+        packets: &[ParsedPacket],
+        filter: impl Fn(&ParsedPacket) -> bool,
+        limit: usize,
+        layout: &ColumnLayout,
+    ) {
+        let first = self.matching.len();
+        self.matching
+            .extend((self.indexed..packets.len()).filter(|&i| filter(&packets[i])));
+        self.indexed = packets.len();
+        if layout.sort.is_some() && self.matching.len() > first {
+            self.merge(packets, first, limit, layout);
+        }
+    }
+
+    /// Merges the packets matched from position `first` on into the sorted window.
+    /// A whole batch costs one pass over the window instead of one per packet.
+    fn merge(
+        &mut self,
+        packets: &[ParsedPacket],
+        first: usize,
+        limit: usize,
+        layout: &ColumnLayout,
+    ) {
+        let window = self.window(limit);
+        let Some(&oldest) = window.first() else {
+            self.sorted.clear();
+            self.rows.clear();
+            return;
+        };
+        let new = self.matching.len() - first;
+        let mut added = window[window.len().saturating_sub(new)..].to_vec();
+        added.sort_by(|&a, &b| compare(layout, &packets[a], &packets[b]));
+
+        // drops the packets that left the window, store indices grow in capture order
+        let kept = std::mem::take(&mut self.sorted)
+            .into_iter()
+            .filter(|&i| i >= oldest);
+        let mut added = added.into_iter().peekable();
+        for index in kept {
+            while let Some(&next) = added.peek() {
+                if compare(layout, &packets[next], &packets[index]) == Ordering::Greater {
+                    break;
+                }
+                self.sorted.push(next);
+                added.next();
+            }
+            self.sorted.push(index);
+        }
+        self.sorted.extend(added);
+        self.index_rows();
+    }
+
+    fn index_rows(&mut self) {
+        self.rows = self
+            .sorted
+            .iter()
+            .enumerate()
+            .map(|(row, &index)| (index, row))
+            .collect();
+    }
+
+    /// Store indices of all indexed matching packets regardless of the view limit,
+    /// in column sort order if one is set and in capture order otherwise.
+    pub fn all(&self, packets: &[ParsedPacket], layout: &ColumnLayout) -> Vec<usize> {
+        let mut all = self.matching.clone();
+        if layout.sort.is_some() {
+            all.sort_by(|&a, &b| compare(layout, &packets[a], &packets[b]));
+        }
+        all
+    }
+
+    /// Number of rows shown with the given view limit.
+    pub fn len(&self, limit: usize) -> usize {
+        self.matching.len().min(limit)
+    }
+
+    /// Store index of the packet shown in `row`, newest first unless a sort column is set.
+    pub fn row(&self, row: usize, limit: usize, layout: &ColumnLayout) -> Option<usize> {
+        if layout.sort.is_some() {
+            self.sorted.get(row).copied()
+        } else {
+            let window = self.window(limit);
+            window.len().checked_sub(row + 1).map(|i| window[i])
+        }
+    }
+
+    /// Row in which the packet at `index` in the store is shown, if it is shown at all.
+    pub fn row_of(&self, index: usize, limit: usize, layout: &ColumnLayout) -> Option<usize> {
+        if layout.sort.is_some() {
+            self.rows.get(&index).copied()
+        } else {
+            let window = self.window(limit);
+            let pos = window.binary_search(&index).ok()?;
+            Some(window.len() - 1 - pos)
+        }
+    }
+
+    fn window(&self, limit: usize) -> &[usize] {
+        &self.matching[self.matching.len().saturating_sub(limit)..]
+    }
+}
+
+/// Column order with the frame number as tie breaker, so equal keys keep capture order.
+fn compare(layout: &ColumnLayout, a: &ParsedPacket, b: &ParsedPacket) -> Ordering {
+    match layout.sort {
+        Some((field, SortOrder::Ascending)) => field.compare(a, b),
+        Some((field, SortOrder::Descending)) => field.compare(b, a),
+        None => Ordering::Equal,
+    }
+    .then(a.number.cmp(&b.number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::column::Field;
+    use crate::data::test_packets;
+
+    /// Packets numbered from 1, with payloads of the given lengths.
+    fn packets(sizes: &[usize]) -> Vec<ParsedPacket> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| {
+                let mut packet = test_packets::udp("10.0.0.1:5000", "192.0.2.7:53", &vec![0; size]);
+                packet.number = i + 1;
+                packet
+            })
+            .collect()
+    }
+
+    fn by_size(order: SortOrder) -> ColumnLayout {
+        ColumnLayout {
+            sort: Some((Field::Size, order)),
+            ..ColumnLayout::default()
+        }
+    }
+
+    fn even(packet: &ParsedPacket) -> bool {
+        packet.number.is_multiple_of(2)
+    }
+
+    /// Payload sizes of the shown rows, top to bottom.
+    fn rows(
+        index: &PacketIndex,
+        packets: &[ParsedPacket],
+        limit: usize,
+        layout: &ColumnLayout,
+    ) -> Vec<usize> {
+        (0..index.len(limit))
+            .map(|row| {
+                let i = index.row(row, limit, layout).unwrap();
+                assert_eq!(index.row_of(i, limit, layout), Some(row));
+                packets[i].data.len() - 42
+            })
+            .collect()
+    }
+
+    #[test]
+    fn appends_in_batches_like_a_rebuild() {
+        let sizes = [5, 1, 9, 3, 7, 3, 8, 2, 6, 4, 0, 9];
+        let all = packets(&sizes);
+        for layout in [
+            ColumnLayout::default(),
+            by_size(SortOrder::Ascending),
+            by_size(SortOrder::Descending),
+        ] {
+            for limit in [usize::MAX, 3, 1] {
+                let mut index = PacketIndex::default();
+                for end in [2, 3, 7, 7, 12] {
+                    index.catch_up(&all[..end], |_| true, limit, &layout);
+                    let mut rebuilt = PacketIndex::default();
+                    rebuilt.rebuild(&all[..end], |_| true, limit, &layout);
+                    assert_eq!(
+                        rows(&index, &all, limit, &layout),
+                        rows(&rebuilt, &all, limit, &layout),
+                        "{:?} up to {} with limit {}",
+                        layout.sort,
+                        end,
+                        limit
+                    );
+                }
+                assert_eq!(index.indexed(), sizes.len());
+            }
+        }
+
+        let layout = by_size(SortOrder::Ascending);
+        let mut index = PacketIndex::default();
+        index.catch_up(&all[..6], even, 2, &layout);
+        index.catch_up(&all, even, 2, &layout);
+        // the two newest even numbered packets, 4 and 9 bytes
+        assert_eq!(rows(&index, &all, 2, &layout), vec![4, 9]);
+        assert_eq!(index.all(&all, &layout).len(), 6);
+    }
+
+    #[test]
+    fn forgets_trimmed_packets() {
+        let mut all = packets(&[5, 1, 9, 3, 7, 3]);
+        let layout = by_size(SortOrder::Descending);
+        let mut index = PacketIndex::default();
+        index.catch_up(&all, |_| true, 4, &layout);
+        assert_eq!(rows(&index, &all, 4, &layout), vec![9, 7, 3, 3]);
+
+        all.drain(..3);
+        index.forget(3);
+        assert_eq!(index.indexed(), 3);
+        assert_eq!(rows(&index, &all, 4, &layout), vec![7, 3, 3]);
+        // trimmed packets are no row anymore, the survivors moved down in the store
+        assert_eq!(index.row_of(3, 4, &layout), None);
+        assert_eq!(index.row(0, 4, &layout), Some(1));
+
+        all.extend(packets(&[0; 8]).into_iter().skip(6).take(1));
+        index.catch_up(&all, |_| true, 4, &layout);
+        assert_eq!(rows(&index, &all, 4, &layout), vec![7, 3, 3, 0]);
+    }
+
+    #[test]
+    fn finds_rows_in_the_sorted_window() {
+        let all = packets(&[5, 1, 9, 3, 7, 3, 8]);
+        let layout = by_size(SortOrder::Ascending);
+        let mut index = PacketIndex::default();
+        index.rebuild(&all, |_| true, 5, &layout);
+        // the window holds the newest five: 9, 3, 7, 3 and 8 bytes
+        assert_eq!(rows(&index, &all, 5, &layout), vec![3, 3, 7, 8, 9]);
+        assert_eq!(index.row_of(2, 5, &layout), Some(4));
+        assert_eq!(index.row_of(3, 5, &layout), Some(0));
+        assert_eq!(index.row_of(5, 5, &layout), Some(1));
+        // outside the view limit
+        assert_eq!(index.row_of(0, 5, &layout), None);
+
+        // unsorted, newest first
+        let layout = ColumnLayout::default();
+        index.resort(&all, 5, &layout);
+        assert_eq!(index.row_of(6, 5, &layout), Some(0));
+        assert_eq!(index.row_of(2, 5, &layout), Some(4));
+        assert_eq!(index.row_of(1, 5, &layout), None);
+    }
+}
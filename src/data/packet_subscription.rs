@@ -1,4 +1,6 @@
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use iced::futures::stream;
 use iced_futures::subscription::Recipe;
@@ -12,7 +14,13 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::{data::parsed_packet::ParsedPacket, Message};
+use crate::{
+    data::{
+        display_filter::DisplayFilter, host_names::HostNames, parsed_packet::ParsedPacket, pcap,
+        process::ProcessTable, quic::QuicTracker, ring_buffer::RingBuffer,
+    },
+    Message,
+};
 
 #[derive(Debug)]
 pub struct PacketSubscription {
@@ -28,25 +36,74 @@ impl PacketSubscription {
         }
     }
 
+    /// Reads frames from the interface until cancelled. The capture filter is a display
+    /// filter checked here in userspace, every frame still crosses from the kernel.
     pub fn sniff(
         tx: Sender<ParsedPacket>,
         interface: NetworkInterface,
         http_only: bool,
+        capture_filter: Option<DisplayFilter>,
+        mut ring_buffer: Option<RingBuffer>,
         token: CancellationToken,
     ) {
         if let Ok(Ethernet(_, mut rx)) = datalink::channel(&interface, Config::default()) {
+            let processes = ProcessTable::start();
+            // names from the DNS answers captured so far, for `ip.host` in the capture filter
+            let mut names = HostNames::default();
+            // and the QUIC packets, for the `quic` fields
+            let mut quic = QuicTracker::default();
             while !token.is_cancelled() {
                 if let Ok(packet) = rx.next() {
-                    match ParsedPacket::parse(packet.to_vec(), http_only) {
-                        Some(p) => {
-                            let _ = tx.blocking_send(p);
+                    // pnet doesn't hand out the kernel timestamp, so take the time right
+                    // after the frame was read
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                    let mut parsed = ParsedPacket::parse(packet.to_vec(), now, &interface);
+                    if let (Some(p), Some(_)) = (&mut parsed, &capture_filter) {
+                        names.learn(p);
+                        quic.analyze(p);
+                    }
+                    // frames too broken to parse only pass when there is nothing to check
+                    let wanted = match &parsed {
+                        Some(p) => capture_filter.as_ref().is_none_or(|f| f.matches(p, &names)),
+                        None => capture_filter.is_none(),
+                    };
+                    if !wanted {
+                        continue;
+                    }
+                    // the disk gets every frame, HTTP only just narrows what is shown
+                    if let Some(writer) = &mut ring_buffer {
+                        if let Err(e) = writer.write(now, packet) {
+                            eprintln!("Writing to disk stopped: {}", e);
+                            ring_buffer = None;
                         }
-                        None => {}
+                    }
+                    if let Some(mut p) = parsed.filter(|p| !http_only || p.is_http()) {
+                        processes.resolve(&mut p);
+                        let _ = tx.blocking_send(p);
                     }
                 }
             }
         }
     }
+
+    /// Reads a capture file, directions are classified relative to the given interface.
+    pub fn read_file(
+        path: &Path,
+        interface: &NetworkInterface,
+    ) -> Result<Vec<ParsedPacket>, String> {
+        let mut packets = pcap::read_file(path)?
+            .into_iter()
+            .filter_map(|frame| {
+                let mut packet = ParsedPacket::parse(frame.data, frame.timestamp, interface)?;
+                packet.annotate(frame.comments);
+                Some(packet)
+            })
+            .collect::<Vec<_>>();
+        for (i, packet) in packets.iter_mut().enumerate() {
+            packet.number = i + 1;
+        }
+        Ok(packets)
+    }
 }
 
 impl Recipe for PacketSubscription {
@@ -72,11 +129,11 @@ impl Recipe for PacketSubscription {
                     tokio::select! {
                         _ = t.cancelled() => {
                             // drain channel instantly on cancel
-                            return Some((Message::PacketsDrained(buffer), (r.clone(), t.clone())));
+                            Some((Message::PacketsDrained(buffer), (r.clone(), t.clone())))
                         }
                         _ = tokio::time::sleep(Duration::from_millis(100)) => {
                             // no cancel -> buffer longer
-                            return Some((Message::PacketsReceived(buffer), (r.clone(), t.clone())));
+                            Some((Message::PacketsReceived(buffer), (r.clone(), t.clone())))
                         }
                     }
                 } else {
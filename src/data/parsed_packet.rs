@@ -1,78 +1,117 @@
 use core::fmt;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
+use pnet::datalink::NetworkInterface;
 use pnet::packet::ip::IpNextHeaderProtocol;
 use pnet::packet::ipv6::Ipv6Packet;
 use pnet::packet::{
+    arp::{ArpOperations, ArpPacket},
     ethernet::{EtherTypes, EthernetPacket},
     ip::IpNextHeaderProtocols,
     ipv4::Ipv4Packet,
-    tcp::TcpPacket,
+    tcp::{TcpFlags, TcpPacket},
     udp::UdpPacket,
     Packet,
 };
 
+use super::direction::Direction;
+use super::process::Process;
+use super::quic::QuicAnalysis;
+use super::tcp_analysis::TcpAnalysis;
+
+/// Packet comment that stands for a mark in pcapng files.
+const MARK_COMMENT: &str = "postal:marked";
+
 #[derive(Debug)]
 pub struct ParsedPacket {
-    pub timestring: String,
+    pub number: usize,
+    /// Capture time since the unix epoch.
+    pub timestamp: Duration,
+    #[allow(clippy::box_collection)]
     pub data: Pin<Box<Vec<u8>>>,
     pub eth: EthernetPacket<'static>,
     pub net: NetworkPacket,
     pub transport: TransportPacket,
+    pub direction: Direction,
+    pub tcp_analysis: TcpAnalysis,
+    pub quic: QuicAnalysis,
+    /// Flagged by the user for later review.
+    pub marked: bool,
+    pub comment: Option<String>,
+    /// Local process the packet belongs to, known for live captures on Linux only.
+    pub process: Option<Process>,
 }
 
 impl ParsedPacket {
     const ETHERNET_HEADER: usize = 14;
+    /// IPv6 extension headers are not followed, the next header field names what is after.
+    const IPV6_HEADER: usize = 40;
 
-    pub fn parse(data: Vec<u8>, discard_non_http: bool) -> Option<Self> {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    /// Parses an Ethernet frame captured at `timestamp` (since the unix epoch).
+    pub fn parse(data: Vec<u8>, timestamp: Duration, interface: &NetworkInterface) -> Option<Self> {
         let raw_data_static: &'static [u8] = unsafe { std::mem::transmute(&data[..]) };
         let eth = EthernetPacket::new(raw_data_static)?;
         let net = match eth.get_ethertype() {
             EtherTypes::Ipv4 => NetworkPacket::Ipv4(Ipv4Packet::new(&raw_data_static[14..])?),
             EtherTypes::Ipv6 => NetworkPacket::Ipv6(Ipv6Packet::new(&raw_data_static[14..])?),
-            _ => return None,
+            // ARP and friends are kept with their Ethernet header only
+            _ => NetworkPacket::Other,
         };
 
         let transport = match net {
             NetworkPacket::Ipv4(ref p) => {
                 let offset = Self::ETHERNET_HEADER + p.get_header_length() as usize * 4;
-                parse_transport_protocol(
-                    p.get_next_level_protocol(),
-                    raw_data_static,
-                    offset,
-                    discard_non_http,
-                )?
+                parse_transport_protocol(p.get_next_level_protocol(), raw_data_static, offset)
             }
             NetworkPacket::Ipv6(ref p) => {
-                let header_len = p.packet().len() - p.get_payload_length() as usize;
-                let offset = Self::ETHERNET_HEADER + header_len;
-                parse_transport_protocol(
-                    p.get_next_header(),
-                    raw_data_static,
-                    offset,
-                    discard_non_http,
-                )?
+                let offset = Self::ETHERNET_HEADER + Self::IPV6_HEADER;
+                parse_transport_protocol(p.get_next_header(), raw_data_static, offset)
             }
-            NetworkPacket::Other => return None,
+            NetworkPacket::Other => TransportPacket::Other,
         };
 
-        let hours = (now.as_secs() / 3600) % 24;
-        let minutes = (now.as_secs() / 60) % 60;
-        let seconds = now.as_secs() % 60;
-        let micros = now.subsec_micros();
-
-        Some(Self {
-            timestring: format!("{:02}:{:02}:{:02}.{:06}", hours, minutes, seconds, micros),
+        let mut packet = Self {
+            number: 0,
+            timestamp,
             data: Pin::new(Box::new(data)),
             eth,
             net,
             transport,
-        })
+            direction: Direction::Forwarded,
+            tcp_analysis: TcpAnalysis::default(),
+            quic: QuicAnalysis::default(),
+            marked: false,
+            comment: None,
+            process: None,
+        };
+        packet.direction = Direction::classify(&packet, interface);
+        Some(packet)
+    }
+
+    /// Restores mark and comment from the packet comments of a pcapng file.
+    pub fn annotate(&mut self, comments: Vec<String>) {
+        let (marks, notes): (Vec<_>, Vec<_>) =
+            comments.into_iter().partition(|c| c == MARK_COMMENT);
+        self.marked = !marks.is_empty();
+        self.comment = (!notes.is_empty()).then(|| notes.join("\n"));
+    }
+
+    /// Packet comments for a pcapng file, a mark is stored as a comment of its own.
+    pub fn pcapng_comments(&self) -> Vec<&str> {
+        let mark = self.marked.then_some(MARK_COMMENT);
+        mark.into_iter().chain(self.comment.as_deref()).collect()
+    }
+
+    /// TCP or UDP from or to port 80 or 443, what the HTTP only option keeps.
+    pub fn is_http(&self) -> bool {
+        match (self.get_source_port(), self.get_destination_port()) {
+            (Some(source), Some(destination)) => is_http_port(source, destination),
+            _ => false,
+        }
     }
 
     pub fn get_source_ip(&self) -> Option<IpAddr> {
@@ -83,7 +122,158 @@ impl ParsedPacket {
         }
     }
 
-    pub fn get_port(&self) -> Option<u16> {
+    pub fn get_destination_ip(&self) -> Option<IpAddr> {
+        match &self.net {
+            NetworkPacket::Ipv4(v4) => Some(IpAddr::V4(v4.get_destination())),
+            NetworkPacket::Ipv6(v6) => Some(IpAddr::V6(v6.get_destination())),
+            NetworkPacket::Other => None,
+        }
+    }
+
+    /// IPv4 time to live or IPv6 hop limit.
+    pub fn get_ttl(&self) -> Option<u8> {
+        match &self.net {
+            NetworkPacket::Ipv4(v4) => Some(v4.get_ttl()),
+            NetworkPacket::Ipv6(v6) => Some(v6.get_hop_limit()),
+            NetworkPacket::Other => None,
+        }
+    }
+
+    pub fn get_tcp_flags(&self) -> Option<String> {
+        match &self.transport {
+            TransportPacket::Tcp(tcp) => Some(format_tcp_flags(tcp.get_flags())),
+            _ => None,
+        }
+    }
+
+    /// One-line summary of the transport layer, similar to Wireshark's info column.
+    /// Problems found by the TCP analysis come first.
+    pub fn info(&self) -> String {
+        let labels = self.tcp_analysis.labels();
+        let summary = self.transport_info();
+        if labels.is_empty() {
+            summary
+        } else {
+            format!("[{}] {}", labels.join("] ["), summary)
+        }
+    }
+
+    fn transport_info(&self) -> String {
+        match &self.transport {
+            TransportPacket::Tcp(tcp) => format!(
+                "{} → {} [{}] Seq={} Ack={} Win={} Len={}",
+                tcp.get_source(),
+                tcp.get_destination(),
+                format_tcp_flags(tcp.get_flags()),
+                tcp.get_sequence(),
+                tcp.get_acknowledgement(),
+                tcp.get_window(),
+                tcp.payload().len()
+            ),
+            TransportPacket::Udp(udp) => {
+                let quic = &self.quic.packets;
+                if quic.is_empty() {
+                    return format!(
+                        "{} → {} Len={}",
+                        udp.get_source(),
+                        udp.get_destination(),
+                        udp.payload().len()
+                    );
+                }
+                let packets = quic.iter().map(ToString::to_string).collect::<Vec<_>>();
+                let server_name = self
+                    .quic
+                    .client_hello
+                    .as_ref()
+                    .and_then(|hello| hello.server_name.as_ref())
+                    .map(|name| format!(", SNI={}", name))
+                    .unwrap_or_default();
+                format!(
+                    "{} → {} QUIC {}{}",
+                    udp.get_source(),
+                    udp.get_destination(),
+                    packets.join("; "),
+                    server_name
+                )
+            }
+            TransportPacket::Other => match &self.net {
+                NetworkPacket::Ipv4(v4) => format!("IP protocol {}", v4.get_next_level_protocol()),
+                NetworkPacket::Ipv6(v6) => format!("IP protocol {}", v6.get_next_header()),
+                NetworkPacket::Other => match ArpPacket::new(self.eth.payload()) {
+                    Some(arp) if self.eth.get_ethertype() == EtherTypes::Arp => {
+                        match arp.get_operation() {
+                            ArpOperations::Request => format!(
+                                "Who has {}? Tell {}",
+                                arp.get_target_proto_addr(),
+                                arp.get_sender_proto_addr()
+                            ),
+                            ArpOperations::Reply => format!(
+                                "{} is at {}",
+                                arp.get_sender_proto_addr(),
+                                arp.get_sender_hw_addr()
+                            ),
+                            operation => format!("ARP operation {}", operation.0),
+                        }
+                    }
+                    _ => format!("Ethertype 0x{:04x}", self.eth.get_ethertype().0),
+                },
+            },
+        }
+    }
+
+    /// Application protocol, guessed from well-known ports.
+    pub fn app_protocol(&self) -> Option<AppProtocol> {
+        let (source, destination) = (self.get_source_port()?, self.get_destination_port()?);
+        let uses = |ports: &[u16]| ports.contains(&source) || ports.contains(&destination);
+        let payload = match &self.transport {
+            TransportPacket::Tcp(tcp) => tcp.payload(),
+            _ => &[],
+        };
+        if uses(&[53]) {
+            Some(AppProtocol::Dns)
+        } else if !self.quic.packets.is_empty() {
+            Some(AppProtocol::Quic)
+        } else if payload.is_empty() {
+            None
+        } else if uses(&[80, 8080]) {
+            Some(AppProtocol::Http)
+        } else if uses(&[443, 8443]) {
+            Some(AppProtocol::Tls)
+        } else {
+            None
+        }
+    }
+
+    /// Type and code of an ICMP (`v6 == false`) or ICMPv6 message.
+    pub fn get_icmp(&self, v6: bool) -> Option<(u8, u8)> {
+        let payload = match &self.net {
+            NetworkPacket::Ipv4(ip)
+                if !v6 && ip.get_next_level_protocol() == IpNextHeaderProtocols::Icmp =>
+            {
+                ip.payload()
+            }
+            NetworkPacket::Ipv6(ip)
+                if v6 && ip.get_next_header() == IpNextHeaderProtocols::Icmpv6 =>
+            {
+                ip.payload()
+            }
+            _ => return None,
+        };
+        match payload {
+            [icmp_type, code, ..] => Some((*icmp_type, *code)),
+            _ => None,
+        }
+    }
+
+    pub fn get_source_port(&self) -> Option<u16> {
+        match &self.transport {
+            TransportPacket::Tcp(tcp) => Some(tcp.get_source()),
+            TransportPacket::Udp(udp) => Some(udp.get_source()),
+            TransportPacket::Other => None,
+        }
+    }
+
+    pub fn get_destination_port(&self) -> Option<u16> {
         match &self.transport {
             TransportPacket::Tcp(tcp) => Some(tcp.get_destination()),
             TransportPacket::Udp(udp) => Some(udp.get_destination()),
@@ -92,41 +282,44 @@ impl ParsedPacket {
     }
 }
 
+/// The transport header at `offset`, `Other` if the frame was cut off before its end.
 fn parse_transport_protocol(
     protocol: IpNextHeaderProtocol,
     data: &'static [u8],
     offset: usize,
-    discard_non_http: bool,
-) -> Option<TransportPacket> {
-    return match protocol {
-        IpNextHeaderProtocols::Tcp => {
-            let packet = TcpPacket::new(&data[offset..])?;
-            if discard_non_http
-                && (packet.get_destination() != 80 && packet.get_destination() != 443)
-            {
-                None
-            } else {
-                Some(TransportPacket::Tcp(packet))
-            }
-        }
-        IpNextHeaderProtocols::Udp => {
-            let packet = UdpPacket::new(&data[offset..])?;
-            if discard_non_http
-                && (packet.get_destination() != 80 && packet.get_destination() != 443)
-            {
-                None
-            } else {
-                Some(TransportPacket::Udp(packet))
-            }
-        }
-        _ => {
-            if discard_non_http {
-                None
-            } else {
-                Some(TransportPacket::Other)
-            }
-        }
+) -> TransportPacket {
+    let Some(data) = data.get(offset..) else {
+        return TransportPacket::Other;
     };
+    let transport = match protocol {
+        IpNextHeaderProtocols::Tcp => TcpPacket::new(data).map(TransportPacket::Tcp),
+        IpNextHeaderProtocols::Udp => UdpPacket::new(data).map(TransportPacket::Udp),
+        _ => None,
+    };
+    transport.unwrap_or(TransportPacket::Other)
+}
+
+pub fn format_tcp_flags(flags: u8) -> String {
+    [
+        (TcpFlags::SYN, "SYN"),
+        (TcpFlags::FIN, "FIN"),
+        (TcpFlags::RST, "RST"),
+        (TcpFlags::PSH, "PSH"),
+        (TcpFlags::ACK, "ACK"),
+        (TcpFlags::URG, "URG"),
+        (TcpFlags::ECE, "ECE"),
+        (TcpFlags::CWR, "CWR"),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, name)| *name)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Requests go to a well-known port, responses come from it.
+fn is_http_port(source: u16, destination: u16) -> bool {
+    [80, 443].contains(&source) || [80, 443].contains(&destination)
 }
 
 impl fmt::Display for ParsedPacket {
@@ -143,16 +336,18 @@ impl fmt::Display for ParsedPacket {
             NetworkPacket::Other => return write!(f, ""),
         };
 
-        let (protocol, port) = match &self.transport {
-            TransportPacket::Tcp(tcp) => ("TCP", tcp.get_destination()),
-            TransportPacket::Udp(udp) => ("UDP", udp.get_destination()),
+        let (protocol, source_port, dest_port) = match &self.transport {
+            TransportPacket::Tcp(tcp) => ("TCP", tcp.get_source(), tcp.get_destination()),
+            TransportPacket::Udp(udp) => ("UDP", udp.get_source(), udp.get_destination()),
             TransportPacket::Other => return write!(f, "Unsupported Transport Protocol"),
         };
 
         write!(
             f,
-            "{} Packet @ Port: {}, Source IP: {}, Destination IP: {}",
-            protocol, port, source, dest
+            "{} Packet, Source: {}, Destination: {}",
+            protocol,
+            SocketAddr::new(source, source_port),
+            SocketAddr::new(dest, dest_port)
         )
     }
 }
@@ -162,11 +357,38 @@ impl Clone for ParsedPacket {
         let eth_data = self.eth.packet().to_vec();
         let eth_clone = EthernetPacket::owned(eth_data).unwrap();
         Self {
-            timestring: self.timestring.clone(),
+            number: self.number,
+            timestamp: self.timestamp,
             data: self.data.clone(),
             eth: eth_clone,
             net: self.net.clone(),
             transport: self.transport.clone(),
+            direction: self.direction,
+            tcp_analysis: self.tcp_analysis,
+            quic: self.quic.clone(),
+            marked: self.marked,
+            comment: self.comment.clone(),
+            process: self.process.clone(),
+        }
+    }
+}
+
+/// Protocols above the transport layer that are told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppProtocol {
+    Http,
+    Tls,
+    Dns,
+    Quic,
+}
+
+impl fmt::Display for AppProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppProtocol::Http => write!(f, "HTTP"),
+            AppProtocol::Tls => write!(f, "TLS"),
+            AppProtocol::Dns => write!(f, "DNS"),
+            AppProtocol::Quic => write!(f, "QUIC"),
         }
     }
 }
@@ -292,3 +514,47 @@ impl fmt::Display for NetworkPacket {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::host_names::HostNames;
+    use crate::data::test_packets;
+
+    fn ethernet(ethertype: [u8; 2]) -> Vec<u8> {
+        let mut frame = vec![0x02, 0, 0, 0, 0, 1, 0x02, 0, 0, 0, 0, 2];
+        frame.extend(ethertype);
+        frame
+    }
+
+    #[test]
+    fn truncated_frames_keep_their_network_layer() {
+        // an IPv6 header announcing 1500 bytes, of which 20 were captured
+        let mut frame = ethernet([0x86, 0xdd]);
+        frame.extend([0x60, 0, 0, 0, 0x05, 0xdc, 6, 64]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 12]));
+        frame.extend([0x20, 0x01, 0x0d, 0xb8].iter().chain(&[0; 11]).chain(&[1]));
+        frame.extend([0; 20]);
+        let packet =
+            ParsedPacket::parse(frame.clone(), Duration::ZERO, &test_packets::interface()).unwrap();
+        assert!(matches!(packet.net, NetworkPacket::Ipv6(_)));
+        assert!(matches!(packet.transport, TransportPacket::Tcp(_)));
+        // cut inside the TCP header
+        frame.truncate(frame.len() - 10);
+        let packet =
+            ParsedPacket::parse(frame, Duration::ZERO, &test_packets::interface()).unwrap();
+        assert!(matches!(packet.transport, TransportPacket::Other));
+
+        // an IPv4 header claiming 60 bytes of options in a frame that ends after 20
+        let mut frame = ethernet([0x08, 0x00]);
+        frame.extend([0x4f, 0, 0x05, 0xdc, 0, 0, 0x40, 0, 64, 17, 0, 0]);
+        frame.extend([10, 0, 0, 1, 10, 0, 0, 2]);
+        let packet =
+            ParsedPacket::parse(frame, Duration::ZERO, &test_packets::interface()).unwrap();
+        assert!(matches!(packet.net, NetworkPacket::Ipv4(_)));
+        assert!(matches!(packet.transport, TransportPacket::Other));
+        // and show without reading past the end
+        packet.info();
+        packet.details(&HostNames::default());
+    }
+}
@@ -0,0 +1,424 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// A frame read from a capture file, timestamped relative to the unix epoch.
+#[derive(Debug, Clone)]
+pub struct RecordedFrame {
+    pub timestamp: Duration,
+    pub data: Vec<u8>,
+    /// Packet comments of a pcapng file.
+    pub comments: Vec<String>,
+}
+
+const LINKTYPE_ETHERNET: u32 = 1;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b23c4d;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_COMMENT: u16 = 1;
+const PCAPNG_IF_TSRESOL: u16 = 9;
+
+/// Reads all Ethernet frames of a pcap or pcapng file.
+pub fn read_file(path: &Path) -> Result<Vec<RecordedFrame>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let magic = bytes
+        .get(..4)
+        .map(|m| u32::from_le_bytes([m[0], m[1], m[2], m[3]]))
+        .ok_or_else(|| format!("{} is not a capture file", path.display()))?;
+    if magic == PCAPNG_SECTION_HEADER {
+        read_pcapng(&bytes)
+    } else {
+        read_pcap(&bytes)
+    }
+    .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Little helper to read integers in the byte order of the file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let slice = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| String::from("unexpected end of file"))?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b: [u8; 2] = self.take(2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b: [u8; 4] = self.take(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+}
+
+fn read_pcap(bytes: &[u8]) -> Result<Vec<RecordedFrame>, String> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        big_endian: false,
+    };
+    let mut magic = reader.u32()?;
+    if magic != PCAP_MAGIC_MICROS && magic != PCAP_MAGIC_NANOS {
+        reader.big_endian = true;
+        reader.pos = 0;
+        magic = reader.u32()?;
+    }
+    let nanos = match magic {
+        PCAP_MAGIC_MICROS => false,
+        PCAP_MAGIC_NANOS => true,
+        _ => return Err(String::from("not a pcap or pcapng file")),
+    };
+    reader.take(16)?;
+    let linktype = reader.u32()? & 0x0fff_ffff;
+    if linktype != LINKTYPE_ETHERNET {
+        return Err(format!("unsupported link type {}", linktype));
+    }
+
+    let mut frames = vec![];
+    while !reader.at_end() {
+        let secs = reader.u32()? as u64;
+        let frac = reader.u32()?;
+        let captured = reader.u32()? as usize;
+        reader.u32()?;
+        let timestamp = if nanos {
+            Duration::new(secs, frac)
+        } else {
+            Duration::new(secs, 0) + Duration::from_micros(frac as u64)
+        };
+        frames.push(RecordedFrame {
+            timestamp,
+            data: reader.take(captured)?.to_vec(),
+            comments: vec![],
+        });
+    }
+    Ok(frames)
+}
+
+fn read_pcapng(bytes: &[u8]) -> Result<Vec<RecordedFrame>, String> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        big_endian: false,
+    };
+    // (link type, timestamp units per second) of each interface in the current section
+    let mut interfaces: Vec<(u16, u64)> = vec![];
+    let mut frames = vec![];
+
+    while !reader.at_end() {
+        let start = reader.pos;
+        let block_type = reader.u32()?;
+        if block_type == PCAPNG_SECTION_HEADER {
+            // the byte order magic follows the length, so peek at it first
+            let order = bytes
+                .get(start + 8..start + 12)
+                .ok_or_else(|| String::from("truncated section header"))?;
+            reader.big_endian = match u32::from_le_bytes(order.try_into().unwrap()) {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => return Err(String::from("invalid section header")),
+            };
+            interfaces.clear();
+        }
+        let total_len = reader.u32()? as usize;
+        if total_len < 12 {
+            return Err(String::from("invalid block length"));
+        }
+        let mut body = Reader {
+            bytes: reader.take(total_len - 12)?,
+            pos: 0,
+            big_endian: reader.big_endian,
+        };
+        reader.u32()?;
+
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                let linktype = body.u16()?;
+                body.take(6)?;
+                let mut resolution = 1_000_000;
+                for (code, value) in read_options(&mut body)? {
+                    if code == PCAPNG_IF_TSRESOL && !value.is_empty() {
+                        let exp = (value[0] & 0x7f) as u32;
+                        resolution = if value[0] & 0x80 == 0 {
+                            10u64.saturating_pow(exp)
+                        } else {
+                            2u64.saturating_pow(exp)
+                        };
+                    }
+                }
+                interfaces.push((linktype, resolution));
+            }
+            PCAPNG_ENHANCED_PACKET => {
+                let interface = body.u32()? as usize;
+                let ts = ((body.u32()? as u64) << 32) | body.u32()? as u64;
+                let captured = body.u32()? as usize;
+                body.u32()?;
+                let &(linktype, resolution) = interfaces
+                    .get(interface)
+                    .ok_or_else(|| String::from("packet references unknown interface"))?;
+                let data = body.take(captured)?.to_vec();
+                body.take((4 - captured % 4) % 4)?;
+                let comments = read_options(&mut body)?
+                    .into_iter()
+                    .filter(|(code, _)| *code == PCAPNG_OPTION_COMMENT)
+                    .map(|(_, value)| String::from_utf8_lossy(&value).into_owned())
+                    .collect();
+                if linktype as u32 == LINKTYPE_ETHERNET {
+                    // u128 as the fraction times 10^9 overflows for fine resolutions
+                    let nanos = (ts % resolution) as u128 * 1_000_000_000 / resolution as u128;
+                    let timestamp = Duration::new(ts / resolution, nanos as u32);
+                    frames.push(RecordedFrame {
+                        timestamp,
+                        data,
+                        comments,
+                    });
+                }
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                let original = body.u32()? as usize;
+                let data = body.take(original.min(body.bytes.len() - 4))?.to_vec();
+                if interfaces.first().map(|i| i.0 as u32) == Some(LINKTYPE_ETHERNET) {
+                    frames.push(RecordedFrame {
+                        timestamp: Duration::ZERO,
+                        data,
+                        comments: vec![],
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(frames)
+}
+
+/// Options trail most pcapng blocks, each padded to 32 bits.
+fn read_options(body: &mut Reader) -> Result<Vec<(u16, Vec<u8>)>, String> {
+    let mut options = vec![];
+    while !body.at_end() {
+        let code = body.u16()?;
+        let len = body.u16()? as usize;
+        if code == PCAPNG_OPTION_END {
+            break;
+        }
+        options.push((code, body.take(len)?.to_vec()));
+        body.take((4 - len % 4) % 4)?;
+    }
+    Ok(options)
+}
+
+/// Writes Ethernet frames into a pcapng file with a single interface.
+#[derive(Debug)]
+pub struct PcapngWriter<W: Write = BufWriter<File>> {
+    file: W,
+    bytes_written: u64,
+}
+
+impl PcapngWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> PcapngWriter<W> {
+    pub fn new(file: W) -> io::Result<Self> {
+        let mut writer = Self {
+            file,
+            bytes_written: 0,
+        };
+        let mut section = vec![];
+        section.extend(PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+        section.extend(1u16.to_le_bytes());
+        section.extend(0u16.to_le_bytes());
+        // unknown section length
+        section.extend((-1i64).to_le_bytes());
+        writer.write_block(PCAPNG_SECTION_HEADER, &section)?;
+
+        let mut interface = vec![];
+        interface.extend((LINKTYPE_ETHERNET as u16).to_le_bytes());
+        interface.extend(0u16.to_le_bytes());
+        interface.extend(0u32.to_le_bytes());
+        interface.extend(option(PCAPNG_IF_TSRESOL, &[9]));
+        interface.extend(option(PCAPNG_OPTION_END, &[]));
+        writer.write_block(PCAPNG_INTERFACE_DESCRIPTION, &interface)?;
+        Ok(writer)
+    }
+
+    /// Writes one frame, `comments` become its packet comments.
+    pub fn write_packet(
+        &mut self,
+        timestamp: Duration,
+        data: &[u8],
+        comments: &[&str],
+    ) -> io::Result<()> {
+        let ts = timestamp.as_nanos() as u64;
+        let mut body = vec![];
+        body.extend(0u32.to_le_bytes());
+        body.extend(((ts >> 32) as u32).to_le_bytes());
+        body.extend((ts as u32).to_le_bytes());
+        body.extend((data.len() as u32).to_le_bytes());
+        body.extend((data.len() as u32).to_le_bytes());
+        body.extend(data);
+        body.resize(body.len().next_multiple_of(4), 0);
+        if !comments.is_empty() {
+            for comment in comments {
+                let bytes = comment.as_bytes();
+                let len = bytes.len().min(u16::MAX as usize);
+                body.extend(option(PCAPNG_OPTION_COMMENT, &bytes[..len]));
+            }
+            body.extend(option(PCAPNG_OPTION_END, &[]));
+        }
+        self.write_block(PCAPNG_ENHANCED_PACKET, &body)
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> io::Result<()> {
+        let total_len = (body.len() + 12) as u32;
+        self.file.write_all(&block_type.to_le_bytes())?;
+        self.file.write_all(&total_len.to_le_bytes())?;
+        self.file.write_all(body)?;
+        self.file.write_all(&total_len.to_le_bytes())?;
+        self.bytes_written += total_len as u64;
+        Ok(())
+    }
+}
+
+fn option(code: u16, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes.extend(code.to_le_bytes());
+    bytes.extend((value.len() as u16).to_le_bytes());
+    bytes.extend(value);
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let total_len = (body.len() + 12) as u32;
+        let mut bytes = block_type.to_le_bytes().to_vec();
+        bytes.extend(total_len.to_le_bytes());
+        bytes.extend(body);
+        bytes.extend(total_len.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn pcapng_round_trip() {
+        let mut file = vec![];
+        let mut writer = PcapngWriter::new(&mut file).unwrap();
+        let timestamp = Duration::new(1_700_000_000, 123_456_789);
+        writer
+            .write_packet(timestamp, &[1, 2, 3, 4, 5], &["first", "second"])
+            .unwrap();
+        writer.write_packet(timestamp, &[6; 8], &[]).unwrap();
+        writer.flush().unwrap();
+
+        let frames = read_pcapng(&file).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].timestamp, timestamp);
+        assert_eq!(frames[0].data, [1, 2, 3, 4, 5]);
+        assert_eq!(frames[0].comments, ["first", "second"]);
+        assert_eq!(frames[1].data, [6; 8]);
+        assert!(frames[1].comments.is_empty());
+    }
+
+    #[test]
+    fn pcap_byte_orders_and_resolutions() {
+        let mut little = vec![];
+        little.extend(PCAP_MAGIC_MICROS.to_le_bytes());
+        little.extend([0; 16]);
+        little.extend(LINKTYPE_ETHERNET.to_le_bytes());
+        for value in [10u32, 250_000, 3, 3] {
+            little.extend(value.to_le_bytes());
+        }
+        little.extend([7, 8, 9]);
+        let frames = read_pcap(&little).unwrap();
+        assert_eq!(frames[0].timestamp, Duration::new(10, 250_000_000));
+        assert_eq!(frames[0].data, [7, 8, 9]);
+
+        let mut big = vec![];
+        big.extend(PCAP_MAGIC_NANOS.to_be_bytes());
+        big.extend([0; 16]);
+        big.extend(LINKTYPE_ETHERNET.to_be_bytes());
+        for value in [10u32, 42, 1, 1] {
+            big.extend(value.to_be_bytes());
+        }
+        big.push(0xff);
+        let frames = read_pcap(&big).unwrap();
+        assert_eq!(frames[0].timestamp, Duration::new(10, 42));
+    }
+
+    #[test]
+    fn binary_timestamp_resolution() {
+        let mut section = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        section.extend([1, 0, 0, 0]);
+        section.extend((-1i64).to_le_bytes());
+        let mut interface = (LINKTYPE_ETHERNET as u16).to_le_bytes().to_vec();
+        interface.extend([0; 6]);
+        // 2^40 units per second, far more than fit a u64 once multiplied by 10^9
+        interface.extend(option(PCAPNG_IF_TSRESOL, &[0x80 | 40]));
+        interface.extend(option(PCAPNG_OPTION_END, &[]));
+        let ts = (5u64 << 40) | (1 << 39);
+        let mut packet = 0u32.to_le_bytes().to_vec();
+        packet.extend(((ts >> 32) as u32).to_le_bytes());
+        packet.extend((ts as u32).to_le_bytes());
+        packet.extend(4u32.to_le_bytes());
+        packet.extend(4u32.to_le_bytes());
+        packet.extend([1, 2, 3, 4]);
+
+        let mut file = block(PCAPNG_SECTION_HEADER, &section);
+        file.extend(block(PCAPNG_INTERFACE_DESCRIPTION, &interface));
+        file.extend(block(PCAPNG_ENHANCED_PACKET, &packet));
+        let frames = read_pcapng(&file).unwrap();
+        assert_eq!(frames[0].timestamp, Duration::new(5, 500_000_000));
+    }
+
+    #[test]
+    fn truncated_files() {
+        let mut file = vec![];
+        let mut writer = PcapngWriter::new(&mut file).unwrap();
+        writer.write_packet(Duration::ZERO, &[1; 20], &[]).unwrap();
+        writer.flush().unwrap();
+        assert!(read_pcapng(&file[..file.len() - 6]).is_err());
+        assert!(read_pcap(&[0xd4, 0xc3, 0xb2]).is_err());
+        assert!(read_pcap(&[0; 24]).is_err());
+    }
+}
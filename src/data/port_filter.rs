@@ -0,0 +1,43 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::parsed_packet::ParsedPacket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PortMatch {
+    Source,
+    Destination,
+    #[default]
+    Either,
+}
+
+impl PortMatch {
+    pub const ALL: [PortMatch; 3] = [PortMatch::Either, PortMatch::Source, PortMatch::Destination];
+
+    /// An empty port list matches everything, including packets without ports.
+    pub fn matches(&self, packet: &ParsedPacket, ports: &[u16]) -> bool {
+        if ports.is_empty() {
+            return true;
+        }
+        let source = packet.get_source_port().is_some_and(|p| ports.contains(&p));
+        let destination = packet
+            .get_destination_port()
+            .is_some_and(|p| ports.contains(&p));
+        match self {
+            PortMatch::Source => source,
+            PortMatch::Destination => destination,
+            PortMatch::Either => source || destination,
+        }
+    }
+}
+
+impl fmt::Display for PortMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortMatch::Source => write!(f, "Source"),
+            PortMatch::Destination => write!(f, "Destination"),
+            PortMatch::Either => write!(f, "Either side"),
+        }
+    }
+}
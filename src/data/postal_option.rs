@@ -1,9 +1,15 @@
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum PostalOption {
     Autoscroll,
     HttpOnly,
+    CaptureToDisk,
+    ResolveNames,
+    ReverseLookups,
 }
 
 impl PostalOption {
@@ -25,15 +31,42 @@ impl PostalOption {
                 Takes effect when a new capture is started.",
             ),
         );
+        map.insert(
+            PostalOption::CaptureToDisk,
+            (
+                false,
+                "Writes every captured packet to rotating pcapng files, only the most recent\n
+                packets are kept in memory. Takes effect when a new capture is started.",
+            ),
+        );
+        map.insert(
+            PostalOption::ResolveNames,
+            (
+                false,
+                "Shows host names instead of IP addresses in the packet list. Names come from\n
+                the hosts file and the DNS answers in the capture.",
+            ),
+        );
+        map.insert(
+            PostalOption::ReverseLookups,
+            (
+                false,
+                "Looks up the names of the remaining addresses through the system resolver.\n
+                This sends DNS queries of its own.",
+            ),
+        );
         map
     }
 }
 
-impl ToString for PostalOption {
-    fn to_string(&self) -> String {
+impl fmt::Display for PostalOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            PostalOption::Autoscroll => "Autoscroll".to_string(),
-            PostalOption::HttpOnly => "HTTP(S) only".to_string(),
+            PostalOption::Autoscroll => write!(f, "Autoscroll"),
+            PostalOption::HttpOnly => write!(f, "HTTP(S) only"),
+            PostalOption::CaptureToDisk => write!(f, "Capture to disk"),
+            PostalOption::ResolveNames => write!(f, "Resolve names"),
+            PostalOption::ReverseLookups => write!(f, "Reverse DNS lookups"),
         }
     }
 }
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::direction::Direction;
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+
+/// Local process owning the socket a packet was sent from or delivered to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    /// The socket of the process the packet was matched with.
+    pub local: SocketAddr,
+}
+
+impl fmt::Display for Process {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.pid)
+    }
+}
+
+impl Process {
+    /// Orders by name first, so the connections of one program end up next to each other.
+    pub fn sort_key(&self) -> (&str, u32) {
+        (&self.name, self.pid)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Owner of a socket, with the time it was last seen in `/proc`.
+#[derive(Debug, Clone)]
+struct Owner {
+    pid: u32,
+    name: String,
+    seen: Instant,
+}
+
+/// Sockets with their owners as of the last read of `/proc`.
+#[derive(Debug, Default)]
+struct Sockets {
+    /// Connected sockets by local and remote address.
+    connected: HashMap<(Protocol, SocketAddr, SocketAddr), Owner>,
+    /// All sockets by local address, listening ones bound to the unspecified address included.
+    bound: HashMap<(Protocol, SocketAddr), Owner>,
+}
+
+impl Sockets {
+    /// Sockets of exited processes are remembered this long, for their last packets.
+    const KEEP: Duration = Duration::from_secs(60);
+
+    fn lookup(
+        &self,
+        protocol: Protocol,
+        candidates: &[(SocketAddr, SocketAddr)],
+    ) -> Option<(&Owner, SocketAddr)> {
+        candidates.iter().find_map(|&(local, remote)| {
+            let unspecified = |ip: IpAddr| SocketAddr::new(ip, local.port());
+            let owner = self
+                .connected
+                .get(&(protocol, local, remote))
+                .or_else(|| self.bound.get(&(protocol, local)))
+                .or_else(|| {
+                    let any = Ipv4Addr::UNSPECIFIED.into();
+                    self.bound.get(&(protocol, unspecified(any)))
+                })
+                .or_else(|| {
+                    let any = Ipv6Addr::UNSPECIFIED.into();
+                    self.bound.get(&(protocol, unspecified(any)))
+                })?;
+            Some((owner, local))
+        })
+    }
+
+    fn update(&mut self, found: Vec<(Protocol, SocketAddr, SocketAddr, Owner)>, now: Instant) {
+        for (protocol, local, remote, owner) in found {
+            if !remote.ip().is_unspecified() {
+                self.connected
+                    .insert((protocol, local, remote), owner.clone());
+            }
+            self.bound.insert((protocol, local), owner);
+        }
+        self.connected
+            .retain(|_, owner| now - owner.seen < Self::KEEP);
+        self.bound.retain(|_, owner| now - owner.seen < Self::KEEP);
+    }
+}
+
+/// Maps sockets to the processes owning them, read from `/proc` on Linux.
+/// Elsewhere packets are simply left without a process.
+///
+/// A thread of its own reads `/proc` while the table is alive, the capture only looks
+/// the packets up in the latest read and never waits for one.
+#[derive(Debug)]
+pub struct ProcessTable {
+    sockets: Arc<RwLock<Sockets>>,
+    /// Asks for an early read, for sockets opened since the last one.
+    rescan: SyncSender<()>,
+}
+
+impl ProcessTable {
+    /// Time after which the table is read again.
+    const REFRESH: Duration = Duration::from_secs(2);
+    /// Shortest time between two reads when a socket is not known yet.
+    const RETRY: Duration = Duration::from_millis(250);
+
+    /// Starts reading `/proc`, until the table is dropped.
+    pub fn start() -> Self {
+        let sockets = Arc::new(RwLock::new(Sockets::default()));
+        let (rescan, requests) = mpsc::sync_channel(1);
+        if cfg!(target_os = "linux") {
+            let shared = sockets.clone();
+            thread::spawn(move || Self::scan(&shared, &requests));
+        }
+        Self { sockets, rescan }
+    }
+
+    fn scan(sockets: &RwLock<Sockets>, requests: &Receiver<()>) {
+        loop {
+            let now = Instant::now();
+            let owners = socket_owners();
+            let found = sockets_of_proc()
+                .into_iter()
+                .filter_map(|(protocol, local, remote, inode)| {
+                    let (pid, name) = owners.get(&inode)?;
+                    let owner = Owner {
+                        pid: *pid,
+                        name: name.clone(),
+                        seen: now,
+                    };
+                    Some((protocol, local, remote, owner))
+                })
+                .collect();
+            if let Ok(mut sockets) = sockets.write() {
+                sockets.update(found, now);
+            }
+            match requests.recv_timeout(Self::REFRESH) {
+                Ok(()) => {
+                    thread::sleep(Self::RETRY.saturating_sub(now.elapsed()));
+                    // requests made while waiting are answered by the same read
+                    while requests.try_recv().is_ok() {}
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    /// Sets the process of the local endpoint of the packet, if one owns it.
+    pub fn resolve(&self, packet: &mut ParsedPacket) {
+        let protocol = match &packet.transport {
+            TransportPacket::Tcp(_) => Protocol::Tcp,
+            TransportPacket::Udp(_) => Protocol::Udp,
+            TransportPacket::Other => return,
+        };
+        let (Some(src), Some(dst), Some(src_port), Some(dst_port)) = (
+            packet.get_source_ip(),
+            packet.get_destination_ip(),
+            packet.get_source_port(),
+            packet.get_destination_port(),
+        ) else {
+            return;
+        };
+        let source = SocketAddr::new(src, src_port);
+        let destination = SocketAddr::new(dst, dst_port);
+        // (local, remote) pairs to try, the sender first where it is this host
+        let candidates = match packet.direction {
+            Direction::Outbound => vec![(source, destination)],
+            Direction::Local => vec![(source, destination), (destination, source)],
+            Direction::Inbound | Direction::Broadcast | Direction::Multicast => {
+                vec![(destination, source)]
+            }
+            Direction::Forwarded => return,
+        };
+
+        let Ok(sockets) = self.sockets.read() else {
+            return;
+        };
+        packet.process = match sockets.lookup(protocol, &candidates) {
+            Some((owner, local)) => Some(Process {
+                pid: owner.pid,
+                name: owner.name.clone(),
+                local,
+            }),
+            None => {
+                // a socket opened since the last read, a full channel already asked
+                let _ = self.rescan.try_send(());
+                None
+            }
+        };
+    }
+}
+
+/// Sockets of `/proc/net/{tcp,tcp6,udp,udp6}` as (protocol, local, remote, inode).
+#[cfg(target_os = "linux")]
+fn sockets_of_proc() -> Vec<(Protocol, SocketAddr, SocketAddr, u64)> {
+    let tables = [
+        ("/proc/net/tcp", Protocol::Tcp),
+        ("/proc/net/tcp6", Protocol::Tcp),
+        ("/proc/net/udp", Protocol::Udp),
+        ("/proc/net/udp6", Protocol::Udp),
+    ];
+    let mut sockets = vec![];
+    for (path, protocol) in tables {
+        let Ok(table) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        for line in table.lines().skip(1) {
+            let Some((local, remote, inode)) = parse_socket(line) else {
+                continue;
+            };
+            // inode 0 belongs to sockets in TIME_WAIT, no process holds them anymore
+            if inode != 0 {
+                sockets.push((protocol, local, remote, inode));
+            }
+        }
+    }
+    sockets
+}
+
+/// Local and remote address and inode of a line of a `/proc/net` table.
+#[cfg(target_os = "linux")]
+fn parse_socket(line: &str) -> Option<(SocketAddr, SocketAddr, u64)> {
+    let columns = line.split_whitespace().collect::<Vec<_>>();
+    Some((
+        parse_address(columns.get(1)?)?,
+        parse_address(columns.get(2)?)?,
+        columns.get(9)?.parse().ok()?,
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sockets_of_proc() -> Vec<(Protocol, SocketAddr, SocketAddr, u64)> {
+    vec![]
+}
+
+/// Parses an address of the `/proc/net` tables, e.g. `0100007F:0035` for 127.0.0.1:53.
+/// The address is hex in host byte order, 32 bit word by word for IPv6.
+#[cfg(target_os = "linux")]
+fn parse_address(address: &str) -> Option<SocketAddr> {
+    let (ip, port) = address.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let word = |i: usize| {
+        ip.get(i * 8..i * 8 + 8)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .map(u32::to_ne_bytes)
+    };
+    let ip = match ip.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(word(0)?)),
+        32 => {
+            let mut octets = [0; 16];
+            for i in 0..4 {
+                octets[i * 4..i * 4 + 4].copy_from_slice(&word(i)?);
+            }
+            let ip = Ipv6Addr::from(octets);
+            // dual stack sockets see IPv4 peers as mapped addresses
+            match ip.to_ipv4_mapped() {
+                Some(v4) => IpAddr::V4(v4),
+                None => IpAddr::V6(ip),
+            }
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Socket inodes with the PID and name of the process holding them, from `/proc/<pid>/fd`.
+/// Only processes readable by the current user show up.
+#[cfg(target_os = "linux")]
+fn socket_owners() -> HashMap<u64, (u32, String)> {
+    let mut owners = HashMap::new();
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+    for process in processes.flatten() {
+        let Some(pid) = process
+            .file_name()
+            .to_str()
+            .and_then(|p| p.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        let mut name = None;
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let Some(inode) = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let name = name.get_or_insert_with(|| {
+                std::fs::read_to_string(process.path().join("comm"))
+                    .map(|comm| comm.trim_end().to_string())
+                    .unwrap_or_default()
+            });
+            owners.insert(inode, (pid, name.clone()));
+        }
+    }
+    owners
+}
+
+#[cfg(not(target_os = "linux"))]
+fn socket_owners() -> HashMap<u64, (u32, String)> {
+    HashMap::new()
+}
+
+#[cfg(all(test, target_os = "linux", target_endian = "little"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_net_addresses() {
+        let cases = [
+            ("0100007F:0035", Some("127.0.0.1:53")),
+            ("00000000:1F90", Some("0.0.0.0:8080")),
+            ("0201A8C0:C350", Some("192.168.1.2:50000")),
+            ("00000000000000000000000001000000:0016", Some("[::1]:22")),
+            (
+                "B80D0120000000000000000001000000:01BB",
+                Some("[2001:db8::1]:443"),
+            ),
+            (
+                "000080FE00000000FF000002FE0300FE:0222",
+                Some("[fe80::200:ff:fe00:3fe]:546"),
+            ),
+            // dual stack sockets see IPv4 peers as mapped addresses
+            (
+                "0000000000000000FFFF00000100007F:0035",
+                Some("127.0.0.1:53"),
+            ),
+            ("0100007F", None),
+            ("0100007F:", None),
+            ("0100007F:10000", None),
+            ("100007F:0035", None),
+            ("0100007G:0035", None),
+            ("00000000000000000000000001000000:", None),
+            ("000000000000000000000000010000:0016", None),
+            ("0000000000000000000000000100000X:0016", None),
+            ("", None),
+        ];
+        for (address, expected) in cases {
+            let expected = expected.map(|a| a.parse::<SocketAddr>().unwrap());
+            assert_eq!(parse_address(address), expected, "{}", address);
+        }
+    }
+
+    #[test]
+    fn parses_proc_net_lines() {
+        let tcp = "   0: 0100007F:0035 00000000:0000 0A 00000000:00000000 00:00000000 \
+                   00000000   101        0 23071 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(
+            parse_socket(tcp),
+            Some((
+                "127.0.0.1:53".parse().unwrap(),
+                "0.0.0.0:0".parse().unwrap(),
+                23071
+            ))
+        );
+        let tcp6 = "   1: 00000000000000000000000001000000:0277 \
+                    B80D0120000000000000000001000000:A2C4 01 00000000:00000000 00:00000000 \
+                    00000000     0        0 0 1 0000000000000000 20 4 30 10 -1";
+        let (local, remote, inode) = parse_socket(tcp6).unwrap();
+        assert_eq!(local, "[::1]:631".parse().unwrap());
+        assert_eq!(remote, "[2001:db8::1]:41668".parse().unwrap());
+        assert_eq!(inode, 0);
+
+        let header = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when \
+                      retrnsmt   uid  timeout inode";
+        for line in [
+            header,
+            "",
+            "   0: 0100007F:0035",
+            "   0: 0100007F:0035 00000000:0000 0A 00000000:00000000 00:00000000 00000000",
+            "   0: 0100007F:0035 0000:0000 0A 00000000:00000000 00:00000000 00000000 101 0 1",
+            "   0: 0100007F:0035 00000000:0000 0A 00000000:00000000 00:00000000 00000000 101 0 x",
+        ] {
+            assert_eq!(parse_socket(line), None, "{}", line);
+        }
+    }
+}
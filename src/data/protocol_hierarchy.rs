@@ -0,0 +1,143 @@
+use std::cmp::Reverse;
+
+use pnet::packet::ethernet::EtherTypes;
+
+use super::parsed_packet::{AppProtocol, NetworkPacket, ParsedPacket, TransportPacket};
+
+/// One protocol in the hierarchy with the frames that carry it.
+#[derive(Debug, Clone)]
+pub struct ProtocolNode {
+    pub name: &'static str,
+    /// Display filter selecting the frames of this node.
+    pub filter: String,
+    pub packets: usize,
+    pub bytes: usize,
+    pub children: Vec<ProtocolNode>,
+}
+
+impl Default for ProtocolNode {
+    /// The root, standing for all frames.
+    fn default() -> Self {
+        Self::new("Frame", String::new())
+    }
+}
+
+impl ProtocolNode {
+    fn new(name: &'static str, filter: String) -> Self {
+        Self {
+            name,
+            filter,
+            packets: 0,
+            bytes: 0,
+            children: vec![],
+        }
+    }
+
+    /// Counts a frame in this node and the protocols it carries.
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        let bytes = packet.data.len();
+        let mut node = self;
+        node.packets += 1;
+        node.bytes += bytes;
+        for (name, filter) in layers(packet) {
+            node = node.child(name, filter);
+            node.packets += 1;
+            node.bytes += bytes;
+        }
+    }
+
+    /// Takes back a frame counted before, once it is trimmed from the store.
+    /// Protocols no frame carries anymore are dropped.
+    pub fn remove(&mut self, packet: &ParsedPacket) {
+        self.uncount(&layers(packet), packet.data.len());
+    }
+
+    fn uncount(&mut self, layers: &[(&str, &str)], bytes: usize) {
+        self.packets = self.packets.saturating_sub(1);
+        self.bytes = self.bytes.saturating_sub(bytes);
+        let Some(((name, _), rest)) = layers.split_first() else {
+            return;
+        };
+        if let Some(index) = self.children.iter().position(|child| child.name == *name) {
+            self.children[index].uncount(rest, bytes);
+            if self.children[index].packets == 0 {
+                self.children.remove(index);
+            }
+        }
+    }
+
+    /// The child named `name`, created on first sight.
+    fn child(&mut self, name: &'static str, filter: &str) -> &mut ProtocolNode {
+        let index = match self.children.iter().position(|child| child.name == name) {
+            Some(index) => index,
+            None => {
+                let filter = match self.filter.as_str() {
+                    "" | "eth" => filter.to_string(),
+                    parent => format!("{} && {}", parent, filter),
+                };
+                self.children.push(ProtocolNode::new(name, filter));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+
+    /// Depth first walk yielding each node below the root with its depth.
+    /// Children are ordered by the number of frames.
+    pub fn flatten(&self) -> Vec<(usize, &ProtocolNode)> {
+        fn push<'a>(
+            stack: &mut Vec<(usize, &'a ProtocolNode)>,
+            depth: usize,
+            node: &'a ProtocolNode,
+        ) {
+            let mut children = node.children.iter().collect::<Vec<_>>();
+            children.sort_by_key(|child| Reverse(child.packets));
+            stack.extend(children.into_iter().rev().map(|child| (depth, child)));
+        }
+        let mut nodes = vec![];
+        let mut stack = vec![];
+        push(&mut stack, 0, self);
+        while let Some((depth, node)) = stack.pop() {
+            nodes.push((depth, node));
+            push(&mut stack, depth + 1, node);
+        }
+        nodes
+    }
+}
+
+/// Protocols carried by the frame, outermost first, with their display filter fields.
+fn layers(packet: &ParsedPacket) -> Vec<(&'static str, &'static str)> {
+    let mut layers = vec![("Ethernet", "eth")];
+    match &packet.net {
+        NetworkPacket::Ipv4(_) => layers.push(("IPv4", "ipv4")),
+        NetworkPacket::Ipv6(_) => layers.push(("IPv6", "ipv6")),
+        NetworkPacket::Other if packet.eth.get_ethertype() == EtherTypes::Arp => {
+            layers.push(("ARP", "arp"));
+            return layers;
+        }
+        NetworkPacket::Other => return layers,
+    }
+
+    match &packet.transport {
+        TransportPacket::Tcp(_) => layers.push(("TCP", "tcp")),
+        TransportPacket::Udp(_) => layers.push(("UDP", "udp")),
+        TransportPacket::Other if packet.get_icmp(false).is_some() => {
+            layers.push(("ICMP", "icmp"));
+            return layers;
+        }
+        TransportPacket::Other if packet.get_icmp(true).is_some() => {
+            layers.push(("ICMPv6", "icmpv6"));
+            return layers;
+        }
+        TransportPacket::Other => return layers,
+    }
+
+    match packet.app_protocol() {
+        Some(AppProtocol::Http) => layers.push(("HTTP", "http")),
+        Some(AppProtocol::Tls) => layers.push(("TLS", "tls")),
+        Some(AppProtocol::Dns) => layers.push(("DNS", "dns")),
+        Some(AppProtocol::Quic) => layers.push(("QUIC", "quic")),
+        None => {}
+    }
+    layers
+}
@@ -0,0 +1,787 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use pnet::packet::Packet;
+
+use super::crypto::{self, Aes128};
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+
+pub const VERSION_1: u32 = 0x0000_0001;
+pub const VERSION_2: u32 = 0x6b33_43cf;
+const DRAFT_29: u32 = 0xff00_001d;
+
+/// Salts of the Initial secrets, RFC 9001 section 5.2 and RFC 9369 section 3.3.1.
+const SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+const SALT_V2: [u8; 20] = [
+    0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93, 0x81, 0xbe, 0x6e, 0x26, 0x9d, 0xcb,
+    0xf9, 0xbd, 0x2e, 0xd9,
+];
+const SALT_DRAFT_29: [u8; 20] = [
+    0xaf, 0xbf, 0xec, 0x28, 0x99, 0x93, 0xd2, 0x4c, 0x9e, 0x97, 0x86, 0xf1, 0x9c, 0x61, 0x11, 0xe0,
+    0x43, 0x90, 0xa8, 0x99,
+];
+
+/// UDP port of HTTP/3. On other ports only known versions and connection IDs count as QUIC.
+pub const PORT: u16 = 443;
+/// Longest connection ID of QUIC version 1 and 2.
+const MAX_CONNECTION_ID: usize = 20;
+
+/// Connection IDs announced in long headers. Short headers do not tell the length of
+/// their destination connection ID, it can only be recognized as one seen before.
+#[derive(Debug, Default)]
+struct ConnectionIds {
+    ids: HashSet<Vec<u8>>,
+    /// The IDs in the order they were seen, the oldest are forgotten first.
+    order: VecDeque<Vec<u8>>,
+    /// Lengths present, longest first.
+    lengths: Vec<usize>,
+}
+
+impl ConnectionIds {
+    /// Connection IDs remembered at most.
+    const MAX: usize = 1 << 16;
+
+    fn insert(&mut self, id: &[u8]) {
+        if id.is_empty() || !self.ids.insert(id.to_vec()) {
+            return;
+        }
+        self.order.push_back(id.to_vec());
+        if self.order.len() > Self::MAX {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        if !self.lengths.contains(&id.len()) {
+            self.lengths.push(id.len());
+            self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        }
+    }
+
+    /// The known connection ID the bytes start with.
+    fn prefix_of(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        self.lengths
+            .iter()
+            .filter_map(|&length| bytes.get(..length))
+            .find(|id| self.ids.contains(*id))
+            .map(<[u8]>::to_vec)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    VersionNegotiation,
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Retry,
+    /// Any packet with a short header.
+    OneRtt,
+}
+
+impl fmt::Display for PacketType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PacketType::VersionNegotiation => "Version Negotiation",
+            PacketType::Initial => "Initial",
+            PacketType::ZeroRtt => "0-RTT",
+            PacketType::Handshake => "Handshake",
+            PacketType::Retry => "Retry",
+            PacketType::OneRtt => "1-RTT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One QUIC packet. A datagram can carry several, long header packets back to back,
+/// optionally followed by one with a short header.
+#[derive(Debug, Clone)]
+pub struct QuicPacket {
+    pub packet_type: PacketType,
+    /// Only long headers carry a version.
+    pub version: Option<u32>,
+    /// Destination connection ID, unknown for short headers of connections not seen starting.
+    pub dcid: Option<Vec<u8>>,
+    /// Source connection ID, long headers only.
+    pub scid: Option<Vec<u8>>,
+    pub token_length: Option<usize>,
+    /// The packet as it is on the wire, header included.
+    pub data: Vec<u8>,
+    /// Where the packet number starts, for the packet types that have one.
+    pn_offset: Option<usize>,
+    /// Payload of Initial packets, decrypted when the packet is parsed.
+    pub initial: Option<Result<InitialPayload, String>>,
+}
+
+/// What the QUIC tracker found in a UDP datagram.
+#[derive(Debug, Clone, Default)]
+pub struct QuicAnalysis {
+    /// QUIC packets carried by the datagram, empty for anything but QUIC.
+    pub packets: Vec<QuicPacket>,
+    /// The ClientHello in the client's Initial packets of the datagram, if they open it.
+    pub client_hello: Option<ClientHello>,
+}
+
+/// Frames of a decrypted Initial packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    /// A run of padding bytes.
+    Padding(usize),
+    Ping,
+    Ack {
+        largest: u64,
+    },
+    Crypto {
+        offset: u64,
+        data: Vec<u8>,
+    },
+    ConnectionClose {
+        code: u64,
+        reason: String,
+    },
+    /// A frame that does not belong into Initial packets, the rest of the payload is skipped.
+    Other(u64),
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frame::Padding(length) => write!(f, "PADDING ({} bytes)", length),
+            Frame::Ping => write!(f, "PING"),
+            Frame::Ack { largest } => write!(f, "ACK (largest {})", largest),
+            Frame::Crypto { offset, data } => {
+                write!(f, "CRYPTO (offset {}, {} bytes)", offset, data.len())
+            }
+            Frame::ConnectionClose { code, reason } if reason.is_empty() => {
+                write!(f, "CONNECTION_CLOSE (error 0x{:x})", code)
+            }
+            Frame::ConnectionClose { code, reason } => {
+                write!(f, "CONNECTION_CLOSE (error 0x{:x}: {})", code, reason)
+            }
+            Frame::Other(kind) => write!(f, "Frame type 0x{:x}", kind),
+        }
+    }
+}
+
+/// Content of a decrypted Initial packet.
+#[derive(Debug, Clone)]
+pub struct InitialPayload {
+    /// As sent, truncated to its 1 to 4 bytes.
+    pub packet_number: u32,
+    pub frames: Vec<Frame>,
+}
+
+/// What a client announces in its TLS ClientHello.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientHello {
+    pub server_name: Option<String>,
+    pub alpn: Vec<String>,
+    /// Whether all of the message was there, a large one spans several Initial packets.
+    pub complete: bool,
+}
+
+/// Packet protection keys of one side.
+struct Keys {
+    key: [u8; 16],
+    iv: [u8; 12],
+    hp: [u8; 16],
+}
+
+impl Keys {
+    /// Keys of the client's Initial packets, derived from the destination connection ID
+    /// of its first Initial, RFC 9001 section 5.2.
+    fn client_initial(version: u32, dcid: &[u8]) -> Option<Self> {
+        let (salt, prefix) = match version {
+            VERSION_1 => (SALT_V1, "quic"),
+            VERSION_2 => (SALT_V2, "quicv2"),
+            DRAFT_29 => (SALT_DRAFT_29, "quic"),
+            _ => return None,
+        };
+        let initial = crypto::hkdf_extract(&salt, dcid);
+        let secret = crypto::hkdf_expand_label(&initial, "client in", 32);
+        let expand = |label: &str, length| {
+            crypto::hkdf_expand_label(&secret, &format!("{} {}", prefix, label), length)
+        };
+        Some(Self {
+            key: expand("key", 16).try_into().ok()?,
+            iv: expand("iv", 12).try_into().ok()?,
+            hp: expand("hp", 16).try_into().ok()?,
+        })
+    }
+}
+
+/// Reads the fields of QUIC headers, frames and TLS messages.
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], at: usize) -> Self {
+        Self { data, at }
+    }
+
+    fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.at..self.at.checked_add(length)?)?;
+        self.at += length;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    /// Bytes preceded by their length in one byte.
+    fn prefixed(&mut self) -> Option<&'a [u8]> {
+        let length = self.u8()? as usize;
+        self.bytes(length)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.bytes(2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.bytes(4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A variable-length integer, its two high bits tell its length.
+    fn varint(&mut self) -> Option<u64> {
+        let first = *self.data.get(self.at)?;
+        let bytes = self.bytes(1 << (first >> 6))?;
+        let value = bytes[1..]
+            .iter()
+            .fold((first & 0x3f) as u64, |n, &b| (n << 8) | b as u64);
+        Some(value)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.at >= self.data.len()
+    }
+}
+
+impl QuicPacket {
+    fn long_header(datagram: &[u8], start: usize) -> Option<Self> {
+        let mut reader = Reader::new(datagram, start);
+        let first = reader.u8()?;
+        let version = reader.u32()?;
+        let dcid = reader.prefixed()?.to_vec();
+        let scid = reader.prefixed()?.to_vec();
+        if version != 0 && dcid.len().max(scid.len()) > MAX_CONNECTION_ID {
+            return None;
+        }
+        let packet_type = if version == 0 {
+            PacketType::VersionNegotiation
+        } else {
+            // version 2 numbers the types one up, middleboxes must not ossify on them
+            let bits = (first >> 4) & 0b11;
+            let bits = if version == VERSION_2 {
+                bits.wrapping_sub(1) & 0b11
+            } else {
+                bits
+            };
+            match bits {
+                0 => PacketType::Initial,
+                1 => PacketType::ZeroRtt,
+                2 => PacketType::Handshake,
+                _ => PacketType::Retry,
+            }
+        };
+
+        let (token_length, end, pn_offset) = match packet_type {
+            PacketType::VersionNegotiation => {
+                // nothing but a list of versions follows
+                let versions = datagram.len() - reader.at;
+                if versions == 0 || !versions.is_multiple_of(4) {
+                    return None;
+                }
+                (None, datagram.len(), None)
+            }
+            PacketType::Retry => (None, datagram.len(), None),
+            _ => {
+                let token_length = match packet_type {
+                    PacketType::Initial => {
+                        let length = reader.varint()? as usize;
+                        reader.bytes(length)?;
+                        Some(length)
+                    }
+                    _ => None,
+                };
+                let length = reader.varint()? as usize;
+                let end = reader.at.checked_add(length)?;
+                if end > datagram.len() {
+                    return None;
+                }
+                (token_length, end, Some(reader.at - start))
+            }
+        };
+        Some(Self {
+            packet_type,
+            version: Some(version),
+            dcid: Some(dcid),
+            scid: Some(scid),
+            token_length,
+            data: datagram[start..end].to_vec(),
+            pn_offset,
+            initial: None,
+        })
+    }
+
+    fn short_header(datagram: &[u8], start: usize, dcid: Option<Vec<u8>>) -> Self {
+        Self {
+            packet_type: PacketType::OneRtt,
+            version: None,
+            dcid,
+            scid: None,
+            token_length: None,
+            data: datagram[start..].to_vec(),
+            pn_offset: None,
+            initial: None,
+        }
+    }
+
+    /// Versions a server offers in a version negotiation packet.
+    pub fn supported_versions(&self) -> Vec<u32> {
+        if self.packet_type != PacketType::VersionNegotiation {
+            return vec![];
+        }
+        let ids = self
+            .dcid
+            .iter()
+            .chain(&self.scid)
+            .map(Vec::len)
+            .sum::<usize>();
+        self.data[7 + ids..]
+            .chunks_exact(4)
+            .map(|v| u32::from_be_bytes([v[0], v[1], v[2], v[3]]))
+            .collect()
+    }
+
+    pub fn is_long_header(&self) -> bool {
+        self.packet_type != PacketType::OneRtt
+    }
+
+    /// Removes the packet protection of a client's Initial packet, whose keys derive from
+    /// its destination connection ID. Initial packets of servers are protected with
+    /// the connection ID the client chose, which their header does not carry.
+    fn decrypt(&self) -> Result<InitialPayload, String> {
+        let (PacketType::Initial, Some(version), Some(dcid), Some(pn_offset)) =
+            (self.packet_type, self.version, &self.dcid, self.pn_offset)
+        else {
+            return Err(String::from("Only Initial packets can be decrypted"));
+        };
+        let keys = Keys::client_initial(version, dcid).ok_or(format!(
+            "No Initial keys for version {}",
+            version_name(version)
+        ))?;
+
+        // header protection masks the packet number length and the packet number,
+        // the sample starts 4 bytes into the packet number field whatever its length
+        let sample = self
+            .data
+            .get(pn_offset + 4..pn_offset + 20)
+            .ok_or("The packet is too short")?;
+        let mask = Aes128::new(&keys.hp).encrypt(sample.try_into().unwrap());
+        let first = self.data[0] ^ (mask[0] & 0x0f);
+        let pn_length = (first & 0b11) as usize + 1;
+        let mut header = self.data[..pn_offset + pn_length].to_vec();
+        header[0] = first;
+        for (byte, mask) in header[pn_offset..].iter_mut().zip(&mask[1..]) {
+            *byte ^= mask;
+        }
+        let packet_number = header[pn_offset..]
+            .iter()
+            .fold(0u32, |n, &b| (n << 8) | b as u32);
+
+        let mut nonce = keys.iv;
+        for (byte, pn) in nonce[8..].iter_mut().zip(packet_number.to_be_bytes()) {
+            *byte ^= pn;
+        }
+        let sealed = &self.data[pn_offset + pn_length..];
+        let plaintext = crypto::aes128_gcm_open(&keys.key, &nonce, &header, sealed)
+            .ok_or("Protected with keys of the client's first DCID, which the packet lacks")?;
+        Ok(InitialPayload {
+            packet_number,
+            frames: frames(&plaintext),
+        })
+    }
+}
+
+impl fmt::Display for QuicPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.packet_type)?;
+        if let Some(version) = self.version.filter(|&v| v != VERSION_1 && v != 0) {
+            write!(f, " ({})", version_name(version))?;
+        }
+        if let Some(dcid) = &self.dcid {
+            write!(f, ", DCID={}", hex(dcid))?;
+        }
+        if let Some(scid) = &self.scid {
+            write!(f, ", SCID={}", hex(scid))?;
+        }
+        Ok(())
+    }
+}
+
+fn frames(payload: &[u8]) -> Vec<Frame> {
+    let mut reader = Reader::new(payload, 0);
+    let mut frames = vec![];
+    while let Some(frame) = frame(&mut reader) {
+        let last = matches!(frame, Frame::Other(_));
+        frames.push(frame);
+        if last || reader.is_empty() {
+            break;
+        }
+    }
+    frames
+}
+
+fn frame(reader: &mut Reader) -> Option<Frame> {
+    let start = reader.at;
+    let kind = reader.varint()?;
+    let frame = match kind {
+        0x00 => {
+            while reader.data.get(reader.at) == Some(&0) {
+                reader.at += 1;
+            }
+            Frame::Padding(reader.at - start)
+        }
+        0x01 => Frame::Ping,
+        0x02 | 0x03 => {
+            let largest = reader.varint()?;
+            // delay, range count and the first range, then gap and length of each range
+            let _delay = reader.varint()?;
+            let ranges = reader.varint()?;
+            let _first_range = reader.varint()?;
+            for _ in 0..ranges * 2 {
+                reader.varint()?;
+            }
+            if kind == 0x03 {
+                // the ECN counts
+                for _ in 0..3 {
+                    reader.varint()?;
+                }
+            }
+            Frame::Ack { largest }
+        }
+        0x06 => {
+            let offset = reader.varint()?;
+            let length = reader.varint()? as usize;
+            let data = reader.bytes(length)?.to_vec();
+            Frame::Crypto { offset, data }
+        }
+        0x1c | 0x1d => {
+            let code = reader.varint()?;
+            if kind == 0x1c {
+                let _frame_type = reader.varint()?;
+            }
+            let length = reader.varint()? as usize;
+            let reason = String::from_utf8_lossy(reader.bytes(length)?).into_owned();
+            Frame::ConnectionClose { code, reason }
+        }
+        other => Frame::Other(other),
+    };
+    Some(frame)
+}
+
+/// The data of CRYPTO frames from offset 0 on, as far as it is contiguous.
+pub fn crypto_stream<'a>(frames: impl IntoIterator<Item = &'a Frame>) -> Vec<u8> {
+    let mut chunks = frames
+        .into_iter()
+        .filter_map(|frame| match frame {
+            Frame::Crypto { offset, data } => Some((*offset as usize, data)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    chunks.sort_by_key(|(offset, _)| *offset);
+    let mut stream = vec![];
+    for (offset, data) in chunks {
+        if offset > stream.len() {
+            break;
+        }
+        // retransmissions overlap what is already there
+        if let Some(new) = data.get(stream.len() - offset..) {
+            stream.extend_from_slice(new);
+        }
+    }
+    stream
+}
+
+/// Reads the TLS ClientHello a CRYPTO stream starts with. A ClientHello spread over
+/// several packets may be cut off, the extensions up to the cut are still read.
+pub fn client_hello(stream: &[u8]) -> Option<ClientHello> {
+    const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+    const SERVER_NAME: u16 = 0;
+    const ALPN: u16 = 16;
+    let mut reader = Reader::new(stream, 0);
+    if reader.u8()? != HANDSHAKE_CLIENT_HELLO {
+        return None;
+    }
+    let length = reader
+        .bytes(3)?
+        .iter()
+        .fold(0, |n, &b| (n << 8) | b as usize);
+    // legacy version and random
+    reader.bytes(2 + 32)?;
+    let _session_id = reader.prefixed()?;
+    let cipher_suites = reader.u16()? as usize;
+    reader.bytes(cipher_suites)?;
+    let _compression = reader.prefixed()?;
+
+    let mut hello = ClientHello {
+        complete: stream.len() >= 4 + length,
+        ..Default::default()
+    };
+    let _extensions_length = reader.u16();
+    while let (Some(kind), Some(length)) = (reader.u16(), reader.u16()) {
+        let Some(data) = reader.bytes(length as usize) else {
+            break;
+        };
+        let mut extension = Reader::new(data, 2);
+        match kind {
+            SERVER_NAME => {
+                while let (Some(name_type), Some(length)) = (extension.u8(), extension.u16()) {
+                    let Some(name) = extension.bytes(length as usize) else {
+                        break;
+                    };
+                    if name_type == 0 {
+                        hello.server_name = Some(String::from_utf8_lossy(name).into_owned());
+                    }
+                }
+            }
+            ALPN => {
+                while let Some(protocol) = extension.prefixed() {
+                    hello
+                        .alpn
+                        .push(String::from_utf8_lossy(protocol).into_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(hello)
+}
+
+/// Versions that make a long header count as QUIC on any port.
+fn is_known_version(version: u32) -> bool {
+    matches!(version, 0 | VERSION_1 | VERSION_2)
+        || version >> 8 == 0xff_0000
+        || version.to_be_bytes()[0] == b'Q'
+}
+
+/// The QUIC packets of a UDP payload, `on_port` if one of the ports is the HTTP/3 one.
+fn parse(datagram: &[u8], on_port: bool, connection_ids: &ConnectionIds) -> Vec<QuicPacket> {
+    let mut packets: Vec<QuicPacket> = vec![];
+    let mut at = 0;
+    while let Some(&first) = datagram.get(at) {
+        if first & 0x80 != 0 {
+            let Some(mut packet) = QuicPacket::long_header(datagram, at) else {
+                break;
+            };
+            if at == 0 && !on_port && !packet.version.is_some_and(is_known_version) {
+                break;
+            }
+            if packet.packet_type == PacketType::Initial {
+                packet.initial = Some(packet.decrypt());
+            }
+            at += packet.data.len();
+            packets.push(packet);
+            continue;
+        }
+        // anything but a short header after the long ones is padding of the datagram
+        if first & 0x40 == 0 {
+            break;
+        }
+        let rest = &datagram[at + 1..];
+        let known = connection_ids.prefix_of(rest);
+        // a short header coalesced with long ones belongs to the same connection
+        let previous = packets
+            .last()
+            .and_then(|p| p.dcid.clone())
+            .filter(|dcid| !dcid.is_empty() && rest.starts_with(dcid));
+        let dcid = known.or(previous);
+        if dcid.is_some() || on_port {
+            packets.push(QuicPacket::short_header(datagram, at, dcid));
+        }
+        break;
+    }
+    packets
+}
+
+/// Finds the QUIC packets of a capture. It remembers the connection IDs of long header
+/// packets, which lets short header packets be told apart by theirs.
+#[derive(Debug, Default)]
+pub struct QuicTracker {
+    connection_ids: ConnectionIds,
+}
+
+impl QuicTracker {
+    pub fn clear(&mut self) {
+        self.connection_ids = ConnectionIds::default();
+    }
+
+    pub fn analyze(&mut self, packet: &mut ParsedPacket) {
+        let TransportPacket::Udp(udp) = &packet.transport else {
+            return;
+        };
+        let on_port = udp.get_source() == PORT || udp.get_destination() == PORT;
+        let packets = parse(udp.payload(), on_port, &self.connection_ids);
+        for id in packets
+            .iter()
+            .filter(|p| p.is_long_header())
+            .flat_map(|p| p.dcid.iter().chain(&p.scid))
+        {
+            self.connection_ids.insert(id);
+        }
+        let frames = packets
+            .iter()
+            .filter_map(|packet| packet.initial.as_ref()?.as_ref().ok())
+            .flat_map(|payload| &payload.frames);
+        packet.quic = QuicAnalysis {
+            client_hello: client_hello(&crypto_stream(frames)),
+            packets,
+        };
+    }
+}
+
+/// E.g. `1`, `2`, `draft-29` or `Q050`.
+pub fn version_name(version: u32) -> String {
+    match version {
+        VERSION_1 => String::from("1"),
+        VERSION_2 => String::from("2"),
+        v if v >> 8 == 0xff_0000 => format!("draft-{}", v & 0xff),
+        v if v.to_be_bytes()[0] == b'Q' => String::from_utf8_lossy(&v.to_be_bytes()).into_owned(),
+        v => format!("0x{:08x}", v),
+    }
+}
+
+/// Connection IDs are shown and filtered by as plain lowercase hex.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_packets;
+
+    fn bytes(text: &str) -> Vec<u8> {
+        let digits = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).unwrap())
+            .collect()
+    }
+
+    /// The client Initial of RFC 9001 appendix A.2, padded to 1200 bytes.
+    const CLIENT_INITIAL: &str = "
+        c000000001088394c8f03e5157080000449e7b9aec34d1b1c98dd7689fb8ec11
+        d242b123dc9bd8bab936b47d92ec356c0bab7df5976d27cd449f63300099f399
+        1c260ec4c60d17b31f8429157bb35a1282a643a8d2262cad67500cadb8e7378c
+        8eb7539ec4d4905fed1bee1fc8aafba17c750e2c7ace01e6005f80fcb7df6212
+        30c83711b39343fa028cea7f7fb5ff89eac2308249a02252155e2347b63d58c5
+        457afd84d05dfffdb20392844ae812154682e9cf012f9021a6f0be17ddd0c208
+        4dce25ff9b06cde535d0f920a2db1bf362c23e596d11a4f5a6cf3948838a3aec
+        4e15daf8500a6ef69ec4e3feb6b1d98e610ac8b7ec3faf6ad760b7bad1db4ba3
+        485e8a94dc250ae3fdb41ed15fb6a8e5eba0fc3dd60bc8e30c5c4287e53805db
+        059ae0648db2f64264ed5e39be2e20d82df566da8dd5998ccabdae053060ae6c
+        7b4378e846d29f37ed7b4ea9ec5d82e7961b7f25a9323851f681d582363aa5f8
+        9937f5a67258bf63ad6f1a0b1d96dbd4faddfcefc5266ba6611722395c906556
+        be52afe3f565636ad1b17d508b73d8743eeb524be22b3dcbc2c7468d54119c74
+        68449a13d8e3b95811a198f3491de3e7fe942b330407abf82a4ed7c1b311663a
+        c69890f4157015853d91e923037c227a33cdd5ec281ca3f79c44546b9d90ca00
+        f064c99e3dd97911d39fe9c5d0b23a229a234cb36186c4819e8b9c5927726632
+        291d6a418211cc2962e20fe47feb3edf330f2c603a9d48c0fcb5699dbfe58964
+        25c5bac4aee82e57a85aaf4e2513e4f05796b07ba2ee47d80506f8d2c25e50fd
+        14de71e6c418559302f939b0e1abd576f279c4b2e0feb85c1f28ff18f58891ff
+        ef132eef2fa09346aee33c28eb130ff28f5b766953334113211996d20011a198
+        e3fc433f9f2541010ae17c1bf202580f6047472fb36857fe843b19f5984009dd
+        c324044e847a4f4a0ab34f719595de37252d6235365e9b84392b061085349d73
+        203a4a13e96f5432ec0fd4a1ee65accdd5e3904df54c1da510b0ff20dcc0c77f
+        cb2c0e0eb605cb0504db87632cf3d8b4dae6e705769d1de354270123cb11450e
+        fc60ac47683d7b8d0f811365565fd98c4c8eb936bcab8d069fc33bd801b03ade
+        a2e1fbc5aa463d08ca19896d2bf59a071b851e6c239052172f296bfb5e724047
+        90a2181014f3b94a4e97d117b438130368cc39dbb2d198065ae3986547926cd2
+        162f40a29f0c3c8745c0f50fba3852e566d44575c29d39a03f0cda721984b6f4
+        40591f355e12d439ff150aab7613499dbd49adabc8676eef023b15b65bfc5ca0
+        6948109f23f350db82123535eb8a7433bdabcb909271a6ecbcb58b936a88cd4e
+        8f2e6ff5800175f113253d8fa9ca8885c2f552e657dc603f252e1a8e308f76f0
+        be79e2fb8f5d5fbbe2e30ecadd220723c8c0aea8078cdfcb3868263ff8f09400
+        54da48781893a7e49ad5aff4af300cd804a6b6279ab3ff3afb64491c85194aab
+        760d58a606654f9f4400e8b38591356fbf6425aca26dc85244259ff2b19c41b9
+        f96f3ca9ec1dde434da7d2d392b905ddf3d1f9af93d1af5950bd493f5aa731b4
+        056df31bd267b6b90a079831aaf579be0a39013137aac6d404f518cfd4684064
+        7e78bfe706ca4cf5e9c5453e9f7cfd2b8b4c8d169a44e55c88d4a9a7f9474241
+        e221af44860018ab0856972e194cd934";
+
+    #[test]
+    fn derives_the_client_initial_keys() {
+        // RFC 9001 appendix A.1
+        let keys = Keys::client_initial(VERSION_1, &bytes("8394c8f03e515708")).unwrap();
+        assert_eq!(keys.key.to_vec(), bytes("1f369613dd76d5467730efcbe3b1a22d"));
+        assert_eq!(keys.iv.to_vec(), bytes("fa044b2f42a3fd3b46fb255c"));
+        assert_eq!(keys.hp.to_vec(), bytes("9f50449e04a0e810283a1e9933adedd2"));
+        assert!(Keys::client_initial(0x0a0a_0a0a, &[]).is_none());
+    }
+
+    #[test]
+    fn decrypts_the_client_initial_of_rfc_9001() {
+        let datagram = bytes(CLIENT_INITIAL);
+        assert_eq!(datagram.len(), 1200);
+        let mut packet = test_packets::udp("192.0.2.1:50000", "192.0.2.2:443", &datagram);
+        let mut tracker = QuicTracker::default();
+        tracker.analyze(&mut packet);
+
+        let [quic] = packet.quic.packets.as_slice() else {
+            panic!("one QUIC packet expected, found {:?}", packet.quic.packets);
+        };
+        assert_eq!(quic.packet_type, PacketType::Initial);
+        assert_eq!(quic.version, Some(VERSION_1));
+        assert_eq!(quic.dcid.as_deref(), Some(&bytes("8394c8f03e515708")[..]));
+        assert_eq!(quic.scid.as_deref(), Some(&[][..]));
+        let payload = quic.initial.clone().unwrap().unwrap();
+        assert_eq!(payload.packet_number, 2);
+        assert!(matches!(
+            payload.frames[0],
+            Frame::Crypto { offset: 0, ref data } if data.len() == 241
+        ));
+        assert!(matches!(payload.frames[1], Frame::Padding(_)));
+
+        let hello = packet.quic.client_hello.unwrap();
+        assert_eq!(hello.server_name.as_deref(), Some("example.com"));
+        assert_eq!(hello.alpn, vec![String::from("alpn")]);
+        assert!(hello.complete);
+    }
+
+    #[test]
+    fn recognizes_short_headers_by_connection_ids_seen_before() {
+        let mut tracker = QuicTracker::default();
+        let mut initial =
+            test_packets::udp("192.0.2.1:50000", "192.0.2.2:443", &bytes(CLIENT_INITIAL));
+        tracker.analyze(&mut initial);
+
+        // off the HTTP/3 port only a known connection ID makes a short header QUIC
+        let mut short = vec![0x40];
+        short.extend(bytes("8394c8f03e515708"));
+        short.extend([0; 24]);
+        let mut packet = test_packets::udp("192.0.2.2:4433", "192.0.2.1:50000", &short);
+        tracker.analyze(&mut packet);
+        assert_eq!(
+            packet.quic.packets[0].dcid.as_deref(),
+            Some(&bytes("8394c8f03e515708")[..])
+        );
+
+        // a new capture starts without them
+        tracker.clear();
+        let mut packet = test_packets::udp("192.0.2.2:4433", "192.0.2.1:50000", &short);
+        tracker.analyze(&mut packet);
+        assert!(packet.quic.packets.is_empty());
+    }
+}
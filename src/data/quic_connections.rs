@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use super::column::SortOrder;
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+use super::process::Process;
+use super::quic::{self, ClientHello, Frame, PacketType};
+
+/// One QUIC connection, told apart by its connection IDs rather than its addresses,
+/// which may change on the way.
+#[derive(Debug, Clone)]
+pub struct QuicConnection {
+    /// Addresses of the first packet, the client is the side that sent the first Initial.
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    /// Local process owning the connection, from live captures on Linux.
+    pub process: Option<Process>,
+    pub version: Option<u32>,
+    /// Connection IDs of both sides in the order they showed up.
+    pub connection_ids: Vec<Vec<u8>>,
+    pub client_hello: Option<ClientHello>,
+    pub first_seen: Duration,
+    pub last_seen: Duration,
+    pub packets: usize,
+    /// Bytes of the frames sent by the client.
+    pub client_bytes: usize,
+    /// Bytes of the frames sent by the server.
+    pub server_bytes: usize,
+    /// Address pairs the connection moved to after its first, by a migration or NAT rebinding.
+    pub migrations: usize,
+    /// CRYPTO frames of the client's Initial packets, a ClientHello may span several.
+    crypto: Vec<Frame>,
+}
+
+impl QuicConnection {
+    fn new(client: SocketAddr, server: SocketAddr, timestamp: Duration) -> Self {
+        Self {
+            client,
+            server,
+            process: None,
+            version: None,
+            connection_ids: vec![],
+            client_hello: None,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            packets: 0,
+            client_bytes: 0,
+            server_bytes: 0,
+            migrations: 0,
+            crypto: vec![],
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.last_seen.saturating_sub(self.first_seen)
+    }
+
+    pub fn server_name(&self) -> Option<&str> {
+        self.client_hello.as_ref()?.server_name.as_deref()
+    }
+
+    pub fn alpn(&self) -> String {
+        self.client_hello
+            .as_ref()
+            .map(|hello| hello.alpn.join(", "))
+            .unwrap_or_default()
+    }
+
+    /// Display filter selecting the packets of this connection: those carrying one of
+    /// its connection IDs and those between its first addresses.
+    pub fn filter(&self) -> String {
+        let mut clauses = self
+            .connection_ids
+            .iter()
+            .map(|id| format!("quic.cid == {}", quic::hex(id)))
+            .collect::<Vec<_>>();
+        clauses.push(format!(
+            "(ip.addr == {} && udp.port == {} && ip.addr == {} && udp.port == {})",
+            self.client.ip(),
+            self.client.port(),
+            self.server.ip(),
+            self.server.port()
+        ));
+        clauses.join(" || ")
+    }
+
+    fn add(&mut self, packet: &ParsedPacket, from_client: bool) {
+        let quic = &packet.quic.packets;
+        let now = packet.timestamp;
+        self.packets += 1;
+        self.last_seen = self.last_seen.max(now);
+        self.first_seen = self.first_seen.min(now);
+        if self.process.is_none() {
+            self.process = packet.process.clone();
+        }
+        if from_client {
+            self.client_bytes += packet.data.len();
+        } else {
+            self.server_bytes += packet.data.len();
+        }
+
+        for id in quic
+            .iter()
+            .flat_map(|p| p.dcid.iter().chain(&p.scid))
+            .filter(|id| !id.is_empty())
+        {
+            if !self.connection_ids.contains(id) {
+                self.connection_ids.push(id.clone());
+            }
+        }
+        // a version negotiation names no version of the connection
+        if let Some(version) = quic.iter().filter_map(|p| p.version).find(|&v| v != 0) {
+            self.version = Some(version);
+        }
+
+        if !from_client {
+            return;
+        }
+        let frames = quic
+            .iter()
+            .filter_map(|p| p.initial.as_ref()?.as_ref().ok())
+            .flat_map(|payload| &payload.frames)
+            .filter(|frame| matches!(frame, Frame::Crypto { .. }))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !frames.is_empty() {
+            self.crypto.extend(frames);
+            self.client_hello = quic::client_hello(&quic::crypto_stream(&self.crypto));
+        }
+    }
+
+    /// Takes back the counts of a packet trimmed from the store. The connection IDs,
+    /// version and ClientHello it brought are kept.
+    fn remove(&mut self, packet: &ParsedPacket, from_client: bool) {
+        self.packets = self.packets.saturating_sub(1);
+        if from_client {
+            self.client_bytes = self.client_bytes.saturating_sub(packet.data.len());
+        } else {
+            self.server_bytes = self.server_bytes.saturating_sub(packet.data.len());
+        }
+        // the oldest packets are trimmed first, the next one is no older
+        self.first_seen = self.first_seen.max(packet.timestamp).min(self.last_seen);
+    }
+}
+
+/// Sortable columns of the QUIC connections table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuicColumn {
+    Client,
+    Server,
+    Process,
+    Version,
+    ServerName,
+    Alpn,
+    ConnectionIds,
+    Start,
+    Duration,
+    Packets,
+    ClientBytes,
+    ServerBytes,
+    Migrations,
+}
+
+impl QuicColumn {
+    pub const ALL: [QuicColumn; 13] = [
+        QuicColumn::Client,
+        QuicColumn::Server,
+        QuicColumn::Process,
+        QuicColumn::Version,
+        QuicColumn::ServerName,
+        QuicColumn::Alpn,
+        QuicColumn::ConnectionIds,
+        QuicColumn::Start,
+        QuicColumn::Duration,
+        QuicColumn::Packets,
+        QuicColumn::ClientBytes,
+        QuicColumn::ServerBytes,
+        QuicColumn::Migrations,
+    ];
+
+    /// Columns listed in order rather than as a ranking when first sorted by.
+    pub fn ascending_first(&self) -> bool {
+        matches!(
+            self,
+            QuicColumn::Client
+                | QuicColumn::Server
+                | QuicColumn::Process
+                | QuicColumn::Version
+                | QuicColumn::ServerName
+                | QuicColumn::Alpn
+                | QuicColumn::Start
+        )
+    }
+
+    /// Sort key of the numeric columns.
+    fn key(&self, connection: &QuicConnection) -> u128 {
+        match self {
+            QuicColumn::Client
+            | QuicColumn::Server
+            | QuicColumn::Process
+            | QuicColumn::ServerName
+            | QuicColumn::Alpn => 0,
+            QuicColumn::Version => connection.version.map_or(0, |v| v as u128 + 1),
+            QuicColumn::ConnectionIds => connection.connection_ids.len() as u128,
+            QuicColumn::Start => connection.first_seen.as_nanos(),
+            QuicColumn::Duration => connection.duration().as_nanos(),
+            QuicColumn::Packets => connection.packets as u128,
+            QuicColumn::ClientBytes => connection.client_bytes as u128,
+            QuicColumn::ServerBytes => connection.server_bytes as u128,
+            QuicColumn::Migrations => connection.migrations as u128,
+        }
+    }
+}
+
+impl fmt::Display for QuicColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            QuicColumn::Client => "Client",
+            QuicColumn::Server => "Server",
+            QuicColumn::Process => "Process",
+            QuicColumn::Version => "Version",
+            QuicColumn::ServerName => "Server Name",
+            QuicColumn::Alpn => "ALPN",
+            QuicColumn::ConnectionIds => "Connection IDs",
+            QuicColumn::Start => "Start",
+            QuicColumn::Duration => "Duration",
+            QuicColumn::Packets => "Packets",
+            QuicColumn::ClientBytes => "Bytes →",
+            QuicColumn::ServerBytes => "Bytes ←",
+            QuicColumn::Migrations => "Migrations",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Groups the QUIC packets into connections by their connection IDs as they arrive.
+/// Packets without a known connection ID, like the server's answers to a client that
+/// chose an empty one, go by their addresses.
+#[derive(Debug, Default)]
+pub struct QuicConnectionTable {
+    connections: Vec<QuicConnection>,
+    by_id: HashMap<Vec<u8>, usize>,
+    by_addresses: HashMap<(SocketAddr, SocketAddr), usize>,
+    /// Connections all packets of which were trimmed, dropped once they are half of all.
+    emptied: usize,
+}
+
+impl QuicConnectionTable {
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        let TransportPacket::Udp(udp) = &packet.transport else {
+            return;
+        };
+        let Some((source, destination)) = sockets(packet) else {
+            return;
+        };
+        let quic = &packet.quic.packets;
+        let key = (source.min(destination), source.max(destination));
+        let ids = connection_ids(packet);
+
+        let existing = self.find(&ids, key);
+        let i = match existing {
+            Some(i) => {
+                if self.connections[i].packets == 0 {
+                    self.emptied -= 1;
+                }
+                i
+            }
+            None => {
+                // the client sends the first Initial, otherwise the server is on the HTTP/3 port
+                let from_server =
+                    quic[0].packet_type != PacketType::Initial && udp.get_source() == quic::PORT;
+                let (client, server) = if from_server {
+                    (destination, source)
+                } else {
+                    (source, destination)
+                };
+                self.connections
+                    .push(QuicConnection::new(client, server, packet.timestamp));
+                self.connections.len() - 1
+            }
+        };
+        for id in ids {
+            self.by_id.entry(id.clone()).or_insert(i);
+        }
+        if self.by_addresses.insert(key, i).is_none() && existing.is_some() {
+            self.connections[i].migrations += 1;
+        }
+        let connection = &mut self.connections[i];
+        let from_client = source == connection.client || destination == connection.server;
+        connection.add(packet, from_client);
+    }
+
+    /// Takes back a packet counted before, once it is trimmed from the store.
+    pub fn remove(&mut self, packet: &ParsedPacket) {
+        let Some((source, destination)) = sockets(packet) else {
+            return;
+        };
+        let key = (source.min(destination), source.max(destination));
+        let Some(i) = self.find(&connection_ids(packet), key) else {
+            return;
+        };
+        let connection = &mut self.connections[i];
+        if connection.packets == 0 {
+            return;
+        }
+        let from_client = source == connection.client || destination == connection.server;
+        connection.remove(packet, from_client);
+        if connection.packets == 0 {
+            self.emptied += 1;
+            if self.emptied * 2 > self.connections.len() {
+                self.compact();
+            }
+        }
+    }
+
+    /// The connection of the first known connection ID, otherwise of the addresses.
+    fn find(&self, ids: &[&Vec<u8>], key: (SocketAddr, SocketAddr)) -> Option<usize> {
+        ids.iter()
+            .find_map(|id| self.by_id.get(*id))
+            .or_else(|| self.by_addresses.get(&key))
+            .copied()
+    }
+
+    /// Drops the connections without packets.
+    fn compact(&mut self) {
+        let mut kept = 0;
+        let moved = self
+            .connections
+            .iter()
+            .map(|connection| {
+                (connection.packets > 0).then(|| {
+                    kept += 1;
+                    kept - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        self.connections.retain(|connection| connection.packets > 0);
+        let remap = |i: &mut usize| moved[*i].map(|new| *i = new).is_some();
+        self.by_id.retain(|_, i| remap(i));
+        self.by_addresses.retain(|_, i| remap(i));
+        self.emptied = 0;
+    }
+
+    /// The connections sorted by `sort`.
+    pub fn sorted(&self, sort: (QuicColumn, SortOrder)) -> Vec<&QuicConnection> {
+        let mut connections = self
+            .connections
+            .iter()
+            .filter(|connection| connection.packets > 0)
+            .collect::<Vec<_>>();
+        let (column, order) = sort;
+        connections.sort_by(|a, b| {
+            let ordering = match column {
+                QuicColumn::Client => a.client.cmp(&b.client),
+                QuicColumn::Server => a.server.cmp(&b.server),
+                QuicColumn::Process => a
+                    .process
+                    .as_ref()
+                    .map(Process::sort_key)
+                    .cmp(&b.process.as_ref().map(Process::sort_key)),
+                QuicColumn::ServerName => a.server_name().cmp(&b.server_name()),
+                QuicColumn::Alpn => a.alpn().cmp(&b.alpn()),
+                column => column.key(a).cmp(&column.key(b)),
+            };
+            match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            }
+        });
+        connections
+    }
+}
+
+/// Source and destination socket of a UDP packet carrying QUIC.
+fn sockets(packet: &ParsedPacket) -> Option<(SocketAddr, SocketAddr)> {
+    let TransportPacket::Udp(udp) = &packet.transport else {
+        return None;
+    };
+    if packet.quic.packets.is_empty() {
+        return None;
+    }
+    Some((
+        SocketAddr::new(packet.get_source_ip()?, udp.get_source()),
+        SocketAddr::new(packet.get_destination_ip()?, udp.get_destination()),
+    ))
+}
+
+/// The non-empty connection IDs of the QUIC packets in a datagram.
+fn connection_ids(packet: &ParsedPacket) -> Vec<&Vec<u8>> {
+    packet
+        .quic
+        .packets
+        .iter()
+        .flat_map(|p| p.dcid.iter().chain(&p.scid))
+        .filter(|id| !id.is_empty())
+        .collect()
+}
@@ -0,0 +1,298 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::pcap::PcapngWriter;
+
+/// Where a capture is written to disk and when a new file is started.
+/// Zero disables the respective limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingBufferConfig {
+    pub directory: PathBuf,
+    pub file_size_mb: u64,
+    pub file_duration_secs: u64,
+    pub max_files: usize,
+}
+
+impl Default for RingBufferConfig {
+    fn default() -> Self {
+        Self {
+            directory: dirs::data_local_dir()
+                .map(|dir| dir.join("postal").join("captures"))
+                .unwrap_or_else(|| PathBuf::from("captures")),
+            file_size_mb: 100,
+            file_duration_secs: 0,
+            max_files: 10,
+        }
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
+pub enum RingBufferField {
+    Directory,
+    FileSize,
+    FileDuration,
+    MaxFiles,
+}
+
+impl RingBufferField {
+    pub const ALL: [RingBufferField; 4] = [
+        RingBufferField::Directory,
+        RingBufferField::FileSize,
+        RingBufferField::FileDuration,
+        RingBufferField::MaxFiles,
+    ];
+}
+
+impl fmt::Display for RingBufferField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RingBufferField::Directory => write!(f, "Directory"),
+            RingBufferField::FileSize => write!(f, "File size (MB)"),
+            RingBufferField::FileDuration => write!(f, "File duration (s)"),
+            RingBufferField::MaxFiles => write!(f, "Max files"),
+        }
+    }
+}
+
+impl RingBufferConfig {
+    pub fn get(&self, field: RingBufferField) -> String {
+        match field {
+            RingBufferField::Directory => self.directory.display().to_string(),
+            RingBufferField::FileSize => self.file_size_mb.to_string(),
+            RingBufferField::FileDuration => self.file_duration_secs.to_string(),
+            RingBufferField::MaxFiles => self.max_files.to_string(),
+        }
+    }
+
+    /// Applies a settings input, an empty number turns the limit off.
+    pub fn set(&mut self, field: RingBufferField, input: &str) -> Result<(), String> {
+        let input = input.trim();
+        let number = || {
+            if input.is_empty() {
+                Ok(0)
+            } else {
+                input
+                    .parse::<u64>()
+                    .map_err(|_| format!("{} must be a whole number", field))
+            }
+        };
+        match field {
+            RingBufferField::Directory => self.directory = PathBuf::from(input),
+            RingBufferField::FileSize => self.file_size_mb = number()?,
+            RingBufferField::FileDuration => self.file_duration_secs = number()?,
+            RingBufferField::MaxFiles => self.max_files = number()? as usize,
+        }
+        Ok(())
+    }
+}
+
+/// Progress of the disk capture, shared with the UI.
+#[derive(Debug, Clone, Default)]
+pub struct RingBufferStatus {
+    pub file: PathBuf,
+    pub file_bytes: u64,
+    pub total_bytes: u64,
+    pub error: Option<String>,
+}
+
+pub type SharedRingBufferStatus = Arc<Mutex<RingBufferStatus>>;
+
+/// Writes captured frames into a sequence of pcapng files, starting a new file once the
+/// current one is too large or too old and deleting the oldest file of this capture once
+/// there are more than `max_files`.
+#[derive(Debug)]
+pub struct RingBuffer {
+    config: RingBufferConfig,
+    writer: PcapngWriter,
+    opened: Instant,
+    last_flush: Instant,
+    files: VecDeque<PathBuf>,
+    sequence: usize,
+    finished_bytes: u64,
+    status: SharedRingBufferStatus,
+}
+
+impl RingBuffer {
+    pub fn create(config: RingBufferConfig) -> Result<Self, String> {
+        fs::create_dir_all(&config.directory).map_err(|e| {
+            format!(
+                "Could not create capture directory {}: {}",
+                config.directory.display(),
+                e
+            )
+        })?;
+        let path = Self::file_path(&config, 1);
+        let writer = PcapngWriter::create(&path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        let status = RingBufferStatus {
+            file: path.clone(),
+            ..RingBufferStatus::default()
+        };
+        Ok(Self {
+            config,
+            writer,
+            opened: Instant::now(),
+            last_flush: Instant::now(),
+            files: VecDeque::from([path]),
+            sequence: 1,
+            finished_bytes: 0,
+            status: Arc::new(Mutex::new(status)),
+        })
+    }
+
+    pub fn status(&self) -> SharedRingBufferStatus {
+        self.status.clone()
+    }
+
+    /// Writes a frame, errors are also recorded in the shared status.
+    pub fn write(&mut self, timestamp: Duration, data: &[u8]) -> io::Result<()> {
+        let result = self.try_write(timestamp, data);
+        let mut status = self.status.lock().unwrap();
+        if let Err(e) = &result {
+            status.error = Some(format!("Writing to disk stopped: {}", e));
+        }
+        status.file_bytes = self.writer.bytes_written();
+        status.total_bytes = self.finished_bytes + self.writer.bytes_written();
+        status.file = self.files.back().cloned().unwrap_or_default();
+        result
+    }
+
+    fn try_write(&mut self, timestamp: Duration, data: &[u8]) -> io::Result<()> {
+        let too_large = self.config.file_size_mb > 0
+            && self.writer.bytes_written() >= self.config.file_size_mb * 1024 * 1024;
+        let too_old = self.config.file_duration_secs > 0
+            && self.opened.elapsed() >= Duration::from_secs(self.config.file_duration_secs);
+        if too_large || too_old {
+            self.rotate()?;
+        }
+        self.writer.write_packet(timestamp, data, &[])?;
+        // keep the file readable by other tools while capturing
+        if self.last_flush.elapsed() >= Duration::from_secs(1) {
+            self.writer.flush()?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.finished_bytes += self.writer.bytes_written();
+        self.sequence += 1;
+        let path = Self::file_path(&self.config, self.sequence);
+        self.writer = PcapngWriter::create(&path)?;
+        self.opened = Instant::now();
+        self.files.push_back(path);
+        while self.config.max_files > 0 && self.files.len() > self.config.max_files {
+            if let Some(oldest) = self.files.pop_front() {
+                if let Err(e) = fs::remove_file(&oldest) {
+                    eprintln!("Could not remove {}: {}", oldest.display(), e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn file_path(config: &RingBufferConfig, sequence: usize) -> PathBuf {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        config
+            .directory
+            .join(format!("postal_{:05}_{}.pcapng", sequence, now.as_secs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::pcap;
+
+    /// A fresh directory under the system's temporary one.
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("postal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn files(directory: &PathBuf) -> Vec<PathBuf> {
+        let mut files = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn rotates_by_size_and_prunes_the_oldest_files() {
+        let directory = directory("ring-buffer-size");
+        let mut ring = RingBuffer::create(RingBufferConfig {
+            directory: directory.clone(),
+            file_size_mb: 1,
+            file_duration_secs: 0,
+            max_files: 2,
+        })
+        .unwrap();
+        let frame = [0xab; 64 * 1024];
+        let first = ring.files[0].clone();
+        // 16 frames of 64 KiB fill the first MiB, the 17th and 33rd start new files
+        for i in 0..40 {
+            ring.write(Duration::from_millis(i), &frame).unwrap();
+        }
+        ring.writer.flush().unwrap();
+
+        assert_eq!(ring.sequence, 3);
+        assert_eq!(files(&directory), Vec::from(ring.files.clone()));
+        assert!(!first.exists());
+        let frames = ring
+            .files
+            .iter()
+            .map(|file| pcap::read_file(file).unwrap().len())
+            .collect::<Vec<_>>();
+        assert_eq!(frames, [16, 8]);
+
+        let status = ring.status().lock().unwrap().clone();
+        assert_eq!(status.file, ring.files[1]);
+        assert_eq!(status.file_bytes, ring.writer.bytes_written());
+        assert!(status.total_bytes > 40 * frame.len() as u64);
+        assert_eq!(status.error, None);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rotates_by_duration_and_keeps_all_files_without_a_limit() {
+        let directory = directory("ring-buffer-duration");
+        let mut ring = RingBuffer::create(RingBufferConfig {
+            directory: directory.clone(),
+            file_size_mb: 0,
+            file_duration_secs: 60,
+            max_files: 0,
+        })
+        .unwrap();
+        for i in 0..5 {
+            ring.write(Duration::from_secs(i), &[i as u8; 60]).unwrap();
+            ring.write(Duration::from_secs(i), &[i as u8; 60]).unwrap();
+            // the next frame finds the file a minute old
+            ring.opened = Instant::now().checked_sub(Duration::from_secs(60)).unwrap();
+        }
+        ring.writer.flush().unwrap();
+
+        assert_eq!(ring.sequence, 5);
+        assert_eq!(files(&directory), Vec::from(ring.files.clone()));
+        for (i, file) in ring.files.iter().enumerate() {
+            let frames = pcap::read_file(file).unwrap();
+            assert_eq!(frames.len(), 2);
+            assert!(frames.iter().all(|frame| frame.data == [i as u8; 60]));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::ops::Range;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+use super::parsed_packet::ParsedPacket;
+
+/// How the search input is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    #[default]
+    Text,
+    Regex,
+    Hex,
+}
+
+impl SearchMode {
+    pub const ALL: [SearchMode; 3] = [SearchMode::Text, SearchMode::Regex, SearchMode::Hex];
+}
+
+impl fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SearchMode::Text => "Text",
+            SearchMode::Regex => "Regex",
+            SearchMode::Hex => "Hex bytes",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A compiled search over the raw bytes of captured frames.
+#[derive(Debug, Clone)]
+pub enum Search {
+    Bytes {
+        needle: Vec<u8>,
+        case_sensitive: bool,
+    },
+    Regex(Regex),
+}
+
+impl Search {
+    pub fn new(input: &str, mode: SearchMode, case_sensitive: bool) -> Result<Self, String> {
+        if input.is_empty() {
+            return Err(String::from("Nothing to search for"));
+        }
+        match mode {
+            SearchMode::Text => Ok(Search::Bytes {
+                needle: input.as_bytes().to_vec(),
+                case_sensitive,
+            }),
+            SearchMode::Regex => RegexBuilder::new(input)
+                .case_insensitive(!case_sensitive)
+                .unicode(false)
+                .build()
+                .map(Search::Regex)
+                .map_err(|e| e.to_string()),
+            SearchMode::Hex => parse_hex(input).map(|needle| Search::Bytes {
+                needle,
+                case_sensitive: true,
+            }),
+        }
+    }
+
+    /// Byte range of the first match in `data`.
+    pub fn find(&self, data: &[u8]) -> Option<Range<usize>> {
+        match self {
+            Search::Bytes {
+                needle,
+                case_sensitive,
+            } => data
+                .windows(needle.len())
+                .position(|window| {
+                    if *case_sensitive {
+                        window == needle.as_slice()
+                    } else {
+                        window.eq_ignore_ascii_case(needle)
+                    }
+                })
+                .map(|start| start..start + needle.len()),
+            Search::Regex(regex) => regex
+                .find_iter(data)
+                .find(|m| !m.is_empty())
+                .map(|m| m.range()),
+        }
+    }
+
+    pub fn matches(&self, packet: &ParsedPacket) -> bool {
+        self.find(&packet.data).is_some()
+    }
+}
+
+/// Accepts `de ad be ef`, `deadbeef`, `de:ad:be:ef` and `0xdeadbeef`.
+fn parse_hex(input: &str) -> Result<Vec<u8>, String> {
+    let digits = input
+        .trim()
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':' && *c != '-')
+        .collect::<String>();
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return Err(format!("'{}' is not a sequence of hex bytes", input.trim()));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("'{}' is not a sequence of hex bytes", input.trim()))
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+use iced::keyboard::{key::Named, Key, Modifiers};
+
+/// Keyboard shortcuts of the main window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    ToggleCapture,
+    Clear,
+    FocusFilter,
+    Find,
+    FindNext,
+    FindPrevious,
+    SelectPrevious,
+    SelectNext,
+    PageUp,
+    PageDown,
+    SelectFirst,
+    SelectLast,
+    ToggleMark,
+    NextMarked,
+    PreviousMarked,
+    ExpandDetails,
+    CollapseDetails,
+    Help,
+    Dismiss,
+}
+
+impl Shortcut {
+    pub const ALL: [Shortcut; 19] = [
+        Shortcut::ToggleCapture,
+        Shortcut::Clear,
+        Shortcut::FocusFilter,
+        Shortcut::Find,
+        Shortcut::FindNext,
+        Shortcut::FindPrevious,
+        Shortcut::SelectPrevious,
+        Shortcut::SelectNext,
+        Shortcut::PageUp,
+        Shortcut::PageDown,
+        Shortcut::SelectFirst,
+        Shortcut::SelectLast,
+        Shortcut::ToggleMark,
+        Shortcut::NextMarked,
+        Shortcut::PreviousMarked,
+        Shortcut::ExpandDetails,
+        Shortcut::CollapseDetails,
+        Shortcut::Help,
+        Shortcut::Dismiss,
+    ];
+
+    pub fn from_key(key: Key<&str>, modifiers: Modifiers) -> Option<Self> {
+        let shortcut = match key {
+            Key::Character("e") if modifiers.command() => Shortcut::ToggleCapture,
+            Key::Character("l") if modifiers.command() => Shortcut::Clear,
+            Key::Character("/") if modifiers.command() => Shortcut::FocusFilter,
+            Key::Character("f") if modifiers.command() => Shortcut::Find,
+            Key::Character("m" | "M") if modifiers.command() => Shortcut::ToggleMark,
+            Key::Character("n" | "N") if modifiers.command() && modifiers.shift() => {
+                Shortcut::NextMarked
+            }
+            Key::Character("b" | "B") if modifiers.command() && modifiers.shift() => {
+                Shortcut::PreviousMarked
+            }
+            Key::Named(Named::F3) if modifiers.shift() => Shortcut::FindPrevious,
+            Key::Named(Named::F3) => Shortcut::FindNext,
+            Key::Named(Named::ArrowUp) => Shortcut::SelectPrevious,
+            Key::Named(Named::ArrowDown) => Shortcut::SelectNext,
+            Key::Named(Named::PageUp) => Shortcut::PageUp,
+            Key::Named(Named::PageDown) => Shortcut::PageDown,
+            Key::Named(Named::Home) => Shortcut::SelectFirst,
+            Key::Named(Named::End) => Shortcut::SelectLast,
+            Key::Named(Named::ArrowRight) => Shortcut::ExpandDetails,
+            Key::Named(Named::ArrowLeft) => Shortcut::CollapseDetails,
+            Key::Named(Named::F1) => Shortcut::Help,
+            Key::Named(Named::Escape) => Shortcut::Dismiss,
+            _ => return None,
+        };
+        Some(shortcut)
+    }
+
+    /// Navigation keys are left to a focused text input.
+    pub fn is_navigation(&self) -> bool {
+        matches!(
+            self,
+            Shortcut::SelectPrevious
+                | Shortcut::SelectNext
+                | Shortcut::PageUp
+                | Shortcut::PageDown
+                | Shortcut::SelectFirst
+                | Shortcut::SelectLast
+                | Shortcut::ExpandDetails
+                | Shortcut::CollapseDetails
+        )
+    }
+
+    pub fn keys(&self) -> &'static str {
+        match self {
+            Shortcut::ToggleCapture => "Ctrl+E",
+            Shortcut::Clear => "Ctrl+L",
+            Shortcut::FocusFilter => "Ctrl+/",
+            Shortcut::Find => "Ctrl+F",
+            Shortcut::FindNext => "F3",
+            Shortcut::FindPrevious => "Shift+F3",
+            Shortcut::SelectPrevious => "↑",
+            Shortcut::SelectNext => "↓",
+            Shortcut::PageUp => "Page Up",
+            Shortcut::PageDown => "Page Down",
+            Shortcut::SelectFirst => "Home",
+            Shortcut::SelectLast => "End",
+            Shortcut::ToggleMark => "Ctrl+M",
+            Shortcut::NextMarked => "Ctrl+Shift+N",
+            Shortcut::PreviousMarked => "Ctrl+Shift+B",
+            Shortcut::ExpandDetails => "→",
+            Shortcut::CollapseDetails => "←",
+            Shortcut::Help => "F1",
+            Shortcut::Dismiss => "Esc",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Shortcut::ToggleCapture => "Start or stop the capture",
+            Shortcut::Clear => "Clear the captured packets",
+            Shortcut::FocusFilter => "Focus the display filter",
+            Shortcut::Find => "Search packet bytes",
+            Shortcut::FindNext => "Next search match",
+            Shortcut::FindPrevious => "Previous search match",
+            Shortcut::SelectPrevious => "Select the previous packet",
+            Shortcut::SelectNext => "Select the next packet",
+            Shortcut::PageUp => "Move the selection one page up",
+            Shortcut::PageDown => "Move the selection one page down",
+            Shortcut::SelectFirst => "Select the first packet",
+            Shortcut::SelectLast => "Select the last packet",
+            Shortcut::ToggleMark => "Mark or unmark the selected packet",
+            Shortcut::NextMarked => "Next marked packet",
+            Shortcut::PreviousMarked => "Previous marked packet",
+            Shortcut::ExpandDetails => "Expand all packet details",
+            Shortcut::CollapseDetails => "Collapse all packet details",
+            Shortcut::Help => "Show or hide this help",
+            Shortcut::Dismiss => "Close the help or the search bar",
+        }
+    }
+}
@@ -0,0 +1,644 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use pnet::packet::{
+    tcp::{TcpFlags, TcpOptionNumbers, TcpPacket},
+    Packet,
+};
+
+use super::parsed_packet::{ParsedPacket, TransportPacket};
+
+/// Findings of the TCP analysis for one packet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TcpAnalysis {
+    pub retransmission: bool,
+    /// Fills a gap shortly after later data was seen.
+    pub out_of_order: bool,
+    /// Starts beyond the data seen so far, the segments in between were not captured.
+    pub lost_segment: bool,
+    /// Number of the duplicate ACK, counted since the original one.
+    pub duplicate_ack: Option<u32>,
+    pub zero_window: bool,
+    /// The segment uses up the window the receiver advertised last.
+    pub window_full: bool,
+    pub reset: bool,
+    /// Time since the segment this packet acknowledges.
+    pub ack_rtt: Option<Duration>,
+    /// Time from the SYN to the ACK completing the handshake, set on that ACK.
+    pub handshake_rtt: Option<Duration>,
+}
+
+impl TcpAnalysis {
+    /// Wireshark style labels of the problems found, for the Info column.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = vec![];
+        let mut flag = |set: bool, label: &str| {
+            if set {
+                labels.push(label.to_string());
+            }
+        };
+        flag(self.lost_segment, "TCP Previous segment not captured");
+        flag(self.retransmission, "TCP Retransmission");
+        flag(self.out_of_order, "TCP Out-Of-Order");
+        flag(self.zero_window, "TCP ZeroWindow");
+        flag(self.window_full, "TCP Window Full");
+        flag(self.reset, "TCP Reset");
+        if let Some(count) = self.duplicate_ack {
+            labels.push(format!("TCP Dup ACK #{}", count));
+        }
+        labels
+    }
+
+    /// Whether any problem was found, RTTs are measurements and do not count.
+    pub fn has_problem(&self) -> bool {
+        self.retransmission
+            || self.out_of_order
+            || self.lost_segment
+            || self.duplicate_ack.is_some()
+            || self.zero_window
+            || self.window_full
+            || self.reset
+    }
+}
+
+/// A segment not acknowledged yet, kept to measure the ACK RTT.
+#[derive(Debug)]
+struct Unacked {
+    end: u32,
+    /// When the segment was sent last.
+    sent: Duration,
+    /// TSval of the last transmission, the ACK echoes it back.
+    tsval: Option<u32>,
+    retransmitted: bool,
+}
+
+/// Options of a segment the analysis reads.
+#[derive(Debug, Default)]
+struct SegmentOptions {
+    /// Ranges the receiver holds beyond the ACK (RFC 2018).
+    sack: Vec<(u32, u32)>,
+    /// TSval and TSecr (RFC 7323).
+    timestamps: Option<(u32, u32)>,
+}
+
+impl SegmentOptions {
+    fn new(tcp: &TcpPacket) -> Self {
+        let mut options = SegmentOptions::default();
+        let word = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        for option in tcp.get_options_iter() {
+            let payload = option.payload();
+            match option.get_number() {
+                TcpOptionNumbers::SACK => {
+                    options.sack = payload
+                        .chunks_exact(8)
+                        .map(|block| (word(&block[..4]), word(&block[4..])))
+                        .collect();
+                }
+                TcpOptionNumbers::TIMESTAMPS if payload.len() == 8 => {
+                    options.timestamps = Some((word(&payload[..4]), word(&payload[4..])));
+                }
+                _ => {}
+            }
+        }
+        options
+    }
+
+    fn tsval(&self) -> Option<u32> {
+        self.timestamps.map(|(tsval, _)| tsval)
+    }
+
+    fn tsecr(&self) -> Option<u32> {
+        self.timestamps.map(|(_, tsecr)| tsecr)
+    }
+
+    /// Ranges between the ACK and the SACK blocks, the receiver is missing them.
+    fn missing(&self, ack: u32) -> Vec<(u32, u32)> {
+        let mut blocks = self
+            .sack
+            .iter()
+            .copied()
+            // D-SACK blocks report duplicates below the ACK
+            .filter(|&(_, right)| seq_after(right, ack))
+            .collect::<Vec<_>>();
+        blocks.sort_by_key(|&(left, _)| left.wrapping_sub(ack));
+        let mut missing = vec![];
+        let mut covered = ack;
+        for (left, right) in blocks {
+            if seq_after(left, covered) {
+                missing.push((covered, left));
+            }
+            if seq_after(right, covered) {
+                covered = right;
+            }
+        }
+        missing
+    }
+}
+
+/// State of one direction of a connection.
+#[derive(Debug, Default)]
+struct FlowState {
+    /// Sequence number following the highest byte sent so far.
+    next_seq: Option<u32>,
+    /// When `next_seq` last moved forward.
+    last_advance: Duration,
+    /// TSval of the segment that moved `next_seq` forward last.
+    advance_tsval: Option<u32>,
+    /// Sequence ranges skipped over by a lost segment, not filled yet.
+    holes: Vec<(u32, u32)>,
+    /// Sequence ranges the receiver reported missing in its last SACK.
+    missing: Vec<(u32, u32)>,
+    last_ack: Option<u32>,
+    last_window: u16,
+    /// `last_window` in bytes.
+    last_window_bytes: u32,
+    dup_acks: u32,
+    /// Shift from the window scale option of the SYN.
+    window_scale: Option<u8>,
+    syn_sent: Option<Duration>,
+    syn_ack_seen: bool,
+    unacked: VecDeque<Unacked>,
+    /// Most recent RTT of the data sent this way, tells out-of-order from retransmission.
+    rtt: Option<Duration>,
+    /// Capture time of the last packet sent this way.
+    last_seen: Duration,
+    /// A FIN was sent or the connection was reset, the flow is over.
+    finished: bool,
+}
+
+impl FlowState {
+    /// Longest list of segments waiting for their ACK.
+    const MAX_UNACKED: usize = 4096;
+    const MAX_HOLES: usize = 16;
+    /// Out-of-order threshold while the RTT is unknown, after Wireshark.
+    const REORDER_TIME: Duration = Duration::from_millis(3);
+    /// Time a finished flow is kept for retransmitted FINs and the last ACKs.
+    const LINGER: Duration = Duration::from_secs(30);
+    /// Time after which a silent flow is forgotten, whether it finished or not.
+    const IDLE: Duration = Duration::from_secs(600);
+
+    fn is_expired(&self, now: Duration) -> bool {
+        let idle = now.saturating_sub(self.last_seen);
+        idle >= Self::IDLE || (self.finished && idle >= Self::LINGER)
+    }
+}
+
+/// Follows the TCP connections of a capture, packets have to be fed in capture order.
+#[derive(Debug, Default)]
+pub struct TcpTracker {
+    flows: HashMap<(SocketAddr, SocketAddr), FlowState>,
+    /// Capture time the expired flows were last removed at.
+    swept: Duration,
+}
+
+impl TcpTracker {
+    /// Time between two sweeps for expired flows.
+    const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn clear(&mut self) {
+        self.flows.clear();
+        self.swept = Duration::ZERO;
+    }
+
+    pub fn analyze(&mut self, packet: &mut ParsedPacket) {
+        let TransportPacket::Tcp(tcp) = &packet.transport else {
+            return;
+        };
+        let (Some(src), Some(dst)) = (packet.get_source_ip(), packet.get_destination_ip()) else {
+            return;
+        };
+        let source = SocketAddr::new(src, tcp.get_source());
+        let destination = SocketAddr::new(dst, tcp.get_destination());
+        let now = packet.timestamp;
+        if now.saturating_sub(self.swept) >= Self::SWEEP_INTERVAL {
+            self.flows.retain(|_, flow| !flow.is_expired(now));
+            self.swept = now;
+        }
+        let flags = tcp.get_flags();
+        let has = |flag: u8| flags & flag != 0;
+        let seq = tcp.get_sequence();
+        let ack = tcp.get_acknowledgement();
+        let options = SegmentOptions::new(tcp);
+        let payload_len = tcp.payload().len() as u32;
+        // SYN and FIN occupy one sequence number each
+        let len = payload_len + u32::from(has(TcpFlags::SYN)) + u32::from(has(TcpFlags::FIN));
+        let end = seq.wrapping_add(len);
+
+        let mut analysis = TcpAnalysis {
+            reset: has(TcpFlags::RST),
+            zero_window: tcp.get_window() == 0
+                && !has(TcpFlags::SYN)
+                && !has(TcpFlags::FIN)
+                && !has(TcpFlags::RST),
+            ..Default::default()
+        };
+
+        // the reverse direction is read first, it holds what this packet acknowledges
+        let (peer_window, peer_scale, handshake_sent, acked_rtt) =
+            match self.flows.get_mut(&(destination, source)) {
+                Some(reverse) => {
+                    let window = reverse.last_ack.map(|ack| (ack, reverse.last_window_bytes));
+                    let acked_rtt = if has(TcpFlags::ACK) {
+                        if options.sack.is_empty() {
+                            reverse.missing.retain(|&(_, end)| seq_after(end, ack));
+                        } else {
+                            reverse.missing = options.missing(ack);
+                        }
+                        take_acked(&mut reverse.unacked, ack, options.tsecr(), now)
+                    } else {
+                        None
+                    };
+                    reverse.rtt = acked_rtt.or(reverse.rtt);
+                    reverse.finished |= has(TcpFlags::RST);
+                    (
+                        window,
+                        reverse.window_scale,
+                        reverse.syn_ack_seen,
+                        acked_rtt,
+                    )
+                }
+                None => (None, None, false, None),
+            };
+
+        let flow = self.flows.entry((source, destination)).or_default();
+        if has(TcpFlags::SYN) && flow.finished {
+            // the ports are reused for a new connection
+            *flow = FlowState::default();
+        }
+        flow.last_seen = now;
+        flow.finished |= has(TcpFlags::FIN) || has(TcpFlags::RST);
+        if has(TcpFlags::SYN) {
+            flow.window_scale = window_scale(tcp);
+            if has(TcpFlags::ACK) {
+                flow.syn_ack_seen = true;
+            } else {
+                flow.syn_sent = Some(now);
+            }
+        } else if has(TcpFlags::ACK) && handshake_sent {
+            if let Some(syn_sent) = flow.syn_sent.take() {
+                analysis.handshake_rtt = Some(now.saturating_sub(syn_sent));
+            }
+        }
+        analysis.ack_rtt = acked_rtt;
+
+        // a keep-alive repeats the last byte on purpose
+        let keep_alive = flow.next_seq.is_some_and(|next| {
+            seq == next.wrapping_sub(1) && payload_len <= 1 && len == payload_len
+        });
+        match flow.next_seq {
+            _ if keep_alive => {}
+            Some(next) if len > 0 && !seq_after(end, next) && !has(TcpFlags::RST) => {
+                let covers =
+                    |&(start, end): &(u32, u32)| !seq_after(start, seq) && seq_after(end, seq);
+                let hole = flow.holes.iter().position(covers);
+                // the receiver asked for it again, whatever the timing
+                let missing = flow.missing.iter().position(covers);
+                // a segment sent before the one that moved `next_seq` was only delayed
+                let sent_before = match (options.tsval(), flow.advance_tsval) {
+                    (Some(tsval), Some(advanced)) if tsval != advanced => {
+                        Some(seq_after(advanced, tsval))
+                    }
+                    _ => None,
+                };
+                let threshold = flow.rtt.unwrap_or(FlowState::REORDER_TIME);
+                let reordered = missing.is_none()
+                    && sent_before.unwrap_or(now.saturating_sub(flow.last_advance) < threshold);
+                if let Some(missing) = missing {
+                    flow.missing.remove(missing);
+                }
+                match hole {
+                    Some(hole) if reordered => {
+                        analysis.out_of_order = true;
+                        flow.holes.remove(hole);
+                    }
+                    _ => {
+                        analysis.retransmission = true;
+                        if let Some(hole) = hole {
+                            flow.holes.remove(hole);
+                        }
+                        for segment in flow.unacked.iter_mut().filter(|s| s.end == end) {
+                            segment.retransmitted = true;
+                            segment.sent = now;
+                            segment.tsval = options.tsval();
+                        }
+                    }
+                }
+            }
+            Some(next) if !seq_after(end, next) => {}
+            next => {
+                if let Some(next) = next {
+                    if seq_after(seq, next) && !has(TcpFlags::RST) {
+                        analysis.lost_segment = true;
+                        if flow.holes.len() < FlowState::MAX_HOLES {
+                            flow.holes.push((next, seq));
+                        }
+                    }
+                }
+                flow.next_seq = Some(end);
+                flow.last_advance = now;
+                flow.advance_tsval = options.tsval();
+                if len > 0 && flow.unacked.len() < FlowState::MAX_UNACKED {
+                    flow.unacked.push_back(Unacked {
+                        end,
+                        sent: now,
+                        tsval: options.tsval(),
+                        retransmitted: false,
+                    });
+                }
+            }
+        }
+
+        if let Some((ack, window)) = peer_window {
+            analysis.window_full = payload_len > 0
+                && window > 0
+                && !has(TcpFlags::SYN)
+                && !has(TcpFlags::FIN)
+                && !has(TcpFlags::RST)
+                && end == ack.wrapping_add(window);
+        }
+
+        if has(TcpFlags::ACK) {
+            let pure_ack = len == 0 && !has(TcpFlags::RST) && !keep_alive;
+            // a window update repeats the ACK too, unless it reports what arrived beyond it
+            let same_window = flow.last_window == tcp.get_window() || !options.sack.is_empty();
+            if pure_ack && flow.last_ack == Some(ack) && same_window {
+                flow.dup_acks += 1;
+                analysis.duplicate_ack = Some(flow.dup_acks);
+            } else if flow.last_ack != Some(ack) {
+                flow.dup_acks = 0;
+            }
+            flow.last_ack = Some(ack);
+            flow.last_window = tcp.get_window();
+            // scaling is in effect only if both ends announced it
+            let scale = flow.window_scale.filter(|_| peer_scale.is_some());
+            flow.last_window_bytes = match scale {
+                Some(shift) if !has(TcpFlags::SYN) => (tcp.get_window() as u32) << shift,
+                _ => tcp.get_window() as u32,
+            };
+        }
+
+        packet.tcp_analysis = analysis;
+    }
+}
+
+/// Removes the segments covered by `ack` and returns the RTT of the last one.
+/// A retransmitted segment only gives one if the ACK echoes the TSval of its last transmission,
+/// otherwise it is unclear which transmission is acknowledged (Karn).
+fn take_acked(
+    unacked: &mut VecDeque<Unacked>,
+    ack: u32,
+    tsecr: Option<u32>,
+    now: Duration,
+) -> Option<Duration> {
+    let mut rtt = None;
+    while let Some(segment) = unacked.front() {
+        if seq_after(segment.end, ack) {
+            break;
+        }
+        let unambiguous = !segment.retransmitted || (tsecr.is_some() && tsecr == segment.tsval);
+        rtt = unambiguous.then(|| now.saturating_sub(segment.sent));
+        unacked.pop_front();
+    }
+    rtt
+}
+
+/// Window scale shift announced in a SYN, capped at 14 (RFC 7323).
+pub fn window_scale(tcp: &TcpPacket) -> Option<u8> {
+    tcp.get_options_iter()
+        .find(|option| option.get_number() == TcpOptionNumbers::WSCALE)
+        .and_then(|option| option.payload().first().map(|&shift| shift.min(14)))
+}
+
+/// Sequence number comparison that survives wrap around (RFC 1982).
+fn seq_after(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::*;
+    use crate::data::test_packets::{self, Segment};
+
+    const CLIENT: &str = "10.0.0.1:40000";
+    const SERVER: &str = "10.0.0.2:80";
+
+    fn send(tracker: &mut TcpTracker, from_client: bool, segment: Segment) -> TcpAnalysis {
+        let (source, destination) = if from_client {
+            (CLIENT, SERVER)
+        } else {
+            (SERVER, CLIENT)
+        };
+        let mut packet = test_packets::tcp(source, destination, segment);
+        tracker.analyze(&mut packet);
+        packet.tcp_analysis
+    }
+
+    /// 100 bytes from the client.
+    fn data(seq: u32, at: u64) -> Segment {
+        Segment {
+            seq,
+            payload: vec![0; 100],
+            at,
+            ..Default::default()
+        }
+    }
+
+    /// An ACK of the server.
+    fn ack(ack: u32, at: u64) -> Segment {
+        Segment {
+            seq: 1,
+            ack,
+            at,
+            ..Default::default()
+        }
+    }
+
+    fn sack(blocks: &[(u32, u32)]) -> Vec<u8> {
+        let mut option = vec![1, 1, 5, 2 + 8 * blocks.len() as u8];
+        for (left, right) in blocks {
+            option.extend(left.to_be_bytes());
+            option.extend(right.to_be_bytes());
+        }
+        option
+    }
+
+    fn timestamps(tsval: u32, tsecr: u32) -> Vec<u8> {
+        let mut option = vec![1, 1, 8, 10];
+        option.extend(tsval.to_be_bytes());
+        option.extend(tsecr.to_be_bytes());
+        option
+    }
+
+    #[test]
+    fn handshake_and_ack_rtt() {
+        let mut tracker = TcpTracker::default();
+        let syn = TcpFlags::SYN;
+        send(
+            &mut tracker,
+            true,
+            Segment {
+                flags: syn,
+                ..Default::default()
+            },
+        );
+        let syn_ack = Segment {
+            ack: 1,
+            flags: syn | TcpFlags::ACK,
+            at: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            send(&mut tracker, false, syn_ack).ack_rtt,
+            Some(Duration::from_millis(10))
+        );
+        let analysis = send(
+            &mut tracker,
+            true,
+            Segment {
+                seq: 1,
+                ack: 1,
+                at: 20,
+                ..Default::default()
+            },
+        );
+        assert_eq!(analysis.handshake_rtt, Some(Duration::from_millis(20)));
+
+        send(&mut tracker, true, data(1, 30));
+        let analysis = send(&mut tracker, false, ack(101, 45));
+        assert_eq!(analysis.ack_rtt, Some(Duration::from_millis(15)));
+        assert!(!analysis.has_problem());
+    }
+
+    #[test]
+    fn lost_segment_out_of_order_and_retransmission() {
+        let mut tracker = TcpTracker::default();
+        assert!(!send(&mut tracker, true, data(1, 0)).has_problem());
+        assert!(send(&mut tracker, true, data(201, 1)).lost_segment);
+        let late = send(&mut tracker, true, data(101, 2));
+        assert!(late.out_of_order && !late.retransmission);
+        let again = send(&mut tracker, true, data(1, 100));
+        assert!(again.retransmission && !again.out_of_order);
+    }
+
+    #[test]
+    fn keep_alive_is_not_a_retransmission() {
+        let mut tracker = TcpTracker::default();
+        send(&mut tracker, true, data(1, 0));
+        let keep_alive = Segment {
+            seq: 100,
+            at: 5000,
+            ..Default::default()
+        };
+        assert!(!send(&mut tracker, true, keep_alive).has_problem());
+    }
+
+    #[test]
+    fn duplicate_acks_with_sack_blocks() {
+        let mut tracker = TcpTracker::default();
+        send(&mut tracker, true, data(1, 0));
+        assert_eq!(send(&mut tracker, false, ack(101, 10)).duplicate_ack, None);
+        assert_eq!(
+            send(&mut tracker, false, ack(101, 11)).duplicate_ack,
+            Some(1)
+        );
+        let update = Segment {
+            window: 2000,
+            ..ack(101, 12)
+        };
+        assert_eq!(send(&mut tracker, false, update).duplicate_ack, None);
+        let with_sack = Segment {
+            window: 500,
+            options: sack(&[(201, 301)]),
+            ..ack(101, 13)
+        };
+        assert_eq!(send(&mut tracker, false, with_sack).duplicate_ack, Some(2));
+        assert_eq!(send(&mut tracker, false, ack(301, 14)).duplicate_ack, None);
+    }
+
+    #[test]
+    fn gap_reported_by_sack_is_retransmitted() {
+        let mut tracker = TcpTracker::default();
+        send(&mut tracker, true, data(1, 0));
+        assert!(send(&mut tracker, true, data(201, 1)).lost_segment);
+        let report = Segment {
+            options: sack(&[(201, 301)]),
+            ..ack(101, 2)
+        };
+        send(&mut tracker, false, report);
+        // as quick as a reordered segment, but the receiver asked for it
+        let fill = send(&mut tracker, true, data(101, 3));
+        assert!(fill.retransmission && !fill.out_of_order);
+    }
+
+    #[test]
+    fn timestamps_tell_reordering_from_retransmission() {
+        let mut tracker = TcpTracker::default();
+        let stamped = |seq, at, tsval| Segment {
+            options: timestamps(tsval, 0),
+            ..data(seq, at)
+        };
+        send(&mut tracker, true, stamped(1, 0, 100));
+        assert!(send(&mut tracker, true, stamped(201, 1, 101)).lost_segment);
+        // long after, but sent before the segment that skipped it
+        let delayed = send(&mut tracker, true, stamped(101, 50, 100));
+        assert!(delayed.out_of_order && !delayed.retransmission);
+
+        send(&mut tracker, true, stamped(401, 51, 102));
+        // right away, but sent after
+        let resent = send(&mut tracker, true, stamped(301, 52, 103));
+        assert!(resent.retransmission && !resent.out_of_order);
+
+        send(&mut tracker, true, stamped(501, 53, 104));
+        send(&mut tracker, true, stamped(501, 70, 105));
+        // the echoed TSval ties the ACK to the retransmission
+        let echo = Segment {
+            options: timestamps(900, 105),
+            ..ack(601, 78)
+        };
+        assert_eq!(
+            send(&mut tracker, false, echo).ack_rtt,
+            Some(Duration::from_millis(8))
+        );
+    }
+
+    #[test]
+    fn finished_flows_are_forgotten() {
+        let mut tracker = TcpTracker::default();
+        send(&mut tracker, true, data(1, 0));
+        // the reverse direction is only followed once it sends
+        assert_eq!(tracker.flows.len(), 1);
+
+        let fin = |seq, at| Segment {
+            seq,
+            flags: TcpFlags::FIN | TcpFlags::ACK,
+            at,
+            ..Default::default()
+        };
+        send(&mut tracker, true, fin(101, 10));
+        send(&mut tracker, false, fin(1, 20));
+        assert_eq!(tracker.flows.len(), 2);
+
+        // a new connection on the same ports starts afresh
+        let syn = Segment {
+            seq: 5000,
+            flags: TcpFlags::SYN,
+            at: 1000,
+            ..Default::default()
+        };
+        assert!(!send(&mut tracker, true, syn).has_problem());
+        assert!(!send(&mut tracker, true, data(5001, 1001)).has_problem());
+
+        let linger = FlowState::LINGER.as_millis() as u64;
+        send(&mut tracker, true, data(5101, 1001 + linger));
+        // the server's flow finished and stayed quiet
+        assert_eq!(tracker.flows.len(), 1);
+
+        let idle = FlowState::IDLE.as_millis() as u64;
+        let mut other = test_packets::tcp("10.0.0.1:40001", SERVER, data(1, 2001 + linger + idle));
+        tracker.analyze(&mut other);
+        assert_eq!(tracker.flows.len(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+//! Hand-built Ethernet frames for the unit tests, checksums are left at zero.
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use pnet::datalink::NetworkInterface;
+use pnet::ipnetwork::IpNetwork;
+use pnet::util::MacAddr;
+
+use super::parsed_packet::ParsedPacket;
+
+/// Address of the capturing host in the frames below.
+pub const HOST: &str = "10.0.0.1";
+pub const HOST_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 1);
+const PEER_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 2);
+
+pub fn interface() -> NetworkInterface {
+    NetworkInterface {
+        name: String::from("test0"),
+        description: String::new(),
+        index: 1,
+        mac: Some(HOST_MAC),
+        ips: vec![IpNetwork::new(HOST.parse().unwrap(), 24).unwrap()],
+        flags: 0,
+    }
+}
+
+/// A TCP segment, `Segment { seq: 1, ..Segment::default() }`.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub window: u16,
+    /// Raw option bytes, padded to 32 bits here.
+    pub options: Vec<u8>,
+    pub payload: Vec<u8>,
+    /// Capture time in milliseconds.
+    pub at: u64,
+}
+
+impl Default for Segment {
+    fn default() -> Self {
+        Self {
+            seq: 0,
+            ack: 0,
+            flags: pnet::packet::tcp::TcpFlags::ACK,
+            window: 1000,
+            options: vec![],
+            payload: vec![],
+            at: 0,
+        }
+    }
+}
+
+pub fn tcp(source: &str, destination: &str, segment: Segment) -> ParsedPacket {
+    let (source, destination) = addresses(source, destination);
+    let mut options = segment.options;
+    options.resize(options.len().next_multiple_of(4), 1);
+    let mut header = vec![];
+    header.extend(source.port().to_be_bytes());
+    header.extend(destination.port().to_be_bytes());
+    header.extend(segment.seq.to_be_bytes());
+    header.extend(segment.ack.to_be_bytes());
+    header.push((((20 + options.len()) / 4) << 4) as u8);
+    header.push(segment.flags);
+    header.extend(segment.window.to_be_bytes());
+    header.extend([0; 4]);
+    header.extend(options);
+    header.extend(segment.payload);
+    frame(source, destination, 6, header, segment.at)
+}
+
+pub fn udp(source: &str, destination: &str, payload: &[u8]) -> ParsedPacket {
+    let (source, destination) = addresses(source, destination);
+    let mut datagram = vec![];
+    datagram.extend(source.port().to_be_bytes());
+    datagram.extend(destination.port().to_be_bytes());
+    datagram.extend((8 + payload.len() as u16).to_be_bytes());
+    datagram.extend([0; 2]);
+    datagram.extend(payload);
+    frame(source, destination, 17, datagram, 0)
+}
+
+fn addresses(source: &str, destination: &str) -> (SocketAddr, SocketAddr) {
+    (source.parse().unwrap(), destination.parse().unwrap())
+}
+
+fn frame(
+    source: SocketAddr,
+    destination: SocketAddr,
+    protocol: u8,
+    transport: Vec<u8>,
+    at: u64,
+) -> ParsedPacket {
+    let mac = |ip: IpAddr| {
+        if ip.to_string() == HOST {
+            HOST_MAC
+        } else {
+            PEER_MAC
+        }
+    };
+    let mut data = vec![];
+    for address in [mac(destination.ip()), mac(source.ip())] {
+        data.extend(address.octets());
+    }
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            data.extend([0x08, 0x00, 0x45, 0]);
+            data.extend((20 + transport.len() as u16).to_be_bytes());
+            data.extend([0, 0, 0x40, 0, 64, protocol, 0, 0]);
+            data.extend(src.octets());
+            data.extend(dst.octets());
+        }
+        (IpAddr::V6(src), IpAddr::V6(dst)) => {
+            data.extend([0x86, 0xdd, 0x60, 0, 0, 0]);
+            data.extend((transport.len() as u16).to_be_bytes());
+            data.extend([protocol, 64]);
+            data.extend(src.octets());
+            data.extend(dst.octets());
+        }
+        _ => panic!("mixed address families"),
+    }
+    data.extend(transport);
+    ParsedPacket::parse(data, Duration::from_millis(at), &interface()).unwrap()
+}
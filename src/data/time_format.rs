@@ -0,0 +1,64 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::time_formatter::{format_local_time, format_seconds, format_utc_datetime};
+
+/// How the Timestamp column shows the capture time of a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimeFormat {
+    #[default]
+    LocalTime,
+    UtcDateTime,
+    SinceStart,
+    SincePrevious,
+    SinceReference,
+}
+
+/// Points in time the relative formats are measured from.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeOrigin {
+    /// First packet of the capture.
+    pub start: Duration,
+    /// Packet displayed or exported before, if any.
+    pub previous: Option<Duration>,
+    /// Packet chosen by the user, the capture start if none is set.
+    pub reference: Option<Duration>,
+}
+
+impl TimeFormat {
+    pub const ALL: [TimeFormat; 5] = [
+        TimeFormat::LocalTime,
+        TimeFormat::UtcDateTime,
+        TimeFormat::SinceStart,
+        TimeFormat::SincePrevious,
+        TimeFormat::SinceReference,
+    ];
+
+    pub fn format(&self, timestamp: Duration, origin: &TimeOrigin) -> String {
+        match self {
+            TimeFormat::LocalTime => format_local_time(timestamp),
+            TimeFormat::UtcDateTime => format_utc_datetime(timestamp),
+            TimeFormat::SinceStart => format_seconds(origin.start, timestamp),
+            TimeFormat::SincePrevious => {
+                format_seconds(origin.previous.unwrap_or(timestamp), timestamp)
+            }
+            TimeFormat::SinceReference => {
+                format_seconds(origin.reference.unwrap_or(origin.start), timestamp)
+            }
+        }
+    }
+}
+
+impl fmt::Display for TimeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeFormat::LocalTime => write!(f, "Local time"),
+            TimeFormat::UtcDateTime => write!(f, "UTC date and time"),
+            TimeFormat::SinceStart => write!(f, "Seconds since capture start"),
+            TimeFormat::SincePrevious => write!(f, "Seconds since previous packet"),
+            TimeFormat::SinceReference => write!(f, "Seconds since reference packet"),
+        }
+    }
+}
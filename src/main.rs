@@ -1,42 +1,110 @@
+#![allow(clippy::new_ret_no_self)]
+
+mod cli;
 mod components;
 mod data;
 mod utils;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+use clap::Parser;
+use cli::Args;
 use components::layout::Layout;
+use components::packet_list::PacketList;
+use data::coloring::{self as coloring, ColoringRule, RuleEdit};
+use data::column::{ColumnDrag, ColumnLayout, Field, SortOrder};
+use data::config::{Config, WindowSize};
+use data::connections::{ConnectionColumn, ConnectionTable};
+use data::direction::Direction;
+use data::display_filter::DisplayFilter;
+use data::endpoints::{EndpointColumn, EndpointKind, EndpointTable};
+use data::export::{self as packet_export, ExportFormat, Timestamps};
+use data::geoip;
+use data::host_names::{self, HostNames};
+use data::main_view::MainView;
+use data::packet_index::PacketIndex;
 use data::packet_subscription::PacketSubscription;
 use data::parsed_packet::ParsedPacket;
 use data::parsed_packet::TransportPacket;
+use data::port_filter::PortMatch;
 use data::postal_option::PostalOption;
-use iced::executor;
-use iced::widget::scrollable;
-use iced::Size;
+use data::protocol_hierarchy::ProtocolNode;
+use data::quic::QuicTracker;
+use data::quic_connections::{QuicColumn, QuicConnectionTable};
+use data::ring_buffer::{RingBuffer, RingBufferConfig, RingBufferField, SharedRingBufferStatus};
+use data::search::{Search, SearchMode};
+use data::shortcut::Shortcut;
+use data::tcp_analysis::TcpTracker;
+use data::time_format::{TimeFormat, TimeOrigin};
+use iced::keyboard;
+use iced::widget::{scrollable, text_input};
+use iced::{event, executor, mouse, window, Event, Point, Size};
 use iced::{Application, Command, Element, Settings, Subscription, Theme};
 use pnet::datalink;
-use pnet::datalink::NetworkInterface;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
-use once_cell::sync::Lazy;
 use crate::data::os_network_interface::OSNetworkInterface;
+use once_cell::sync::Lazy;
 
 static SCROLLABLE_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
+static SEARCH_INPUT_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
+static FILTER_INPUT_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
 static NETWORK_INTERFACES: Lazy<Vec<OSNetworkInterface>> = Lazy::new(|| {
     datalink::interfaces()
         .into_iter()
         .filter(|e| !e.ips.is_empty())
-        .map(|i| OSNetworkInterface::new(i))
+        .map(OSNetworkInterface::new)
         .collect()
 });
 
+/// The named interface, or the first one with an IPv4 address that isn't loopback.
+fn find_interface(name: Option<&str>) -> Option<&'static OSNetworkInterface> {
+    name.and_then(|name| {
+        NETWORK_INTERFACES
+            .iter()
+            .find(|i| i.get_identifier() == name)
+    })
+    .or_else(|| {
+        NETWORK_INTERFACES.iter().find(|i| {
+            i.interface
+                .ips
+                .iter()
+                .any(|ip| ip.is_ipv4() && !i.interface.is_loopback())
+        })
+    })
+}
+
 #[tokio::main]
 pub async fn main() -> iced::Result {
-    let mut settings = Settings::default();
-    settings.window.size = Size::new(1600.0, 900.0);
+    let args = Args::parse();
+    let mut config = Config::load();
+    if let Err(e) = args.apply(&mut config) {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    if let Some(path) = &args.export {
+        match args.export(&config, path) {
+            Ok(count) => {
+                println!("Exported {} packets to {}", count, path.display());
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut settings = Settings::with_flags((config.clone(), args));
+    settings.window.size = Size::new(config.window.width, config.window.height);
+    // the window size is saved before closing
+    settings.window.exit_on_close_request = false;
     Postal::run(settings)
 }
 
@@ -46,16 +114,82 @@ struct Postal {
     theme: Theme,
     total_captured: usize,
     total_mem: usize,
+    /// Frame number of the next packet, kept across clears so numbers never repeat.
+    next_frame_number: usize,
     packets: Vec<ParsedPacket>,
+    index: PacketIndex,
     options: HashMap<PostalOption, (bool, &'static str)>,
     tp_types: HashMap<TransportPacket, bool>,
+    directions: HashMap<Direction, bool>,
     port_input: String,
     port_list: Vec<u16>,
+    port_match: PortMatch,
     cache_input: String,
     cache_size: usize,
+    filter_input: String,
+    display_filter: Option<DisplayFilter>,
+    filter_error: Option<String>,
+    capture_filter_input: String,
+    capture_filter: Option<DisplayFilter>,
+    capture_filter_error: Option<String>,
+    status: Option<String>,
+    columns: ColumnLayout,
+    time_format: TimeFormat,
+    coloring_rules: Vec<ColoringRule>,
+    tcp_tracker: TcpTracker,
+    quic_tracker: QuicTracker,
+    /// Protocol tree of the packets in the store.
+    protocols: ProtocolNode,
+    /// Names of the addresses learned in the capture.
+    host_names: HostNames,
+    /// Capture time of the first packet since the last clear.
+    capture_start: Option<Duration>,
+    /// Frame number and capture time of the time reference packet.
+    time_reference: Option<(usize, Duration)>,
+    column_drag: Option<ColumnDrag>,
+    scroll_offset: f32,
+    /// Height of the packet list viewport as of the last scroll.
+    list_height: Option<f32>,
+    window_size: Size,
+    show_help: bool,
+    main_view: MainView,
+    /// Traffic per endpoint of the packets in the store.
+    endpoints: EndpointTable,
+    /// TCP connections of the packets in the store.
+    connections: ConnectionTable,
+    /// QUIC connections of the packets in the store.
+    quic_connections: QuicConnectionTable,
+    endpoint_sort: (EndpointColumn, SortOrder),
+    connection_sort: (ConnectionColumn, SortOrder),
+    failed_connections_only: bool,
+    quic_sort: (QuicColumn, SortOrder),
+    frozen: bool,
+    /// Number of matching packets captured while the view was frozen.
+    pending: usize,
+    /// Frame number of the selected packet.
+    selected: Option<usize>,
+    /// Detail pane sections the user folded away.
+    collapsed_details: HashSet<&'static str>,
+    goto_input: String,
+    search_open: bool,
+    search_input: String,
+    search_mode: SearchMode,
+    search_case_sensitive: bool,
+    search: Option<Search>,
+    search_error: Option<String>,
+    marked_only: bool,
+    /// Hides the packets the search does not match.
+    search_only_matches: bool,
+    ring_buffer: RingBufferConfig,
+    ring_buffer_inputs: HashMap<RingBufferField, String>,
+    disk_status: Option<SharedRingBufferStatus>,
+    /// GeoIP database path from the settings, opened in the background at start.
+    geoip: Option<PathBuf>,
+    export_format: ExportFormat,
     receiver: Option<Arc<Mutex<Receiver<ParsedPacket>>>>,
     cancellation_token: CancellationToken,
-    network_interface: OSNetworkInterface,
+    /// Interface to capture on, missing on machines without a usable one.
+    network_interface: Option<OSNetworkInterface>,
 }
 
 #[derive(Debug, Clone)]
@@ -67,46 +201,193 @@ pub enum Message {
     StopSniffing,
     OptionChanged(PostalOption, bool),
     FilterChanged(TransportPacket, bool),
+    DirectionFilterChanged(Direction, bool),
     Scrolled(scrollable::Viewport),
     NetworkInterfaceSelected(String),
     ClearCache,
-    RowClicked(Vec<u8>),
+    RowClicked(usize),
+    DetailToggled(&'static str),
+    GotoInputChanged(String),
+    GotoSubmitted,
+    ShortcutPressed(Shortcut),
+    MarkToggled(usize),
+    MarkedOnlyToggled(bool),
+    CommentChanged(String),
+    SearchOpened,
+    SearchClosed,
+    SearchInputChanged(String),
+    SearchModeSelected(SearchMode),
+    SearchCaseToggled(bool),
+    SearchOnlyMatchesToggled(bool),
+    SearchNext,
+    SearchPrevious,
+    TimeFormatSelected(TimeFormat),
+    TimeReferenceToggled,
+    ColoringRuleEdited(usize, RuleEdit),
+    ColoringRuleAdded,
+    ColoringRulesApplied,
+    FreezeToggled,
     PortInputChanged(String),
     PortFilterApplied,
+    PortMatchSelected(PortMatch),
     CacheInputChanged(String),
     CacheSizeApplied,
+    FilterInputChanged(String),
+    DisplayFilterApplied,
+    FilterSelected(String),
+    MainViewSelected(MainView),
+    EndpointKindSelected(EndpointKind),
+    EndpointSortToggled(EndpointColumn),
+    ConnectionSortToggled(ConnectionColumn),
+    FailedConnectionsToggled(bool),
+    QuicSortToggled(QuicColumn),
+    CaptureFilterInputChanged(String),
+    CaptureFilterApplied,
+    RingBufferInputChanged(RingBufferField, String),
+    RingBufferApplied(RingBufferField),
+    FileLoaded(Result<Vec<ParsedPacket>, String>),
+    NamesResolved(Vec<(IpAddr, Option<String>)>),
+    GeoIpLoaded(Vec<String>),
+    ExportFormatSelected(ExportFormat),
+    ExportRequested,
+    Exported(Result<PathBuf, String>),
+    ColumnToggled(Field, bool),
+    ColumnPressed(usize),
+    ColumnHovered(usize),
+    ColumnResizeStarted(usize),
+    CursorMoved(Point),
+    ColumnDragEnded,
+    WindowResized(Size),
+    CloseRequested(window::Id),
 }
 
 impl Application for Postal {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
-
-    fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
-        (
-            Self {
-                capturing: false,
-                theme: Theme::Light,
-                total_captured: 0,
-                total_mem: 0,
-                packets: Vec::with_capacity(1000),
-                options: PostalOption::as_map(),
-                tp_types: TransportPacket::as_map(),
-                port_input: String::new(),
-                port_list: vec![],
-                cache_input: String::from("200"),
-                cache_size: 200,
-                receiver: None,
-                cancellation_token: CancellationToken::new(),
-                network_interface: NETWORK_INTERFACES
-                    .iter()
-                    .find(|i| i.interface.ips.iter().any(|ip| ip.is_ipv4() && !i.interface.is_loopback()))
-                    .expect("No default network interface found.")
-                    .clone()
+    type Flags = (Config, Args);
+
+    fn new((config, args): Self::Flags) -> (Self, Command<Message>) {
+        let mut options = PostalOption::as_map();
+        for (option, (toggled, _)) in options.iter_mut() {
+            *toggled = config.options.get(option).copied().unwrap_or(*toggled);
+        }
+        let mut tp_types = TransportPacket::as_map();
+        for (tp_type, toggled) in tp_types.iter_mut() {
+            *toggled = config
+                .protocols
+                .get(&tp_type.to_string())
+                .copied()
+                .unwrap_or(*toggled);
+        }
+        let mut directions = Direction::as_map();
+        for (direction, toggled) in directions.iter_mut() {
+            *toggled = config
+                .directions
+                .get(direction)
+                .copied()
+                .unwrap_or(*toggled);
+        }
+        // filters were validated in main
+        let display_filter_input = args.display_filter.clone().unwrap_or_default();
+        let capture_filter_input = args.capture_filter.clone().unwrap_or_default();
+
+        let mut app = Self {
+            capturing: false,
+            theme: Theme::ALL
+                .iter()
+                .find(|theme| theme.to_string() == config.theme)
+                .cloned()
+                .unwrap_or(Theme::Light),
+            total_captured: 0,
+            total_mem: 0,
+            next_frame_number: 1,
+            packets: Vec::with_capacity(1000),
+            index: PacketIndex::default(),
+            options,
+            tp_types,
+            directions,
+            port_input: config
+                .ports
+                .iter()
+                .map(|port| port.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            port_list: config.ports,
+            port_match: config.port_match,
+            cache_input: config.view_limit.to_string(),
+            cache_size: config.view_limit,
+            display_filter: display_filter_input.parse().ok(),
+            filter_input: display_filter_input,
+            filter_error: None,
+            capture_filter: capture_filter_input.parse().ok(),
+            capture_filter_input,
+            capture_filter_error: None,
+            status: None,
+            columns: config.columns,
+            time_format: config.time_format,
+            coloring_rules: config.coloring_rules,
+            tcp_tracker: TcpTracker::default(),
+            quic_tracker: QuicTracker::default(),
+            protocols: ProtocolNode::default(),
+            host_names: HostNames::default(),
+            capture_start: None,
+            time_reference: None,
+            column_drag: None,
+            scroll_offset: 0.0,
+            list_height: None,
+            window_size: Size::new(config.window.width, config.window.height),
+            show_help: false,
+            main_view: MainView::default(),
+            endpoints: EndpointTable::default(),
+            connections: ConnectionTable::default(),
+            quic_connections: QuicConnectionTable::default(),
+            endpoint_sort: (EndpointColumn::Bytes, SortOrder::Descending),
+            connection_sort: (ConnectionColumn::Start, SortOrder::Ascending),
+            failed_connections_only: false,
+            quic_sort: (QuicColumn::Start, SortOrder::Ascending),
+            frozen: false,
+            pending: 0,
+            selected: None,
+            collapsed_details: HashSet::new(),
+            goto_input: String::new(),
+            search_open: false,
+            search_input: String::new(),
+            search_mode: SearchMode::default(),
+            search_case_sensitive: false,
+            search: None,
+            search_error: None,
+            search_only_matches: false,
+            marked_only: false,
+            ring_buffer_inputs: RingBufferField::ALL
+                .into_iter()
+                .map(|field| (field, config.ring_buffer.get(field)))
+                .collect(),
+            ring_buffer: config.ring_buffer,
+            disk_status: None,
+            geoip: config.geoip,
+            export_format: ExportFormat::default(),
+            receiver: None,
+            cancellation_token: CancellationToken::new(),
+            network_interface: find_interface(config.interface.as_deref()).cloned(),
+        };
+
+        let geoip = app.geoip.clone();
+        let mut commands = vec![Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || geoip::open(geoip.as_deref()))
+                    .await
+                    .unwrap_or_default()
             },
-            Command::none(),
-        )
+            Message::GeoIpLoaded,
+        )];
+        if let Some(path) = args.read {
+            commands.push(app.open_file(path));
+        }
+        if args.capture {
+            commands.push(app.update(Message::StartSniffing));
+        }
+        (app, Command::batch(commands))
     }
 
     fn title(&self) -> String {
@@ -117,6 +398,7 @@ impl Application for Postal {
         match message {
             Message::ThemeSelected(theme) => {
                 self.theme = theme;
+                self.save_config();
             }
             Message::PacketsReceived(mut packets) => {
                 return append_new_packets(self, &mut packets);
@@ -126,15 +408,39 @@ impl Application for Postal {
                 return append_new_packets(self, &mut packets);
             }
             Message::StartSniffing => {
+                let Some(ninf) = self.network_interface.clone() else {
+                    self.status = Some(String::from("No network interface to capture on"));
+                    return Command::none();
+                };
+                let ring_buffer = if self.options[&PostalOption::CaptureToDisk].0 {
+                    match RingBuffer::create(self.ring_buffer.clone()) {
+                        Ok(ring_buffer) => Some(ring_buffer),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            self.status = Some(e);
+                            return Command::none();
+                        }
+                    }
+                } else {
+                    None
+                };
+                self.disk_status = ring_buffer.as_ref().map(RingBuffer::status);
                 println!("Capturing..");
                 let (tx, rx) = mpsc::channel::<ParsedPacket>(1000);
                 self.receiver = Some(Arc::new(Mutex::new(rx)));
                 let token = CancellationToken::new();
                 self.cancellation_token = token.clone();
-                let ninf = self.network_interface.clone();
                 let http_only = self.options[&PostalOption::HttpOnly].0;
+                let capture_filter = self.capture_filter.clone();
                 tokio::task::spawn_blocking(move || {
-                    PacketSubscription::sniff(tx, ninf.interface, http_only, token)
+                    PacketSubscription::sniff(
+                        tx,
+                        ninf.interface,
+                        http_only,
+                        capture_filter,
+                        ring_buffer,
+                        token,
+                    )
                 });
                 self.capturing = true;
             }
@@ -148,53 +454,351 @@ impl Application for Postal {
                     .entry(opt)
                     .and_modify(|(toggled, _)| *toggled = b)
                     .or_default();
+                self.save_config();
+                if opt == PostalOption::ReverseLookups && b {
+                    return reverse_lookups(self.host_names.unrequested(&self.packets));
+                }
+            }
+            Message::Scrolled(viewport) => {
+                self.scroll_offset = viewport.absolute_offset().y;
+                self.list_height = Some(viewport.bounds().height);
             }
-            Message::Scrolled(_) => {}
             Message::NetworkInterfaceSelected(n) => {
+                let Some(interface) = NETWORK_INTERFACES.iter().find(|i| i.get_identifier() == n)
+                else {
+                    self.status = Some(format!("Unknown network interface '{}'", n));
+                    return Command::none();
+                };
                 self.cancellation_token.cancel();
-                self.network_interface = NETWORK_INTERFACES
-                    .iter()
-                    .find(|i| i.get_identifier() == &n)
-                    .expect("Network Interface not recognized")
-                    .clone();
-                self.packets.clear();
+                self.network_interface = Some(interface.clone());
+                self.clear_packets();
+                self.save_config();
             }
             Message::FilterChanged(f, b) => {
                 self.tp_types
                     .entry(f)
                     .and_modify(|toggled| *toggled = b)
                     .or_default();
+                self.refilter();
+                self.save_config();
+            }
+            Message::DirectionFilterChanged(d, b) => {
+                self.directions
+                    .entry(d)
+                    .and_modify(|toggled| *toggled = b)
+                    .or_default();
+                self.refilter();
+                self.save_config();
             }
             Message::ClearCache => {
                 self.total_mem = 0;
                 self.total_captured = 0;
-                self.packets.clear();
+                self.clear_packets();
+            }
+            Message::RowClicked(number) => {
+                self.selected = match self.selected {
+                    Some(selected) if selected == number => None,
+                    _ => Some(number),
+                };
+            }
+            Message::DetailToggled(id) => {
+                if !self.collapsed_details.remove(id) {
+                    self.collapsed_details.insert(id);
+                }
+            }
+            Message::GotoInputChanged(input) => self.goto_input = input,
+            Message::GotoSubmitted => {
+                let Ok(number) = self.goto_input.trim().parse::<usize>() else {
+                    self.status = Some(format!("'{}' is not a frame number", self.goto_input));
+                    return Command::none();
+                };
+                return self.go_to(number);
+            }
+            Message::ShortcutPressed(shortcut) => return self.shortcut(shortcut),
+            Message::MarkToggled(number) => {
+                if let Some(i) = self.find_packet(number) {
+                    self.packets[i].marked = !self.packets[i].marked;
+                }
+                if self.marked_only {
+                    self.refilter();
+                }
+            }
+            Message::MarkedOnlyToggled(marked_only) => {
+                self.marked_only = marked_only;
+                self.refilter();
+            }
+            Message::CommentChanged(comment) => {
+                if let Some(i) = self.selected.and_then(|number| self.find_packet(number)) {
+                    self.packets[i].comment = (!comment.is_empty()).then_some(comment);
+                }
+            }
+            Message::SearchOpened => {
+                self.search_open = true;
+                return text_input::focus(SEARCH_INPUT_ID.clone());
+            }
+            Message::SearchClosed => {
+                self.search_open = false;
+                self.search_input.clear();
+                self.update_search();
+            }
+            Message::SearchInputChanged(input) => {
+                self.search_input = input;
+                self.update_search();
+            }
+            Message::SearchModeSelected(mode) => {
+                self.search_mode = mode;
+                self.update_search();
+            }
+            Message::SearchCaseToggled(case_sensitive) => {
+                self.search_case_sensitive = case_sensitive;
+                self.update_search();
+            }
+            Message::SearchOnlyMatchesToggled(only_matches) => {
+                self.search_only_matches = only_matches;
+                self.refilter();
+            }
+            Message::SearchNext => return self.find_match(true),
+            Message::SearchPrevious => return self.find_match(false),
+            Message::TimeFormatSelected(format) => {
+                self.time_format = format;
+                self.save_config();
+            }
+            Message::ColoringRuleEdited(index, edit) => {
+                if coloring::edit(&mut self.coloring_rules, index, edit) {
+                    self.save_config();
+                }
+            }
+            Message::ColoringRulesApplied => self.save_config(),
+            Message::ColoringRuleAdded => {
+                self.coloring_rules
+                    .push(ColoringRule::new("New rule", "", "#000000", "#ffffff"));
+                self.save_config();
+            }
+            Message::TimeReferenceToggled => {
+                self.time_reference = match (self.time_reference, self.selected) {
+                    (Some((reference, _)), Some(selected)) if reference == selected => None,
+                    (_, Some(selected)) => self
+                        .packets
+                        .iter()
+                        .find(|p| p.number == selected)
+                        .map(|p| (p.number, p.timestamp)),
+                    (reference, None) => reference,
+                };
+            }
+            Message::FreezeToggled => {
+                if self.is_frozen() {
+                    // resuming drops everything that keeps the view frozen
+                    self.frozen = false;
+                    self.selected = None;
+                    self.scroll_offset = 0.0;
+                    self.release_pending();
+                    return scrollable::scroll_to(
+                        SCROLLABLE_ID.clone(),
+                        scrollable::AbsoluteOffset { x: 0.0, y: 0.0 },
+                    );
+                }
+                self.frozen = true;
             }
-            Message::RowClicked(payload) => println!("{:?}", String::from_utf8(payload)),
             Message::PortInputChanged(ports) => self.port_input = ports,
             Message::PortFilterApplied => {
                 self.port_list = self
                     .port_input
-                    .split(",")
-                    .filter_map(|port| {
-                        let trimmed = port.trim();
-                        match trimmed.parse::<u16>() {
-                            Ok(val) => Some(val),
-                            Err(_) => None,
-                        }
-                    })
+                    .split(',')
+                    .filter_map(|port| port.trim().parse::<u16>().ok())
                     .collect::<Vec<u16>>();
+                self.refilter();
+                self.save_config();
+            }
+            Message::PortMatchSelected(port_match) => {
+                self.port_match = port_match;
+                self.refilter();
+                self.save_config();
             }
             Message::CacheInputChanged(size) => self.cache_input = size,
             Message::CacheSizeApplied => {
-                self.cache_size = self.cache_input.parse::<usize>().unwrap_or(1000)
+                self.cache_size = self.cache_input.parse::<usize>().unwrap_or(1000);
+                self.index
+                    .resort(&self.packets, self.cache_size, &self.columns);
+                self.save_config();
+            }
+            Message::FilterInputChanged(input) => self.filter_input = input,
+            Message::DisplayFilterApplied => match parse_filter(&self.filter_input) {
+                Ok(filter) => {
+                    self.display_filter = filter;
+                    self.filter_error = None;
+                    self.refilter();
+                }
+                Err(e) => self.filter_error = Some(e),
+            },
+            Message::FilterSelected(filter) => {
+                self.filter_input = filter;
+                self.main_view = MainView::Packets;
+                return self.update(Message::DisplayFilterApplied);
+            }
+            Message::MainViewSelected(view) => self.main_view = view,
+            Message::EndpointKindSelected(kind) => {
+                self.endpoints = EndpointTable::new(kind);
+                for packet in &self.packets {
+                    self.endpoints.add(packet);
+                }
+            }
+            Message::EndpointSortToggled(column) => {
+                self.endpoint_sort = match self.endpoint_sort {
+                    (sorted, SortOrder::Descending) if sorted == column => {
+                        (column, SortOrder::Ascending)
+                    }
+                    (sorted, SortOrder::Ascending) if sorted == column => {
+                        (column, SortOrder::Descending)
+                    }
+                    // addresses read best in order, everything else as a ranking
+                    _ if matches!(
+                        column,
+                        EndpointColumn::Address
+                            | EndpointColumn::Location
+                            | EndpointColumn::AutonomousSystem
+                    ) =>
+                    {
+                        (column, SortOrder::Ascending)
+                    }
+                    _ => (column, SortOrder::Descending),
+                }
+            }
+            Message::ConnectionSortToggled(column) => {
+                self.connection_sort = match self.connection_sort {
+                    (sorted, SortOrder::Descending) if sorted == column => {
+                        (column, SortOrder::Ascending)
+                    }
+                    (sorted, SortOrder::Ascending) if sorted == column => {
+                        (column, SortOrder::Descending)
+                    }
+                    _ if column.ascending_first() => (column, SortOrder::Ascending),
+                    _ => (column, SortOrder::Descending),
+                }
+            }
+            Message::FailedConnectionsToggled(only) => self.failed_connections_only = only,
+            Message::QuicSortToggled(column) => {
+                self.quic_sort = match self.quic_sort {
+                    (sorted, SortOrder::Descending) if sorted == column => {
+                        (column, SortOrder::Ascending)
+                    }
+                    (sorted, SortOrder::Ascending) if sorted == column => {
+                        (column, SortOrder::Descending)
+                    }
+                    _ if column.ascending_first() => (column, SortOrder::Ascending),
+                    _ => (column, SortOrder::Descending),
+                }
+            }
+            Message::CaptureFilterInputChanged(input) => self.capture_filter_input = input,
+            Message::CaptureFilterApplied => match parse_filter(&self.capture_filter_input) {
+                Ok(filter) => {
+                    self.capture_filter = filter;
+                    self.capture_filter_error = None;
+                }
+                Err(e) => self.capture_filter_error = Some(e),
+            },
+            Message::RingBufferInputChanged(field, input) => {
+                self.ring_buffer_inputs.insert(field, input);
+            }
+            Message::RingBufferApplied(field) => {
+                match self
+                    .ring_buffer
+                    .set(field, &self.ring_buffer_inputs[&field])
+                {
+                    Ok(()) => {
+                        self.status = None;
+                        self.save_config();
+                    }
+                    Err(e) => self.status = Some(e),
+                }
+            }
+            Message::FileLoaded(Ok(mut packets)) => {
+                self.status = Some(format!("Loaded {} packets", packets.len()));
+                // the file replaces what was shown, names of other captures included
+                self.clear_packets();
+                return append_new_packets(self, &mut packets);
+            }
+            Message::NamesResolved(names) => {
+                self.host_names.insert(names);
+                if self
+                    .display_filter
+                    .as_ref()
+                    .is_some_and(DisplayFilter::uses_host_names)
+                {
+                    self.refilter();
+                }
+            }
+            Message::GeoIpLoaded(report) => {
+                if !report.is_empty() {
+                    self.status = Some(report.join("; "));
+                }
+            }
+            Message::FileLoaded(Err(e)) => {
+                eprintln!("{}", e);
+                self.status = Some(e);
+            }
+            Message::ExportFormatSelected(format) => self.export_format = format,
+            Message::ExportRequested => return self.export(),
+            Message::Exported(Ok(path)) => {
+                self.status = Some(format!("Exported to {}", path.display()));
+            }
+            Message::Exported(Err(e)) => {
+                eprintln!("{}", e);
+                self.status = Some(e);
+            }
+            Message::ColumnToggled(field, visible) => {
+                self.columns.set_visible(field, visible);
+                self.save_config();
+            }
+            Message::ColumnPressed(index) => {
+                self.column_drag = Some(ColumnDrag::Move {
+                    from: index,
+                    to: index,
+                })
+            }
+            Message::ColumnHovered(index) => {
+                if let Some(ColumnDrag::Move { to, .. }) = &mut self.column_drag {
+                    *to = index;
+                }
+            }
+            Message::ColumnResizeStarted(index) => {
+                self.column_drag = Some(ColumnDrag::Resize {
+                    index,
+                    last_x: None,
+                })
+            }
+            Message::CursorMoved(position) => {
+                if let Some(ColumnDrag::Resize { index, last_x }) = &mut self.column_drag {
+                    if let Some(x) = last_x {
+                        self.columns.resize(*index, position.x - *x);
+                    }
+                    *last_x = Some(position.x);
+                }
+            }
+            Message::ColumnDragEnded => {
+                match self.column_drag.take() {
+                    // a press and release on the same header is a click
+                    Some(ColumnDrag::Move { from, to }) if from == to => {
+                        self.columns.toggle_sort(self.columns.columns[from].field);
+                        self.index
+                            .resort(&self.packets, self.cache_size, &self.columns);
+                    }
+                    Some(ColumnDrag::Move { from, to }) => self.columns.move_column(from, to),
+                    Some(ColumnDrag::Resize { .. }) | None => {}
+                }
+                self.save_config();
+            }
+            Message::WindowResized(size) => self.window_size = size,
+            Message::CloseRequested(id) => {
+                self.save_config();
+                return window::close(id);
             }
         }
 
+        self.release_pending();
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         Layout::view(self)
     }
 
@@ -203,14 +807,482 @@ impl Application for Postal {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        if self.capturing {
+        let capture = if self.capturing {
             iced_futures::Subscription::from_recipe(PacketSubscription::new(
                 self.receiver.as_ref().unwrap().clone(),
                 self.cancellation_token.clone(),
             ))
         } else {
             Subscription::none()
+        };
+        let column_drag = if self.column_drag.is_some() {
+            event::listen_with(column_drag_events)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([
+            capture,
+            column_drag,
+            event::listen_with(window_events),
+            event::listen_with(keyboard_events),
+        ])
+    }
+}
+
+impl Postal {
+    fn save_config(&self) {
+        Config {
+            theme: self.theme.to_string(),
+            interface: self
+                .network_interface
+                .as_ref()
+                .map(|i| i.get_identifier().to_string()),
+            options: self
+                .options
+                .iter()
+                .map(|(option, (toggled, _))| (*option, *toggled))
+                .collect(),
+            protocols: self
+                .tp_types
+                .iter()
+                .map(|(tp_type, toggled)| (tp_type.to_string(), *toggled))
+                .collect(),
+            directions: self.directions.clone(),
+            ports: self.port_list.clone(),
+            port_match: self.port_match,
+            view_limit: self.cache_size,
+            columns: self.columns.clone(),
+            time_format: self.time_format,
+            coloring_rules: self.coloring_rules.clone(),
+            window: WindowSize {
+                width: self.window_size.width,
+                height: self.window_size.height,
+            },
+            ring_buffer: self.ring_buffer.clone(),
+            geoip: self.geoip.clone(),
+            ..Config::default()
         }
+        .save();
+    }
+
+    fn matches_filters(&self, packet: &ParsedPacket) -> bool {
+        self.tp_types[&packet.transport]
+            && self.directions[&packet.direction]
+            && self.port_match.matches(packet, &self.port_list)
+            && self
+                .display_filter
+                .as_ref()
+                .is_none_or(|f| f.matches(packet, &self.host_names))
+            && (!self.marked_only || packet.marked)
+            && (!self.search_only_matches || self.search.as_ref().is_none_or(|s| s.matches(packet)))
+    }
+
+    fn open_file(&self, path: PathBuf) -> Command<Message> {
+        let interface = self
+            .network_interface
+            .as_ref()
+            .map_or_else(OSNetworkInterface::detached, |i| i.interface.clone());
+        Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    PacketSubscription::read_file(&path, &interface)
+                })
+                .await
+                .map_err(|e| e.to_string())?
+            },
+            Message::FileLoaded,
+        )
+    }
+
+    /// Store index of the packet with the given frame number.
+    fn find_packet(&self, number: usize) -> Option<usize> {
+        self.packets
+            .binary_search_by_key(&number, |p| p.number)
+            .ok()
+    }
+
+    fn selected_packet(&self) -> Option<&ParsedPacket> {
+        self.selected
+            .and_then(|number| self.find_packet(number))
+            .map(|i| &self.packets[i])
+    }
+
+    /// Selects a packet and scrolls its row to the top of the list.
+    fn go_to(&mut self, number: usize) -> Command<Message> {
+        let Some(index) = self.find_packet(number) else {
+            self.status = Some(format!("There is no packet {}", number));
+            return Command::none();
+        };
+        self.selected = Some(number);
+        let Some(row) = self.index.row_of(index, self.cache_size, &self.columns) else {
+            self.status = Some(format!("Packet {} is hidden by the filters", number));
+            return Command::none();
+        };
+        self.status = None;
+        self.scroll_offset = row as f32 * PacketList::ROW_HEIGHT;
+        scrollable::scroll_to(
+            SCROLLABLE_ID.clone(),
+            scrollable::AbsoluteOffset {
+                x: 0.0,
+                y: self.scroll_offset,
+            },
+        )
+    }
+
+    fn shortcut(&mut self, shortcut: Shortcut) -> Command<Message> {
+        let page = (self.list_height.unwrap_or(self.window_size.height / 2.0)
+            / PacketList::ROW_HEIGHT) as isize;
+        match shortcut {
+            Shortcut::ToggleCapture if self.capturing => return self.update(Message::StopSniffing),
+            Shortcut::ToggleCapture => return self.update(Message::StartSniffing),
+            Shortcut::Clear => return self.update(Message::ClearCache),
+            Shortcut::FocusFilter => return text_input::focus(FILTER_INPUT_ID.clone()),
+            Shortcut::Find => return self.update(Message::SearchOpened),
+            Shortcut::FindNext => return self.find_match(true),
+            Shortcut::FindPrevious => return self.find_match(false),
+            Shortcut::SelectPrevious => return self.move_selection(-1),
+            Shortcut::SelectNext => return self.move_selection(1),
+            Shortcut::PageUp => return self.move_selection(-page.max(1)),
+            Shortcut::PageDown => return self.move_selection(page.max(1)),
+            Shortcut::SelectFirst => return self.move_selection(isize::MIN),
+            Shortcut::SelectLast => return self.move_selection(isize::MAX),
+            Shortcut::ToggleMark => {
+                if let Some(number) = self.selected {
+                    return self.update(Message::MarkToggled(number));
+                }
+            }
+            Shortcut::NextMarked => return self.find_marked(true),
+            Shortcut::PreviousMarked => return self.find_marked(false),
+            Shortcut::ExpandDetails => self.collapsed_details.clear(),
+            Shortcut::CollapseDetails => {
+                let ids = self
+                    .selected_packet()
+                    .map(|packet| {
+                        let sections = packet.details(&self.host_names).into_iter();
+                        sections.map(|section| section.id)
+                    })
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                self.collapsed_details.extend(ids);
+            }
+            Shortcut::Help => self.show_help = !self.show_help,
+            Shortcut::Dismiss if self.show_help => self.show_help = false,
+            Shortcut::Dismiss if self.search_open => return self.update(Message::SearchClosed),
+            Shortcut::Dismiss => {}
+        }
+        Command::none()
+    }
+
+    /// Moves the selection by `rows` through the displayed packets, keeping it in view.
+    /// Without a selection the first row is selected.
+    fn move_selection(&mut self, rows: isize) -> Command<Message> {
+        let total = self.index.len(self.cache_size);
+        if total == 0 {
+            return Command::none();
+        }
+        let row = match self
+            .selected
+            .and_then(|number| self.find_packet(number))
+            .and_then(|index| self.index.row_of(index, self.cache_size, &self.columns))
+        {
+            Some(row) => row.saturating_add_signed(rows).min(total - 1),
+            None if rows == isize::MAX => total - 1,
+            None => 0,
+        };
+        let Some(index) = self.index.row(row, self.cache_size, &self.columns) else {
+            return Command::none();
+        };
+        self.selected = Some(self.packets[index].number);
+
+        let top = row as f32 * PacketList::ROW_HEIGHT;
+        let height = self.list_height.unwrap_or(self.window_size.height / 2.0);
+        self.scroll_offset = if top < self.scroll_offset {
+            top
+        } else if top + PacketList::ROW_HEIGHT > self.scroll_offset + height {
+            top + PacketList::ROW_HEIGHT - height
+        } else {
+            return Command::none();
+        };
+        scrollable::scroll_to(
+            SCROLLABLE_ID.clone(),
+            scrollable::AbsoluteOffset {
+                x: 0.0,
+                y: self.scroll_offset,
+            },
+        )
+    }
+
+    /// Recompiles the search after its input or options changed.
+    fn update_search(&mut self) {
+        (self.search, self.search_error) = if self.search_input.is_empty() {
+            (None, None)
+        } else {
+            match Search::new(
+                &self.search_input,
+                self.search_mode,
+                self.search_case_sensitive,
+            ) {
+                Ok(search) => (Some(search), None),
+                Err(e) => (None, Some(e)),
+            }
+        };
+        if self.search_only_matches {
+            self.refilter();
+        }
+    }
+
+    fn find_match(&mut self, forward: bool) -> Command<Message> {
+        let Some(search) = &self.search else {
+            return Command::none();
+        };
+        match self.next_row_matching(forward, |packet| search.matches(packet)) {
+            Some(number) => self.go_to(number),
+            None => {
+                self.status = Some(format!("No packet matches '{}'", self.search_input));
+                Command::none()
+            }
+        }
+    }
+
+    fn find_marked(&mut self, forward: bool) -> Command<Message> {
+        match self.next_row_matching(forward, |packet| packet.marked) {
+            Some(number) => self.go_to(number),
+            None => {
+                self.status = Some(String::from("No packet is marked"));
+                Command::none()
+            }
+        }
+    }
+
+    /// Frame number of the next displayed packet below or above the selection that
+    /// satisfies `predicate`, wrapping around at the end of the list.
+    fn next_row_matching(
+        &self,
+        forward: bool,
+        predicate: impl Fn(&ParsedPacket) -> bool,
+    ) -> Option<usize> {
+        let total = self.index.len(self.cache_size) as isize;
+        let current = self
+            .selected
+            .and_then(|number| self.find_packet(number))
+            .and_then(|index| self.index.row_of(index, self.cache_size, &self.columns))
+            .map(|row| row as isize)
+            .unwrap_or(if forward { -1 } else { total });
+        let step = if forward { 1 } else { -1 };
+        (1..=total)
+            .map(|k| (current + step * k).rem_euclid(total) as usize)
+            .filter_map(|row| self.index.row(row, self.cache_size, &self.columns))
+            .find(|&i| predicate(&self.packets[i]))
+            .map(|i| self.packets[i].number)
+    }
+
+    /// Text of the Timestamp column for the packet shown in `row`.
+    /// The previous packet is the one displayed before it: the row above when sorted by a
+    /// column, the row below in the newest first capture order.
+    fn time_text(&self, row: usize) -> String {
+        let Some(index) = self.index.row(row, self.cache_size, &self.columns) else {
+            return String::new();
+        };
+        let packet = &self.packets[index];
+        if self
+            .time_reference
+            .is_some_and(|(number, _)| number == packet.number)
+        {
+            return String::from("*REF*");
+        }
+        let previous_row = match self.columns.sort {
+            Some(_) => row.checked_sub(1),
+            None => Some(row + 1),
+        };
+        let origin = TimeOrigin {
+            start: self.capture_start.unwrap_or(packet.timestamp),
+            previous: previous_row
+                .and_then(|row| self.index.row(row, self.cache_size, &self.columns))
+                .map(|previous| self.packets[previous].timestamp),
+            reference: self.time_reference.map(|(_, timestamp)| timestamp),
+        };
+        self.time_format.format(packet.timestamp, &origin)
+    }
+
+    /// Every packet in the store passing the filters, not only those within the view limit.
+    /// They are in capture order unless a sort column is set, packets still pending while
+    /// the view is frozen come last.
+    fn matching_packets(&self) -> Vec<&ParsedPacket> {
+        let pending = self.packets[self.index.indexed()..]
+            .iter()
+            .filter(|p| self.matches_filters(p));
+        self.index
+            .all(&self.packets, &self.columns)
+            .into_iter()
+            .map(|i| &self.packets[i])
+            .chain(pending)
+            .collect()
+    }
+
+    /// Writes the packets matching the filters into the download directory.
+    fn export(&self) -> Command<Message> {
+        let mut content = vec![];
+        if let Err(e) = packet_export::write(
+            &mut content,
+            self.export_format,
+            self.matching_packets(),
+            &self.columns,
+            Timestamps {
+                format: self.time_format,
+                origin: TimeOrigin {
+                    start: self.capture_start.unwrap_or_default(),
+                    previous: None,
+                    reference: self.time_reference.map(|(_, timestamp)| timestamp),
+                },
+            },
+        ) {
+            return Command::perform(async move { Err(e.to_string()) }, Message::Exported);
+        }
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_default()
+            .join(format!(
+                "postal_export_{}.{}",
+                secs,
+                self.export_format.extension()
+            ));
+        Command::perform(
+            async move {
+                tokio::fs::write(&path, content)
+                    .await
+                    .map(|_| path.clone())
+                    .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+            },
+            Message::Exported,
+        )
+    }
+
+    /// Re-evaluates the display filters against the capture.
+    /// Packets that arrived while the view is frozen stay pending.
+    fn refilter(&mut self) {
+        let indexed = self.index.indexed();
+        let mut index = std::mem::take(&mut self.index);
+        index.rebuild(
+            &self.packets[..indexed],
+            |p| self.matches_filters(p),
+            self.cache_size,
+            &self.columns,
+        );
+        self.index = index;
+        self.pending = self.packets[indexed..]
+            .iter()
+            .filter(|p| self.matches_filters(p))
+            .count();
+        self.release_pending();
+    }
+
+    /// The visible list stops changing while the user is looking at something specific.
+    fn is_frozen(&self) -> bool {
+        self.frozen || self.selected.is_some() || self.scroll_offset > 0.0
+    }
+
+    /// Drops the stored packets and everything learned from them.
+    fn clear_packets(&mut self) {
+        self.packets.clear();
+        self.index.clear();
+        self.pending = 0;
+        self.selected = None;
+        self.capture_start = None;
+        self.time_reference = None;
+        self.tcp_tracker.clear();
+        self.quic_tracker.clear();
+        self.protocols = ProtocolNode::default();
+        self.endpoints = EndpointTable::new(self.endpoints.kind());
+        self.connections = ConnectionTable::default();
+        self.quic_connections = QuicConnectionTable::default();
+        self.host_names.clear();
+    }
+
+    /// While writing to disk only the most recent packets are kept in memory.
+    /// The store is trimmed in chunks, so the index is shifted rarely.
+    fn trim_store(&mut self) {
+        if self.disk_status.is_none() {
+            return;
+        }
+        let excess = self.packets.len().saturating_sub(self.cache_size);
+        if excess > self.cache_size.max(1000) {
+            // pending packets are only trimmed after the view stayed frozen for long
+            if excess > self.index.indexed() {
+                self.pending -= self.packets[self.index.indexed()..excess]
+                    .iter()
+                    .filter(|p| self.matches_filters(p))
+                    .count();
+            }
+            for packet in self.packets.drain(..excess) {
+                self.protocols.remove(&packet);
+                self.endpoints.remove(&packet);
+                self.connections.remove(&packet);
+                self.quic_connections.remove(&packet);
+            }
+            self.index.forget(excess);
+        }
+    }
+
+    fn release_pending(&mut self) {
+        if self.is_frozen() {
+            return;
+        }
+        let mut index = std::mem::take(&mut self.index);
+        index.catch_up(
+            &self.packets,
+            |p| self.matches_filters(p),
+            self.cache_size,
+            &self.columns,
+        );
+        self.index = index;
+        self.pending = 0;
+    }
+}
+
+/// An empty filter input clears the filter.
+fn parse_filter(input: &str) -> Result<Option<DisplayFilter>, String> {
+    if input.trim().is_empty() {
+        Ok(None)
+    } else {
+        input.parse().map(Some)
+    }
+}
+
+fn window_events(event: Event, _status: event::Status) -> Option<Message> {
+    match event {
+        Event::Window(_, window::Event::Resized { width, height }) => Some(Message::WindowResized(
+            Size::new(width as f32, height as f32),
+        )),
+        Event::Window(id, window::Event::CloseRequested) => Some(Message::CloseRequested(id)),
+        _ => None,
+    }
+}
+
+fn keyboard_events(event: Event, status: event::Status) -> Option<Message> {
+    let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    let shortcut = Shortcut::from_key(key.as_ref(), modifiers)?;
+    if shortcut.is_navigation() && status == event::Status::Captured {
+        return None;
+    }
+    Some(Message::ShortcutPressed(shortcut))
+}
+
+fn column_drag_events(event: Event, _status: event::Status) -> Option<Message> {
+    match event {
+        Event::Mouse(mouse::Event::CursorMoved { position }) => {
+            Some(Message::CursorMoved(position))
+        }
+        Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+            Some(Message::ColumnDragEnded)
+        }
+        _ => None,
     }
 }
 
@@ -218,10 +1290,37 @@ fn append_new_packets(
     app: &mut Postal,
     new_packets: &mut Vec<ParsedPacket>,
 ) -> iced::Command<Message> {
+    for (i, packet) in new_packets.iter_mut().enumerate() {
+        packet.number = app.next_frame_number + i;
+        app.tcp_tracker.analyze(packet);
+        app.quic_tracker.analyze(packet);
+        app.host_names.learn(packet);
+        app.protocols.add(packet);
+        app.endpoints.add(packet);
+        app.connections.add(packet);
+        app.quic_connections.add(packet);
+    }
+    let lookups = if app.options[&PostalOption::ReverseLookups].0 {
+        reverse_lookups(app.host_names.unrequested(new_packets.iter()))
+    } else {
+        Command::none()
+    };
+    if app.capture_start.is_none() {
+        app.capture_start = new_packets.first().map(|p| p.timestamp);
+    }
+    if app.is_frozen() {
+        app.pending += new_packets
+            .iter()
+            .filter(|p| app.matches_filters(p))
+            .count();
+    }
     app.total_captured += new_packets.len();
+    app.next_frame_number += new_packets.len();
     app.total_mem += new_packets.iter().fold(0, |acc, p| acc + p.data.len());
     app.packets.append(new_packets);
-    Command::none()
+    app.release_pending();
+    app.trim_store();
+    lookups
     // See comment in postal_option.rs
     // if app.options[&PostalOption::Autoscroll].0 {
     //     return scrollable::snap_to(SCROLLABLE_ID.clone(), scrollable::RelativeOffset::END);
@@ -229,3 +1328,11 @@ fn append_new_packets(
     //     Command::none()
     // }
 }
+
+/// Resolves the names of the addresses in the background, if there are any.
+fn reverse_lookups(ips: Vec<IpAddr>) -> Command<Message> {
+    if ips.is_empty() {
+        return Command::none();
+    }
+    Command::perform(host_names::reverse_lookup(ips), Message::NamesResolved)
+}
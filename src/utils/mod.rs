@@ -1 +1,2 @@
 pub mod byte_formatter;
+pub mod time_formatter;
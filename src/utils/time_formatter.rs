@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+/// Civil date (year, month, day) of a day count since 1970-01-01,
+/// see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Offset of the local timezone to UTC in seconds at the given time.
+#[cfg(unix)]
+pub fn utc_offset(since_epoch: Duration) -> i64 {
+    let secs = since_epoch.as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        0
+    } else {
+        tm.tm_gmtoff as i64
+    }
+}
+
+#[cfg(not(unix))]
+pub fn utc_offset(_since_epoch: Duration) -> i64 {
+    0
+}
+
+fn time_of_day(secs: u64, micros: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:06}",
+        (secs / 3600) % 24,
+        (secs / 60) % 60,
+        secs % 60,
+        micros
+    )
+}
+
+/// Local wall clock time, e.g. `14:30:00.000042`.
+pub fn format_local_time(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs() as i64 + utc_offset(since_epoch);
+    time_of_day(secs.max(0) as u64, since_epoch.subsec_micros())
+}
+
+/// UTC date and time, e.g. `2024-05-01 12:30:00.000042`.
+pub fn format_utc_datetime(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!(
+        "{:04}-{:02}-{:02} {}",
+        year,
+        month,
+        day,
+        time_of_day(secs, since_epoch.subsec_micros())
+    )
+}
+
+/// Signed difference in seconds, e.g. `-0.250000`.
+pub fn format_seconds(from: Duration, to: Duration) -> String {
+    if to >= from {
+        format!("{:.6}", (to - from).as_secs_f64())
+    } else {
+        format!("-{:.6}", (from - to).as_secs_f64())
+    }
+}
+
+/// UTC time in RFC 3339 format with microseconds, e.g. `2024-05-01T12:30:00.000042Z`.
+pub fn format_rfc3339(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        (secs / 3600) % 24,
+        (secs / 60) % 60,
+        secs % 60,
+        since_epoch.subsec_micros()
+    )
+}